@@ -0,0 +1,63 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use fastgeotoolkit::*;
+use std::collections::HashMap;
+
+// city sized random walks, roughly the shape of a large personal heatmap
+fn random_tracks(count: usize, points: usize) -> Vec<Vec<[f64; 2]>> {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % 10_000) as f64 / 10_000.0 - 0.5
+    };
+
+    (0..count)
+        .map(|_| {
+            let mut point = [40.7 + next() * 0.1, -74.0 + next() * 0.1];
+            (0..points)
+                .map(|_| {
+                    point = [point[0] + next() * 0.002, point[1] + next() * 0.002];
+                    point
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn count_segments<K: std::hash::Hash + Eq>(
+    tracks: &[Vec<[f64; 2]>],
+    key: impl Fn([f64; 2], [f64; 2]) -> K,
+) -> usize {
+    let mut usage: HashMap<K, u32> = HashMap::new();
+    for track in tracks {
+        for window in track.windows(2) {
+            *usage.entry(key(window[0], window[1])).or_insert(0) += 1;
+        }
+    }
+    usage.len()
+}
+
+fn segment_keys(c: &mut Criterion) {
+    let tracks = random_tracks(2_000, 200);
+
+    let mut group = c.benchmark_group("segment_counting");
+    group.sample_size(20);
+    group.bench_function("string_keys", |b| {
+        b.iter(|| count_segments(black_box(&tracks), create_segment_key))
+    });
+    group.bench_function("packed_ids", |b| {
+        b.iter(|| count_segments(black_box(&tracks), |a, b| segment_id(a, b, 0.001)))
+    });
+    group.finish();
+
+    c.bench_function("create_heatmap_from_tracks", |b| {
+        b.iter(|| create_heatmap_from_tracks(black_box(tracks.clone())))
+    });
+    c.bench_function("find_track_intersections", |b| {
+        b.iter(|| find_track_intersections_rust(black_box(&tracks), 0.001))
+    });
+}
+
+criterion_group!(benches, segment_keys);
+criterion_main!(benches);
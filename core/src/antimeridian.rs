@@ -0,0 +1,122 @@
+// handling of tracks and boxes across the ±180 meridian. a bbox [min_lat, min_lon, max_lat,
+// max_lon] with min_lon > max_lon is read the RFC 7946 way, as running east from min_lon over
+// the antimeridian to max_lon
+
+// wraps any longitude into [-180, 180), keeping 180 itself as is
+pub fn normalize_longitude(lon: f64) -> f64 {
+    if (-180.0..=180.0).contains(&lon) {
+        lon
+    } else {
+        (lon + 180.0).rem_euclid(360.0) - 180.0
+    }
+}
+
+pub fn bbox_crosses_antimeridian(bbox: [f64; 4]) -> bool {
+    bbox[1] > bbox[3]
+}
+
+// degrees of longitude covered going east from min_lon to max_lon
+pub fn bbox_longitude_span(bbox: [f64; 4]) -> f64 {
+    let [_, min_lon, _, max_lon] = bbox;
+    if min_lon <= max_lon {
+        max_lon - min_lon
+    } else {
+        max_lon - min_lon + 360.0
+    }
+}
+
+pub fn bbox_contains(bbox: [f64; 4], [lat, lon]: [f64; 2]) -> bool {
+    let [min_lat, min_lon, max_lat, max_lon] = bbox;
+    let in_lon = if bbox_crosses_antimeridian(bbox) {
+        lon >= min_lon || lon <= max_lon
+    } else {
+        lon >= min_lon && lon <= max_lon
+    };
+    lat >= min_lat && lat <= max_lat && in_lon
+}
+
+// the one or two plain boxes either side of the antimeridian that make up bbox
+pub fn split_bbox_at_antimeridian(bbox: [f64; 4]) -> Vec<[f64; 4]> {
+    let [min_lat, min_lon, max_lat, max_lon] = bbox;
+    if bbox_crosses_antimeridian(bbox) {
+        vec![
+            [min_lat, min_lon, max_lat, 180.0],
+            [min_lat, -180.0, max_lat, max_lon],
+        ]
+    } else {
+        vec![bbox]
+    }
+}
+
+// smallest bbox around the points. longitudes are treated as a circle: the box leaves out the
+// widest gap between them, which for a pacific crossing is the rest of the world rather than
+// the few degrees over the antimeridian
+pub(crate) fn points_bounding_box(points: &[[f64; 2]]) -> [f64; 4] {
+    let mut min_lat = f64::MAX;
+    let mut max_lat = f64::MIN;
+    let mut lons = Vec::with_capacity(points.len());
+    for &[lat, lon] in points {
+        min_lat = min_lat.min(lat);
+        max_lat = max_lat.max(lat);
+        lons.push(normalize_longitude(lon));
+    }
+    if lons.is_empty() {
+        return [min_lat, f64::MAX, max_lat, f64::MIN];
+    }
+    lons.sort_by(f64::total_cmp);
+
+    let (first, last) = (lons[0], lons[lons.len() - 1]);
+    // the gap around the back of the globe, from the easternmost back to the westernmost
+    let mut widest = 360.0 - (last - first);
+    let (mut min_lon, mut max_lon) = (first, last);
+    for pair in lons.windows(2) {
+        let gap = pair[1] - pair[0];
+        if gap > widest {
+            widest = gap;
+            (min_lon, max_lon) = (pair[1], pair[0]);
+        }
+    }
+    [min_lat, min_lon, max_lat, max_lon]
+}
+
+// cuts a track into parts wherever a step between two points crosses ±180 (the step is taken
+// the short way, so 179.9 to -179.9 is 0.2 degrees east). the cut points are interpolated onto
+// the meridian and end one part at 180 and start the next at -180 or the other way around, as
+// RFC 7946 asks of LineStrings. points stay [lat, lon]
+pub fn split_at_antimeridian(coords: &[[f64; 2]]) -> Vec<Vec<[f64; 2]>> {
+    let mut parts = Vec::new();
+    let mut current: Vec<[f64; 2]> = Vec::new();
+
+    for &[lat, lon] in coords {
+        let lon = normalize_longitude(lon);
+        if let Some(&[prev_lat, prev_lon]) = current.last() {
+            let delta = lon - prev_lon;
+            if delta.abs() > 180.0 {
+                // the meridian the previous point runs into, and the step unwrapped past it
+                let edge = if delta < 0.0 { 180.0 } else { -180.0 };
+                let unwrapped = lon + 2.0 * edge;
+                let t = (edge - prev_lon) / (unwrapped - prev_lon);
+                let cross_lat = prev_lat + t * (lat - prev_lat);
+
+                if prev_lon != edge {
+                    current.push([cross_lat, edge]);
+                }
+                // a part that is nothing but a point on the meridian carries on over there
+                if current.len() > 1 {
+                    parts.push(std::mem::take(&mut current));
+                } else {
+                    current.clear();
+                }
+                if lon != -edge {
+                    current.push([cross_lat, -edge]);
+                }
+            }
+        }
+        current.push([lat, lon]);
+    }
+
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
@@ -0,0 +1,474 @@
+use crate::csv::split_csv_records;
+use crate::{
+    days_from_civil, parse_file, parse_timestamp_ms, Error, FileFormat, TrackPoint,
+    MAX_DECOMPRESSED_SIZE, MS_PER_DAY,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+// Garmin exports wrap the uploaded files in zips inside the export zip
+const MAX_ARCHIVE_DEPTH: usize = 3;
+
+// what parse_file can read, before any .gz
+const ACTIVITY_EXTENSIONS: &[&str] = &["gpx", "fit", "igc", "nmea", "nma", "csv", "json"];
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ExportActivity {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub activity_type: Option<String>,
+    pub date: Option<String>,       // as written in activities.csv
+    pub start_time_ms: Option<i64>, // from the csv date, else the first timestamped point
+    pub file: String,               // path inside the archive
+    pub format: Option<FileFormat>,
+    pub tracks: Vec<Vec<TrackPoint>>,
+    pub warnings: Vec<String>,
+}
+
+impl ExportActivity {
+    pub fn coordinates(&self) -> Vec<Vec<[f64; 2]>> {
+        self.tracks
+            .iter()
+            .map(|track| track.iter().map(|p| [p.lat, p.lon]).collect())
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ExportArchive {
+    pub activities: Vec<ExportActivity>,
+    pub warnings: Vec<String>,
+}
+
+impl ExportArchive {
+    // every track of every activity, in the shape create_heatmap_from_tracks expects
+    pub fn heatmap_tracks(&self) -> Vec<Vec<[f64; 2]>> {
+        self.activities
+            .iter()
+            .flat_map(ExportActivity::coordinates)
+            .filter(|track| track.len() >= 2)
+            .collect()
+    }
+}
+
+pub fn is_zip_file(data: &[u8]) -> bool {
+    data.starts_with(b"PK\x03\x04") || data.starts_with(b"PK\x05\x06")
+}
+
+// caps on what one export may decompress to. a file over max_file_size or an archive tree
+// (nested zips included) over max_total_size fails the whole archive instead of being cut off
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArchiveLimits {
+    pub max_file_size: u64,
+    pub max_total_size: u64,
+}
+
+impl Default for ArchiveLimits {
+    fn default() -> Self {
+        Self {
+            max_file_size: MAX_DECOMPRESSED_SIZE as u64,
+            max_total_size: 4 * MAX_DECOMPRESSED_SIZE as u64,
+        }
+    }
+}
+
+pub fn parse_export_archive(data: &[u8]) -> Result<ExportArchive, Error> {
+    parse_export_archive_with_limits(data, ArchiveLimits::default())
+}
+
+pub fn parse_export_archive_with_limits(
+    data: &[u8],
+    limits: ArchiveLimits,
+) -> Result<ExportArchive, Error> {
+    let archive = ZipArchive::new(Cursor::new(data)).map_err(|e| Error::Archive(e.to_string()))?;
+    let mut collector = Collector {
+        limits,
+        read: 0,
+        files: HashMap::new(),
+        referenced: HashSet::new(),
+        warnings: Vec::new(),
+    };
+    collector.collect(archive, "", 0)?;
+    let Collector {
+        files,
+        mut warnings,
+        ..
+    } = collector;
+
+    let manifest = files
+        .keys()
+        .filter(|path| is_manifest(path))
+        .min_by_key(|path| path.len())
+        .cloned();
+
+    let mut archive = match manifest {
+        Some(manifest) => activities_from_manifest(&files, &manifest),
+        None => activities_from_files(&files),
+    };
+
+    warnings.append(&mut archive.warnings);
+    archive.warnings = warnings;
+    Ok(archive)
+}
+
+// flattens the archive into path -> bytes, descending into nested zips. only manifests, zips,
+// track files and the files a manifest names are read, photos and the like never leave the zip
+struct Collector {
+    limits: ArchiveLimits,
+    read: u64,
+    files: HashMap<String, Vec<u8>>,
+    // lowercase file names listed in the manifests read so far
+    referenced: HashSet<String>,
+    warnings: Vec<String>,
+}
+
+impl Collector {
+    fn collect(
+        &mut self,
+        mut archive: ZipArchive<Cursor<&[u8]>>,
+        prefix: &str,
+        depth: usize,
+    ) -> Result<(), Error> {
+        // manifests first, so the files they name are known before the rest is looked at
+        let mut order: Vec<usize> = (0..archive.len()).collect();
+        let manifests: HashSet<usize> = order
+            .iter()
+            .copied()
+            .filter(|&index| {
+                archive
+                    .by_index_raw(index)
+                    .is_ok_and(|entry| is_manifest(entry.name()))
+            })
+            .collect();
+        order.sort_by_key(|index| !manifests.contains(index));
+
+        for index in order {
+            let mut entry = match archive.by_index(index) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.warnings
+                        .push(format!("skipped unreadable archive entry {index}: {e}"));
+                    continue;
+                }
+            };
+            if entry.is_dir() {
+                continue;
+            }
+
+            let path = format!("{prefix}{}", entry.name());
+            let nested = has_extension(&path, &["zip"]);
+            if !nested && !is_manifest(&path) && !self.wanted(&path) {
+                continue;
+            }
+
+            if entry.size() > self.limits.max_file_size {
+                return Err(Error::Archive(format!(
+                    "{path} decompresses to more than {} bytes",
+                    self.limits.max_file_size
+                )));
+            }
+            let bytes = self.read_entry(&mut entry, &path)?;
+
+            if nested && is_zip_file(&bytes) && depth + 1 < MAX_ARCHIVE_DEPTH {
+                match ZipArchive::new(Cursor::new(bytes.as_slice())) {
+                    Ok(inner) => self.collect(inner, &format!("{path}/"), depth + 1)?,
+                    Err(e) => self.warnings.push(format!("skipped {path}: {e}")),
+                }
+                continue;
+            }
+
+            if is_manifest(&path) {
+                self.referenced.extend(manifest_files(&bytes));
+            }
+            self.files.insert(path, bytes);
+        }
+
+        Ok(())
+    }
+
+    fn wanted(&self, path: &str) -> bool {
+        let name = file_name(path).to_ascii_lowercase();
+        if self.referenced.contains(&name) {
+            return true;
+        }
+        has_extension(
+            name.strip_suffix(".gz").unwrap_or(&name),
+            ACTIVITY_EXTENSIONS,
+        )
+    }
+
+    fn read_entry(&mut self, entry: &mut impl Read, path: &str) -> Result<Vec<u8>, Error> {
+        let remaining = self.limits.max_total_size.saturating_sub(self.read);
+        let limit = self.limits.max_file_size.min(remaining);
+
+        // one byte past the limit tells a file that fits exactly from one that doesn't
+        let mut bytes = Vec::new();
+        entry
+            .take(limit.saturating_add(1))
+            .read_to_end(&mut bytes)
+            .map_err(|e| Error::Archive(format!("{path}: {e}")))?;
+        if bytes.len() as u64 > limit {
+            return Err(Error::Archive(if limit < self.limits.max_file_size {
+                format!(
+                    "archive decompresses to more than {} bytes",
+                    self.limits.max_total_size
+                )
+            } else {
+                format!(
+                    "{path} decompresses to more than {} bytes",
+                    self.limits.max_file_size
+                )
+            }));
+        }
+
+        self.read += bytes.len() as u64;
+        Ok(bytes)
+    }
+}
+
+// file names from the Filename column of an activities.csv
+fn manifest_files(data: &[u8]) -> Vec<String> {
+    let text = String::from_utf8_lossy(data);
+    let records = split_csv_records(text.trim_start_matches('\u{feff}'), ',');
+    let (header, rows) = match records.split_first() {
+        Some(split) => split,
+        None => return Vec::new(),
+    };
+    let column = ["Filename", "File"].iter().find_map(|name| {
+        header
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name))
+    });
+
+    rows.iter()
+        .filter_map(|record| record.get(column?))
+        .map(|file| file_name(file.trim()).to_ascii_lowercase())
+        .filter(|file| !file.is_empty())
+        .collect()
+}
+
+fn is_manifest(path: &str) -> bool {
+    file_name(path).eq_ignore_ascii_case("activities.csv")
+}
+
+fn has_extension(path: &str, extensions: &[&str]) -> bool {
+    match file_name(path).rsplit_once('.') {
+        Some((_, extension)) => extensions.iter().any(|e| extension.eq_ignore_ascii_case(e)),
+        None => false,
+    }
+}
+
+fn activities_from_manifest(files: &HashMap<String, Vec<u8>>, manifest: &str) -> ExportArchive {
+    let text = String::from_utf8_lossy(&files[manifest]);
+    let records = split_csv_records(text.trim_start_matches('\u{feff}'), ',');
+    let mut archive = ExportArchive::default();
+
+    let (header, rows) = match records.split_first() {
+        Some(split) => split,
+        None => {
+            archive.warnings.push("activities.csv is empty".to_string());
+            return archive;
+        }
+    };
+
+    // columns follow the Strava "Download your data" export, which repeats
+    // some headers (Distance, Elapsed Time), the first one wins
+    let column = |names: &[&str]| {
+        names.iter().find_map(|name| {
+            header
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(name))
+        })
+    };
+    let id_column = column(&["Activity ID"]);
+    let name_column = column(&["Activity Name", "Name"]);
+    let type_column = column(&["Activity Type", "Type"]);
+    let date_column = column(&["Activity Date", "Date"]);
+    let file_column = column(&["Filename", "File"]);
+
+    // paths in the csv are relative to the folder activities.csv lives in
+    let base = &manifest[..manifest.len() - file_name(manifest).len()];
+    let mut without_file = 0;
+
+    for record in rows {
+        let field = |index: Option<usize>| {
+            index
+                .and_then(|i| record.get(i))
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        // manual entries have no recorded file
+        let file = match field(file_column) {
+            Some(file) => file,
+            None => {
+                without_file += 1;
+                continue;
+            }
+        };
+
+        let date = field(date_column);
+        let mut activity = ExportActivity {
+            id: field(id_column),
+            name: field(name_column),
+            activity_type: field(type_column),
+            start_time_ms: date.as_deref().and_then(parse_export_date),
+            date,
+            file: file.clone(),
+            format: None,
+            tracks: Vec::new(),
+            warnings: Vec::new(),
+        };
+
+        match resolve_file(files, base, &file) {
+            Some(path) => {
+                activity.file = path.clone();
+                load_activity_file(&mut activity, &files[path]);
+            }
+            None => activity
+                .warnings
+                .push(format!("{file} was not found in the archive")),
+        }
+
+        archive.activities.push(activity);
+    }
+
+    if without_file > 0 {
+        archive
+            .warnings
+            .push(format!("{without_file} activities have no recorded file"));
+    }
+
+    archive
+}
+
+// without a manifest (Garmin exports) every readable track file becomes an activity
+fn activities_from_files(files: &HashMap<String, Vec<u8>>) -> ExportArchive {
+    let mut paths: Vec<&String> = files.keys().collect();
+    paths.sort();
+
+    let activities = paths
+        .into_iter()
+        .filter_map(|path| {
+            let mut activity = ExportActivity {
+                id: None,
+                name: Some(activity_name(path)),
+                activity_type: None,
+                date: None,
+                start_time_ms: None,
+                file: path.clone(),
+                format: None,
+                tracks: Vec::new(),
+                warnings: Vec::new(),
+            };
+            load_activity_file(&mut activity, &files[path]);
+            activity.format.map(|_| activity)
+        })
+        .collect();
+
+    ExportArchive {
+        activities,
+        warnings: Vec::new(),
+    }
+}
+
+fn load_activity_file(activity: &mut ExportActivity, data: &[u8]) {
+    match parse_file(data) {
+        Ok(parsed) => {
+            activity.format = Some(parsed.format);
+            activity.start_time_ms = activity.start_time_ms.or(parsed.start_time_ms());
+            activity.tracks = parsed.tracks;
+            activity.warnings.extend(parsed.warnings);
+        }
+        Err(Error::UnknownFormat) => {}
+        Err(e) => activity.warnings.push(e.to_string()),
+    }
+}
+
+// exact path first, then the same file anywhere in the archive (exports are often re-zipped
+// inside an extra top level folder)
+fn resolve_file<'a>(
+    files: &'a HashMap<String, Vec<u8>>,
+    base: &str,
+    file: &str,
+) -> Option<&'a String> {
+    let file = file.trim_start_matches("./").replace('\\', "/");
+    let candidates = [format!("{base}{file}"), file.clone()];
+
+    candidates
+        .iter()
+        .find_map(|candidate| files.get_key_value(candidate).map(|(path, _)| path))
+        .or_else(|| {
+            let suffix = format!("/{file}");
+            files
+                .keys()
+                .filter(|path| path.ends_with(&suffix))
+                .min_by_key(|path| path.len())
+        })
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn activity_name(path: &str) -> String {
+    let name = file_name(path);
+    let name = name.strip_suffix(".gz").unwrap_or(name);
+    match name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem.to_string(),
+        _ => name.to_string(),
+    }
+}
+
+// "Mar 31, 2024, 11:59:58 PM" in UTC as written by Strava, ISO 8601 otherwise
+pub fn parse_export_date(value: &str) -> Option<i64> {
+    if let Some(ms) = parse_timestamp_ms(value) {
+        return Some(ms);
+    }
+
+    let parts: Vec<&str> = value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .collect();
+    if parts.len() < 3 {
+        return None;
+    }
+
+    let month_name = parts[0].get(..3)?.to_ascii_lowercase();
+    let month = MONTHS.iter().position(|m| *m == month_name)? as u32 + 1;
+    let day: u32 = parts[1].parse().ok()?;
+    let year: i32 = parts[2].parse().ok()?;
+    if !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let mut seconds = 0;
+    if let Some(time) = parts.get(3) {
+        let fields: Vec<u32> = time
+            .split(':')
+            .map(|field| field.parse().ok())
+            .collect::<Option<_>>()?;
+        let (mut hours, minutes, secs) = match fields[..] {
+            [h, m] => (h, m, 0),
+            [h, m, s] => (h, m, s),
+            _ => return None,
+        };
+        match parts.get(4).map(|meridiem| meridiem.to_ascii_uppercase()) {
+            Some(m) if m == "AM" && hours == 12 => hours = 0,
+            Some(m) if m == "PM" && hours < 12 => hours += 12,
+            _ => {}
+        }
+        if hours > 23 || minutes > 59 || secs > 60 {
+            return None;
+        }
+        seconds = (hours * 3600 + minutes * 60 + secs) as i64;
+    }
+
+    Some(days_from_civil(year as i64, month, day) * MS_PER_DAY + seconds * 1000)
+}
@@ -0,0 +1,370 @@
+// area actually covered by tracks: every track is widened into a corridor, the corridors are
+// unioned on a fine lat/lon grid and the outline of the covered cells is traced into polygons.
+// the area comes from those polygons on the sphere, so two commutes on opposite sides of a city
+// count as two strips of road rather than the whole city between them
+use crate::antimeridian::points_bounding_box;
+use crate::{calculate_distance_between_points, normalize_longitude};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, HashSet};
+
+const METERS_PER_DEGREE: f64 = 111_320.0;
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct CoverageOptions {
+    // full width of the corridor, half of it on either side of a track
+    pub width_m: f64,
+    // grid the corridors are unioned on, smaller traces a smoother outline
+    pub cell_size_m: f64,
+    // cells are made larger when the corridors would cover more than this
+    pub max_cells: usize,
+}
+
+impl Default for CoverageOptions {
+    fn default() -> Self {
+        Self {
+            width_m: 50.0,
+            cell_size_m: 5.0,
+            max_cells: 4_000_000,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Coverage {
+    // polygons of closed [lat, lon] rings, the outline counter clockwise first and then its
+    // holes clockwise. polygons are cut at the antimeridian
+    pub polygons: Vec<Vec<Vec<[f64; 2]>>>,
+    pub area_km2: f64,
+    // [min_lat, min_lon, max_lat, max_lon] of the track points, min_lon > max_lon across ±180
+    pub bbox: [f64; 4],
+    pub point_count: usize,
+    pub width_m: f64,
+    // the grid actually used, larger than asked for when max_cells kicked in
+    pub cell_size_m: f64,
+}
+
+impl Coverage {
+    // a Feature with a MultiPolygon, the area and corridor width as properties
+    pub fn to_geojson(&self) -> serde_json::Value {
+        // geojson uses [lng, lat] order
+        let polygons: Vec<Vec<Vec<[f64; 2]>>> = self
+            .polygons
+            .iter()
+            .map(|rings| {
+                rings
+                    .iter()
+                    .map(|ring| ring.iter().map(|&[lat, lon]| [lon, lat]).collect())
+                    .collect()
+            })
+            .collect();
+
+        json!({
+            "type": "Feature",
+            "geometry": {
+                "type": "MultiPolygon",
+                "coordinates": polygons
+            },
+            "properties": {
+                "area_km2": self.area_km2,
+                "point_count": self.point_count,
+                "width_m": self.width_m
+            }
+        })
+    }
+}
+
+// None when there are no points at all
+pub fn track_coverage(tracks: &[Vec<[f64; 2]>], options: &CoverageOptions) -> Option<Coverage> {
+    let points: Vec<[f64; 2]> = tracks.iter().flatten().copied().collect();
+    if points.is_empty() {
+        return None;
+    }
+    let half_width = options.width_m.max(0.0) / 2.0;
+
+    // rough count of covered cells to keep the grid within max_cells
+    let mut covered_m2 = 0.0;
+    for track in tracks.iter().filter(|track| !track.is_empty()) {
+        covered_m2 += std::f64::consts::PI * half_width * half_width;
+        for pair in track.windows(2) {
+            let length_km =
+                calculate_distance_between_points(pair[0][0], pair[0][1], pair[1][0], pair[1][1]);
+            covered_m2 += length_km * 1000.0 * 2.0 * half_width;
+        }
+    }
+    let mut cell = options.cell_size_m.max(0.01);
+    let max_cells = options.max_cells.max(1) as f64;
+    if covered_m2 / (cell * cell) > max_cells {
+        cell = (covered_m2 / max_cells).sqrt();
+    }
+
+    // whole numbers of rows and columns around the globe, the columns roughly square at the
+    // tracks' mean latitude
+    let mean_lat = points.iter().map(|p| p[0]).sum::<f64>() / points.len() as f64;
+    let height = cell / METERS_PER_DEGREE;
+    let rows = ((180.0 / height).round() as i64).max(1);
+    let height = 180.0 / rows as f64;
+    let width = height / mean_lat.to_radians().cos().max(0.01);
+    let columns = ((360.0 / width).round() as i64).max(1);
+    let width = 360.0 / columns as f64;
+    let grid = Grid {
+        rows,
+        columns,
+        height,
+        width,
+    };
+
+    let mut cells = HashSet::new();
+    for track in tracks {
+        let mut previous: Option<[f64; 2]> = None;
+        for &[lat, lon] in track {
+            let lon = normalize_longitude(lon);
+            let start = previous.unwrap_or([lat, lon]);
+            // the short way round, which may take the end past ±180
+            let mut end_lon = lon;
+            if end_lon - start[1] > 180.0 {
+                end_lon -= 360.0;
+            } else if end_lon - start[1] < -180.0 {
+                end_lon += 360.0;
+            }
+            grid.cover_segment(start, [lat, end_lon], half_width, &mut cells);
+            previous = Some([lat, lon]);
+        }
+    }
+
+    let polygons = trace_polygons(&cells);
+    let area_km2 = polygons
+        .iter()
+        .flatten()
+        .map(|ring| grid.ring_area_km2(ring))
+        .sum::<f64>()
+        .max(0.0);
+    let polygons = polygons
+        .iter()
+        .map(|rings| {
+            rings
+                .iter()
+                .map(|ring| ring.iter().map(|&vertex| grid.vertex(vertex)).collect())
+                .collect()
+        })
+        .collect();
+
+    Some(Coverage {
+        polygons,
+        area_km2,
+        bbox: points_bounding_box(&points),
+        point_count: points.len(),
+        width_m: half_width * 2.0,
+        cell_size_m: cell,
+    })
+}
+
+// rows count north from -90, columns east from -180. a cell is (row, column)
+struct Grid {
+    rows: i64,
+    columns: i64,
+    height: f64,
+    width: f64,
+}
+
+impl Grid {
+    // marks every cell whose center is within half_width of the segment, measured on a local
+    // flat approximation around the cell
+    fn cover_segment(
+        &self,
+        start: [f64; 2],
+        end: [f64; 2],
+        half_width: f64,
+        cells: &mut HashSet<(i64, i64)>,
+    ) {
+        let reach_lat = half_width / METERS_PER_DEGREE;
+        let min_lat = start[0].min(end[0]) - reach_lat;
+        let max_lat = start[0].max(end[0]) + reach_lat;
+        let widest = min_lat.abs().max(max_lat.abs()).min(89.9);
+        let reach_lon = half_width / (METERS_PER_DEGREE * widest.to_radians().cos());
+
+        let first_row = (((min_lat + 90.0) / self.height).floor() as i64).max(0);
+        let last_row = (((max_lat + 90.0) / self.height).floor() as i64).min(self.rows - 1);
+        let column_at = |lon: f64| ((lon + 180.0) / self.width).floor() as i64;
+
+        for row in first_row..=last_row {
+            let lat = -90.0 + (row as f64 + 0.5) * self.height;
+            let meters_lon = METERS_PER_DEGREE * lat.to_radians().cos();
+
+            // only the stretch of the segment within reach of this row can cover its cells
+            let dlat = end[0] - start[0];
+            let (mut t0, mut t1) = if dlat.abs() > f64::EPSILON {
+                (
+                    (lat - reach_lat - start[0]) / dlat,
+                    (lat + reach_lat - start[0]) / dlat,
+                )
+            } else {
+                (0.0, 1.0)
+            };
+            if t0 > t1 {
+                (t0, t1) = (t1, t0);
+            }
+            let lon0 = start[1] + t0.clamp(0.0, 1.0) * (end[1] - start[1]);
+            let lon1 = start[1] + t1.clamp(0.0, 1.0) * (end[1] - start[1]);
+
+            for column in
+                column_at(lon0.min(lon1) - reach_lon)..=column_at(lon0.max(lon1) + reach_lon)
+            {
+                let lon = -180.0 + (column as f64 + 0.5) * self.width;
+                let a = [
+                    (start[1] - lon) * meters_lon,
+                    (start[0] - lat) * METERS_PER_DEGREE,
+                ];
+                let b = [
+                    (end[1] - lon) * meters_lon,
+                    (end[0] - lat) * METERS_PER_DEGREE,
+                ];
+                if distance_to_origin(a, b) <= half_width {
+                    cells.insert((row, column.rem_euclid(self.columns)));
+                }
+            }
+        }
+    }
+
+    fn vertex(&self, (x, y): (i64, i64)) -> [f64; 2] {
+        [
+            (-90.0 + y as f64 * self.height).clamp(-90.0, 90.0),
+            (-180.0 + x as f64 * self.width).clamp(-180.0, 180.0),
+        ]
+    }
+
+    // signed, positive for counter clockwise rings. the rings only run along parallels and
+    // meridians, for which sum((lon2 - lon1) * (sin lat1 + sin lat2)) / 2 is the exact area
+    // on the sphere
+    fn ring_area_km2(&self, ring: &[(i64, i64)]) -> f64 {
+        let mut sum = 0.0;
+        for pair in ring.windows(2) {
+            let [lat1, lon1] = self.vertex(pair[0]);
+            let [lat2, lon2] = self.vertex(pair[1]);
+            sum += (lon2 - lon1).to_radians() * (lat1.to_radians().sin() + lat2.to_radians().sin());
+        }
+        -sum / 2.0 * EARTH_RADIUS_KM * EARTH_RADIUS_KM
+    }
+}
+
+fn distance_to_origin(a: [f64; 2], b: [f64; 2]) -> f64 {
+    let d = [b[0] - a[0], b[1] - a[1]];
+    let length_squared = d[0] * d[0] + d[1] * d[1];
+    let t = if length_squared > 0.0 {
+        (-(a[0] * d[0] + a[1] * d[1]) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (a[0] + t * d[0]).hypot(a[1] + t * d[1])
+}
+
+// east, north, west, south steps in (x, y) = (column, row) grid corners
+const STEPS: [(i64, i64); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+// outlines of the covered cells as closed rings of grid corners, grouped into polygons of an
+// outline followed by its holes. every cell side between a covered and an uncovered cell is an
+// edge with the covered cell on its left, so outlines run counter clockwise and holes clockwise.
+// cells touching only at a corner end up in separate rings
+fn trace_polygons(cells: &HashSet<(i64, i64)>) -> Vec<Vec<Vec<(i64, i64)>>> {
+    // outgoing edge directions per corner, a corner where two cells meet diagonally has two
+    let mut edges: BTreeMap<(i64, i64), Vec<usize>> = BTreeMap::new();
+    for &(row, column) in cells {
+        let (x, y) = (column, row);
+        let sides = [
+            ((row - 1, column), (x, y), 0),
+            ((row, column + 1), (x + 1, y), 1),
+            ((row + 1, column), (x + 1, y + 1), 2),
+            ((row, column - 1), (x, y + 1), 3),
+        ];
+        for (neighbor, corner, direction) in sides {
+            if !cells.contains(&neighbor) {
+                edges.entry(corner).or_default().push(direction);
+            }
+        }
+    }
+
+    let mut used = HashSet::new();
+    let mut outlines: Vec<(Vec<(i64, i64)>, f64)> = Vec::new();
+    let mut holes = Vec::new();
+    for (&start, directions) in &edges {
+        for &start_direction in directions {
+            if used.contains(&(start, start_direction)) {
+                continue;
+            }
+
+            let mut ring = vec![start];
+            let (mut corner, mut direction) = (start, start_direction);
+            loop {
+                used.insert((corner, direction));
+                let next = (corner.0 + STEPS[direction].0, corner.1 + STEPS[direction].1);
+                let outgoing = &edges[&next];
+                // at a diagonal corner keep turning left, around the same cell
+                let next_direction = if outgoing.len() > 1 {
+                    (direction + 1) % 4
+                } else {
+                    outgoing[0]
+                };
+                if next_direction != direction {
+                    ring.push(next);
+                }
+                (corner, direction) = (next, next_direction);
+                if (corner, direction) == (start, start_direction) {
+                    break;
+                }
+            }
+            if ring.last() != Some(&start) {
+                ring.push(start);
+            }
+
+            let area = shoelace(&ring);
+            if area > 0.0 {
+                outlines.push((ring, area));
+            } else {
+                // the covered cell to the left of the first edge, some outline holds it
+                let (dx, dy) =
+                    [(0.5, 0.5), (-0.5, 0.5), (-0.5, -0.5), (0.5, -0.5)][start_direction];
+                holes.push((ring, [start.0 as f64 + dx, start.1 as f64 + dy]));
+            }
+        }
+    }
+
+    let mut polygons: Vec<Vec<Vec<(i64, i64)>>> = outlines
+        .iter()
+        .map(|(ring, _)| vec![ring.clone()])
+        .collect();
+    for (hole, inside) in holes {
+        // the innermost outline around it, for islands inside other holes
+        let owner = outlines
+            .iter()
+            .enumerate()
+            .filter(|(_, (ring, _))| contains(ring, inside))
+            .min_by(|a, b| a.1 .1.total_cmp(&b.1 .1));
+        if let Some((index, _)) = owner {
+            polygons[index].push(hole);
+        }
+    }
+    polygons
+}
+
+fn shoelace(ring: &[(i64, i64)]) -> f64 {
+    ring.windows(2)
+        .map(|pair| (pair[0].0 * pair[1].1 - pair[1].0 * pair[0].1) as f64)
+        .sum::<f64>()
+        / 2.0
+}
+
+fn contains(ring: &[(i64, i64)], [x, y]: [f64; 2]) -> bool {
+    let mut inside = false;
+    for pair in ring.windows(2) {
+        let (a, b) = (
+            (pair[0].0 as f64, pair[0].1 as f64),
+            (pair[1].0 as f64, pair[1].1 as f64),
+        );
+        if (a.1 > y) != (b.1 > y) && x < a.0 + (y - a.1) / (b.1 - a.1) * (b.0 - a.0) {
+            inside = !inside;
+        }
+    }
+    inside
+}
@@ -0,0 +1,316 @@
+use crate::{format_timestamp_ms, is_valid_coordinate, parse_timestamp_ms, TrackPoint};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// header names recognised when a column is not configured explicitly,
+// compared after lowercasing and dropping everything that isn't alphanumeric
+const LAT_HEADERS: &[&str] = &[
+    "lat",
+    "latitude",
+    "latdeg",
+    "gpslat",
+    "gpslatitude",
+    "positionlat",
+];
+const LON_HEADERS: &[&str] = &[
+    "lon",
+    "lng",
+    "long",
+    "longitude",
+    "londeg",
+    "gpslon",
+    "gpslng",
+    "gpslongitude",
+    "positionlong",
+];
+const TIME_HEADERS: &[&str] = &["time", "timestamp", "datetime", "date", "utc", "gpstime"];
+const ELEVATION_HEADERS: &[&str] = &["ele", "elevation", "elev", "alt", "altitude", "height"];
+const TRACK_ID_HEADERS: &[&str] = &["trackid", "track", "activityid", "segmentid", "tripid"];
+
+const DELIMITER_CANDIDATES: [char; 4] = [',', ';', '\t', '|'];
+
+// every field is optional, anything left unset is detected from the header row
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct CsvOptions {
+    pub delimiter: Option<char>,
+    pub decimal_comma: Option<bool>,
+    pub lat_column: Option<String>,
+    pub lon_column: Option<String>,
+    pub time_column: Option<String>,
+    pub elevation_column: Option<String>,
+    pub track_id_column: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CsvTrack {
+    pub id: Option<String>,
+    pub points: Vec<TrackPoint>,
+}
+
+impl CsvTrack {
+    pub fn coordinates(&self) -> Vec<[f64; 2]> {
+        self.points
+            .iter()
+            .map(|point| [point.lat, point.lon])
+            .collect()
+    }
+}
+
+// resolved column indices for one file
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsvColumnMapping {
+    pub delimiter: char,
+    pub decimal_comma: bool,
+    pub lat: usize,
+    pub lon: usize,
+    pub time: Option<usize>,
+    pub elevation: Option<usize>,
+    pub track_id: Option<usize>,
+}
+
+pub fn detect_csv_mapping(data: &[u8], options: &CsvOptions) -> Result<CsvColumnMapping, String> {
+    let text = String::from_utf8_lossy(data);
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+
+    let header_line = lines.next().ok_or("csv file is empty")?;
+    let header_line = header_line.trim_start_matches('\u{feff}');
+    let delimiter = options
+        .delimiter
+        .unwrap_or_else(|| detect_delimiter(header_line));
+    let headers = split_csv_line(header_line, delimiter);
+
+    let lat = resolve_column(&headers, options.lat_column.as_deref(), LAT_HEADERS)?
+        .ok_or("no latitude column found")?;
+    let lon = resolve_column(&headers, options.lon_column.as_deref(), LON_HEADERS)?
+        .ok_or("no longitude column found")?;
+    let time = resolve_column(&headers, options.time_column.as_deref(), TIME_HEADERS)?;
+    let elevation = resolve_column(
+        &headers,
+        options.elevation_column.as_deref(),
+        ELEVATION_HEADERS,
+    )?;
+    let track_id = resolve_column(
+        &headers,
+        options.track_id_column.as_deref(),
+        TRACK_ID_HEADERS,
+    )?;
+
+    // a comma inside a coordinate can only be a decimal separator when it isn't the delimiter
+    let decimal_comma = options.decimal_comma.unwrap_or_else(|| {
+        delimiter != ','
+            && lines.take(10).any(|line| {
+                let fields = split_csv_line(line, delimiter);
+                fields.get(lat).is_some_and(|value| value.contains(','))
+            })
+    });
+
+    Ok(CsvColumnMapping {
+        delimiter,
+        decimal_comma,
+        lat,
+        lon,
+        time,
+        elevation,
+        track_id,
+    })
+}
+
+pub fn parse_csv(data: &[u8], options: &CsvOptions) -> Result<Vec<CsvTrack>, String> {
+    let mapping = detect_csv_mapping(data, options)?;
+    let text = String::from_utf8_lossy(data);
+
+    let mut tracks: Vec<CsvTrack> = Vec::new();
+    let mut track_index: HashMap<Option<String>, usize> = HashMap::new();
+
+    for line in text.lines().filter(|line| !line.trim().is_empty()).skip(1) {
+        let fields = split_csv_line(line, mapping.delimiter);
+        let number = |index: usize| parse_number(fields.get(index)?, mapping.decimal_comma);
+
+        let (lat, lon) = match (number(mapping.lat), number(mapping.lon)) {
+            (Some(lat), Some(lon)) if is_valid_coordinate(lat, lon) => (lat, lon),
+            _ => continue,
+        };
+
+        let point = TrackPoint {
+            lat,
+            lon,
+            elevation: mapping.elevation.and_then(number),
+            timestamp_ms: mapping
+                .time
+                .and_then(|index| fields.get(index))
+                .and_then(|value| parse_timestamp_ms(value)),
+        };
+
+        let id = mapping
+            .track_id
+            .and_then(|index| fields.get(index))
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty());
+
+        // tracks keep the order in which their ids first appear
+        let index = *track_index.entry(id.clone()).or_insert_with(|| {
+            tracks.push(CsvTrack {
+                id,
+                points: Vec::new(),
+            });
+            tracks.len() - 1
+        });
+        tracks[index].points.push(point);
+    }
+
+    Ok(tracks)
+}
+
+pub fn write_csv(tracks: &[CsvTrack], options: &CsvOptions) -> String {
+    let delimiter = options.delimiter.unwrap_or(',');
+    // a decimal comma can't be combined with a comma delimiter
+    let decimal_comma = options.decimal_comma.unwrap_or(false) && delimiter != ',';
+    let number = |value: f64, precision: usize| {
+        let formatted = format!("{value:.precision$}");
+        if decimal_comma {
+            formatted.replace('.', ",")
+        } else {
+            formatted
+        }
+    };
+
+    let header = ["track_id", "lat", "lon", "ele", "time"];
+    let mut csv = header.join(&delimiter.to_string());
+    csv.push('\n');
+
+    for (i, track) in tracks.iter().enumerate() {
+        let id = track.id.clone().unwrap_or_else(|| (i + 1).to_string());
+
+        for point in &track.points {
+            let row = [
+                quote_csv_field(&id, delimiter),
+                number(point.lat, 6),
+                number(point.lon, 6),
+                point
+                    .elevation
+                    .map(|ele| number(ele, 1))
+                    .unwrap_or_default(),
+                point
+                    .timestamp_ms
+                    .map(format_timestamp_ms)
+                    .unwrap_or_default(),
+            ];
+            csv.push_str(&row.join(&delimiter.to_string()));
+            csv.push('\n');
+        }
+    }
+
+    csv
+}
+
+fn detect_delimiter(header_line: &str) -> char {
+    // max_by_key keeps the last maximum, reverse so ties go to the earlier candidate
+    DELIMITER_CANDIDATES
+        .iter()
+        .rev()
+        .copied()
+        .max_by_key(|&candidate| split_csv_line(header_line, candidate).len())
+        .unwrap_or(',')
+}
+
+fn normalize_header(header: &str) -> String {
+    header
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+// an explicit column must exist, auto-detected ones are allowed to be missing
+fn resolve_column(
+    headers: &[String],
+    configured: Option<&str>,
+    candidates: &[&str],
+) -> Result<Option<usize>, String> {
+    let normalized: Vec<String> = headers.iter().map(|h| normalize_header(h)).collect();
+
+    if let Some(name) = configured {
+        let wanted = normalize_header(name);
+        return normalized
+            .iter()
+            .position(|header| *header == wanted)
+            .map(Some)
+            .ok_or_else(|| format!("column '{name}' not found in csv header"));
+    }
+
+    // candidates are ordered by preference, so search them in that order
+    Ok(candidates
+        .iter()
+        .find_map(|candidate| normalized.iter().position(|header| header == candidate)))
+}
+
+fn parse_number(value: &str, decimal_comma: bool) -> Option<f64> {
+    let value = value.trim();
+    let parsed = if decimal_comma {
+        value.replace(',', ".").parse::<f64>()
+    } else {
+        value.parse::<f64>()
+    };
+    parsed.ok().filter(|number| number.is_finite())
+}
+
+// splits one row, honouring double quoted fields with "" escapes
+fn split_csv_line(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            c if c == delimiter && !in_quotes => {
+                fields.push(current.trim().to_string());
+                current.clear();
+            }
+            c => current.push(c),
+        }
+    }
+    fields.push(current.trim().to_string());
+
+    fields
+}
+
+// splits a whole document into rows, quoted fields may span several lines
+pub(crate) fn split_csv_records(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for line in text.lines() {
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(line);
+        in_quotes ^= line.matches('"').count() % 2 == 1;
+
+        if !in_quotes {
+            if !current.trim().is_empty() {
+                records.push(split_csv_line(&current, delimiter));
+            }
+            current.clear();
+        }
+    }
+    if !current.trim().is_empty() {
+        records.push(split_csv_line(&current, delimiter));
+    }
+
+    records
+}
+
+fn quote_csv_field(value: &str, delimiter: char) -> String {
+    if value.contains(delimiter) || value.contains('"') || value.contains('\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,217 @@
+// geohash strings for indexing activities in databases. a hash interleaves longitude and
+// latitude bits starting with longitude, five bits per base32 character, so every prefix of
+// a hash is the cell containing it
+use crate::split_bbox_at_antimeridian;
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+const BASE32: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+pub const MAX_GEOHASH_PRECISION: usize = 12;
+
+// counts of one cell, same meaning as on a heatmap segment. frequency follows the builder's
+// frequency_metric, weights and recency decay
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GeohashCell {
+    pub geohash: String,
+    // [lat, lon]
+    pub center: [f64; 2],
+    // [min_lat, min_lon, max_lat, max_lon]
+    pub bbox: [f64; 4],
+    pub frequency: u32,
+    // times a track entered the cell
+    pub passes: u32,
+    // distinct activities through the cell
+    pub activities: u32,
+    pub weight: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GeohashResult {
+    pub precision: usize,
+    // sorted by geohash
+    pub cells: Vec<GeohashCell>,
+    pub max_frequency: u32,
+}
+
+// precision is the number of characters, clamped to 1..=12
+pub fn encode_geohash(lat: f64, lon: f64, precision: usize) -> String {
+    let precision = precision.clamp(1, MAX_GEOHASH_PRECISION);
+    let mut lat_range = [-90.0, 90.0];
+    let mut lon_range = [-180.0, 180.0];
+    let (lat, lon) = (lat.clamp(-90.0, 90.0), lon.clamp(-180.0, 180.0));
+
+    let mut hash = String::with_capacity(precision);
+    let mut even = true;
+    for _ in 0..precision {
+        let mut index = 0;
+        for _ in 0..5 {
+            let (range, value) = if even {
+                (&mut lon_range, lon)
+            } else {
+                (&mut lat_range, lat)
+            };
+            let middle = (range[0] + range[1]) / 2.0;
+            index <<= 1;
+            if value >= middle {
+                index |= 1;
+                range[0] = middle;
+            } else {
+                range[1] = middle;
+            }
+            even = !even;
+        }
+        hash.push(BASE32[index] as char);
+    }
+    hash
+}
+
+// [min_lat, min_lon, max_lat, max_lon] of the cell, None for an empty hash or characters
+// outside the geohash alphabet
+pub fn geohash_bounds(hash: &str) -> Option<[f64; 4]> {
+    if hash.is_empty() {
+        return None;
+    }
+
+    let mut lat_range = [-90.0, 90.0];
+    let mut lon_range = [-180.0, 180.0];
+    let mut even = true;
+    for byte in hash.bytes() {
+        let index = BASE32
+            .iter()
+            .position(|&c| c == byte.to_ascii_lowercase())?;
+        for bit in (0..5).rev() {
+            let range = if even { &mut lon_range } else { &mut lat_range };
+            let middle = (range[0] + range[1]) / 2.0;
+            if index >> bit & 1 == 1 {
+                range[0] = middle;
+            } else {
+                range[1] = middle;
+            }
+            even = !even;
+        }
+    }
+    Some([lat_range[0], lon_range[0], lat_range[1], lon_range[1]])
+}
+
+// center [lat, lon] of the cell
+pub fn decode_geohash(hash: &str) -> Option<[f64; 2]> {
+    let [min_lat, min_lon, max_lat, max_lon] = geohash_bounds(hash)?;
+    Some([(min_lat + max_lat) / 2.0, (min_lon + max_lon) / 2.0])
+}
+
+// the surrounding cells of the same precision clockwise from north: n, ne, e, se, s, sw, w,
+// nw. longitude wraps around, cells beyond a pole are left out
+pub fn geohash_neighbors(hash: &str) -> Option<Vec<String>> {
+    let [min_lat, min_lon, max_lat, max_lon] = geohash_bounds(hash)?;
+    let (height, width) = (max_lat - min_lat, max_lon - min_lon);
+    let center = [(min_lat + max_lat) / 2.0, (min_lon + max_lon) / 2.0];
+
+    let neighbors = [
+        (1, 0),
+        (1, 1),
+        (0, 1),
+        (-1, 1),
+        (-1, 0),
+        (-1, -1),
+        (0, -1),
+        (1, -1),
+    ]
+    .into_iter()
+    .filter_map(|(dlat, dlon)| {
+        let lat = center[0] + dlat as f64 * height;
+        if !(-90.0..=90.0).contains(&lat) {
+            return None;
+        }
+        let lon = (center[1] + dlon as f64 * width + 540.0).rem_euclid(360.0) - 180.0;
+        Some(encode_geohash(lat, lon, hash.len()))
+    })
+    .collect();
+    Some(neighbors)
+}
+
+// covers with more cells than this are refused, at about 50 bytes a hash that's 50 MB
+pub const MAX_GEOHASH_COVER_CELLS: usize = 1_000_000;
+
+// every cell of the precision that intersects bbox [min_lat, min_lon, max_lat, max_lon],
+// row by row from the south west. the count grows 32 times per extra character, None when it
+// would be more than MAX_GEOHASH_COVER_CELLS. a bbox with min_lon > max_lon spans the
+// antimeridian, the cells west of 180 come first
+pub fn geohash_cover(bbox: [f64; 4], precision: usize) -> Option<Vec<String>> {
+    geohash_cover_with_limit(bbox, precision, MAX_GEOHASH_COVER_CELLS)
+}
+
+// counted before a single hash is built
+pub fn geohash_cover_with_limit(
+    bbox: [f64; 4],
+    precision: usize,
+    max_cells: usize,
+) -> Option<Vec<String>> {
+    let grid = CoverGrid::new(precision);
+    let parts: Vec<_> = split_bbox_at_antimeridian(bbox)
+        .into_iter()
+        .filter_map(|part| grid.ranges(part))
+        .collect();
+
+    let cells = parts
+        .iter()
+        .map(|(rows, columns)| {
+            (rows.end() - rows.start() + 1).saturating_mul(columns.end() - columns.start() + 1)
+        })
+        .fold(0u64, u64::saturating_add);
+    if cells > max_cells as u64 {
+        return None;
+    }
+
+    let mut hashes = Vec::with_capacity(cells as usize);
+    for (rows, columns) in parts {
+        for row in rows {
+            for column in columns.clone() {
+                let lat = -90.0 + (row as f64 + 0.5) * grid.height;
+                let lon = -180.0 + (column as f64 + 0.5) * grid.width;
+                hashes.push(encode_geohash(lat, lon, grid.precision));
+            }
+        }
+    }
+    Some(hashes)
+}
+
+// the cells of one precision as a plain lat/lon grid
+struct CoverGrid {
+    precision: usize,
+    lon_cells: u64,
+    lat_cells: u64,
+    width: f64,
+    height: f64,
+}
+
+impl CoverGrid {
+    fn new(precision: usize) -> Self {
+        let precision = precision.clamp(1, MAX_GEOHASH_PRECISION);
+        let bits = 5 * precision as u32;
+        let (lon_cells, lat_cells) = (1u64 << bits.div_ceil(2), 1u64 << (bits / 2));
+        Self {
+            precision,
+            lon_cells,
+            lat_cells,
+            width: 360.0 / lon_cells as f64,
+            height: 180.0 / lat_cells as f64,
+        }
+    }
+
+    // rows and columns a bbox that doesn't cross the antimeridian touches
+    fn ranges(&self, bbox: [f64; 4]) -> Option<(RangeInclusive<u64>, RangeInclusive<u64>)> {
+        let [min_lat, min_lon, max_lat, max_lon] = bbox;
+        if !(min_lat <= max_lat && min_lon <= max_lon) {
+            return None;
+        }
+
+        let index = |value: f64, origin: f64, size: f64, cells: u64| {
+            (((value - origin) / size).floor().max(0.0) as u64).min(cells - 1)
+        };
+        let rows = index(min_lat, -90.0, self.height, self.lat_cells)
+            ..=index(max_lat, -90.0, self.height, self.lat_cells);
+        let columns = index(min_lon, -180.0, self.width, self.lon_cells)
+            ..=index(max_lon, -180.0, self.width, self.lon_cells);
+        Some((rows, columns))
+    }
+}
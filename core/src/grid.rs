@@ -0,0 +1,118 @@
+// packed integer ids for snapped grid cells and segments. these replace the formatted
+// strings that used to be hashed, a cell is the pair of grid indices (lat / tolerance,
+// lon / tolerance) rounded the same way snap_to_grid does
+
+pub type CellId = u64;
+pub type SegmentId = u128;
+
+// index along one axis, saturates for tolerances far below anything a GPS can resolve
+fn grid_index(value: f64, tolerance: f64) -> i32 {
+    (value / tolerance).round() as i32
+}
+
+pub fn grid_cell(point: [f64; 2], tolerance: f64) -> CellId {
+    let (lat_index, lon_index) = grid_position(point, tolerance);
+    pack_cell(lat_index, lon_index)
+}
+
+pub fn grid_position(point: [f64; 2], tolerance: f64) -> (i32, i32) {
+    (
+        grid_index(point[0], tolerance),
+        grid_index(point[1], tolerance),
+    )
+}
+
+pub fn pack_cell(lat_index: i32, lon_index: i32) -> CellId {
+    ((lat_index as u32 as u64) << 32) | lon_index as u32 as u64
+}
+
+pub fn unpack_cell(cell: CellId) -> (i32, i32) {
+    ((cell >> 32) as u32 as i32, cell as u32 as i32)
+}
+
+// the same coordinate snap_to_grid returns for every point in the cell
+pub fn cell_coordinate(cell: CellId, tolerance: f64) -> [f64; 2] {
+    let (lat_index, lon_index) = unpack_cell(cell);
+    [lat_index as f64 * tolerance, lon_index as f64 * tolerance]
+}
+
+// direction independent, the smaller cell (by lat index, then lon index) comes first
+pub fn segment_id(start: [f64; 2], end: [f64; 2], tolerance: f64) -> SegmentId {
+    cell_segment_id(
+        grid_position(start, tolerance),
+        grid_position(end, tolerance),
+    )
+}
+
+pub fn cell_segment_id(a: (i32, i32), b: (i32, i32)) -> SegmentId {
+    let (p1, p2) = if a < b { (a, b) } else { (b, a) };
+    directed_segment_id(p1, p2)
+}
+
+// keeps the direction of travel, from is always the first cell
+pub fn directed_segment_id(from: (i32, i32), to: (i32, i32)) -> SegmentId {
+    ((pack_cell(from.0, from.1) as u128) << 64) | pack_cell(to.0, to.1) as u128
+}
+
+pub fn reverse_segment(segment: SegmentId) -> SegmentId {
+    segment.rotate_left(64)
+}
+
+pub fn segment_cells(segment: SegmentId) -> (CellId, CellId) {
+    ((segment >> 64) as u64, segment as u64)
+}
+
+// line walk over the grid: every cell a straight segment passes through, in order, together
+// with the fraction of the segment at which it is entered. steps are 4-connected so
+// consecutive cells always share an edge, a line exactly through a corner takes the lat step.
+// the walk is straight in lat/lon and as long as the manhattan distance between the end cells,
+// callers split steps over ±180 and bound the length first
+pub fn walk_cells(
+    start: [f64; 2],
+    end: [f64; 2],
+    tolerance: f64,
+    mut visit: impl FnMut((i32, i32), f64),
+) {
+    let mut cell = grid_position(start, tolerance);
+    let last = grid_position(end, tolerance);
+    visit(cell, 0.0);
+
+    // in grid units a cell spans index - 0.5 to index + 0.5
+    let from = [start[0] / tolerance, start[1] / tolerance];
+    let delta = [end[0] / tolerance - from[0], end[1] / tolerance - from[1]];
+    let step = [delta[0].signum() as i32, delta[1].signum() as i32];
+
+    let boundary = |index: i32, axis: usize| {
+        if delta[axis] == 0.0 {
+            f64::INFINITY
+        } else {
+            (index as f64 + 0.5 * step[axis] as f64 - from[axis]) / delta[axis]
+        }
+    };
+    let mut next_crossing = [boundary(cell.0, 0), boundary(cell.1, 1)];
+    let crossing_step = [
+        1.0 / delta[0].abs(), // infinite for an axis the segment doesn't move along
+        1.0 / delta[1].abs(),
+    ];
+
+    // the manhattan distance bounds the walk even when rounding disagrees at the very end
+    let steps = (last.0 - cell.0).unsigned_abs() + (last.1 - cell.1).unsigned_abs();
+    for _ in 0..steps {
+        let remaining = [(last.0 - cell.0) * step[0], (last.1 - cell.1) * step[1]];
+        let axis =
+            if remaining[1] <= 0 || (remaining[0] > 0 && next_crossing[0] <= next_crossing[1]) {
+                0
+            } else {
+                1
+            };
+
+        let entered = next_crossing[axis].clamp(0.0, 1.0);
+        if axis == 0 {
+            cell.0 += step[0];
+        } else {
+            cell.1 += step[1];
+        }
+        next_crossing[axis] += crossing_step[axis];
+        visit(cell, entered);
+    }
+}
@@ -0,0 +1,646 @@
+use crate::{
+    cell_segment_id, decode_geohash, directed_segment_id, encode_geohash, geohash_bounds,
+    grid_position, normalize_longitude, reverse_segment, walk_cells, GeohashCell, GeohashResult,
+    HeatmapResult, HeatmapTrack, SegmentId, MAX_GEOHASH_PRECISION, MS_PER_DAY,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// knobs for the heatmap pipeline. the defaults are the values it always used except for
+// densify_segments, which is now on; set it to false for the old end-snapped counts. walking
+// heatmaps want a much finer grid than cross-country cycling ones
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct HeatmapOptions {
+    // degrees, segments are snapped to this grid for matching
+    pub grid_tolerance: f64,
+    // degrees, points closer than this to the last kept one are dropped
+    pub simplify_tolerance: f64,
+    // decimals kept when a track is prepared
+    pub coordinate_precision: u32,
+    // larger jumps between points are treated as GPS glitches
+    pub max_jump_km: f64,
+    // count every grid cell a segment crosses instead of only snapping its two ends, so
+    // usage doesn't depend on how densely a track happens to be sampled
+    pub densify_segments: bool,
+    // count each direction of travel separately, segment output then carries forward and
+    // backward counts and a dominant bearing
+    pub directional: bool,
+    // which of the two counts every segment keeps drives frequency
+    pub frequency_metric: FrequencyMetric,
+    // older activities count less, off unless set
+    pub recency_decay: Option<RecencyDecay>,
+}
+
+// degrees, about 1 cm
+pub const MIN_GRID_TOLERANCE: f64 = 1e-7;
+
+// cells a densified segment may walk before it is counted between its end cells only
+pub const MAX_DENSIFIED_CELLS: u64 = 100_000;
+
+impl Default for HeatmapOptions {
+    fn default() -> Self {
+        Self {
+            grid_tolerance: 0.001, // About 100 meters
+            simplify_tolerance: 0.00005,
+            coordinate_precision: 5,
+            max_jump_km: 100.0,
+            densify_segments: true,
+            directional: false,
+            frequency_metric: FrequencyMetric::Passes,
+            recency_decay: None,
+        }
+    }
+}
+
+// an activity half_life_days older than reference_ms counts half. activities without a
+// timestamp aren't decayed, ones after the reference count as if they happened at it. a
+// half_life_days that isn't a positive number turns decay off rather than giving NaN weights
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct RecencyDecay {
+    pub half_life_days: f64,
+    pub reference_ms: i64,
+}
+
+// passes counts every traversal, so an activity lapping a running track 25 times adds 25.
+// activities counts each activity at most once per segment
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FrequencyMetric {
+    #[default]
+    Passes,
+    Activities,
+}
+
+// input to the weighted entry points, one per recorded activity
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WeightedActivity {
+    pub tracks: Vec<Vec<[f64; 2]>>,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    #[serde(default)]
+    pub timestamp_ms: Option<i64>,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+impl RecencyDecay {
+    // multiplier for an activity at timestamp_ms
+    pub fn factor(&self, timestamp_ms: i64) -> f64 {
+        if self.half_life_days.is_nan() || self.half_life_days <= 0.0 {
+            return 1.0;
+        }
+        let age_days = (self.reference_ms - timestamp_ms).max(0) as f64 / MS_PER_DAY as f64;
+        0.5f64.powf(age_days / self.half_life_days)
+    }
+}
+
+// a run of consecutive segments along one track that share the same usage count
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HeatmapSegment {
+    pub coordinates: Vec<[f64; 2]>,
+    pub frequency: u32,
+    // both counts regardless of frequency_metric, summed over both directions
+    #[serde(default)]
+    pub passes: u32,
+    #[serde(default)]
+    pub activities: u32,
+    // weighted and decayed value frequency is rounded from, before the minimum of 1
+    #[serde(default)]
+    pub weight: f64,
+    // only in directional mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direction: Option<SegmentDirection>,
+}
+
+// forward is the order of the run's coordinates, frequency is then forward + backward.
+// bearing is in degrees clockwise from north, pointing the way most traffic went
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SegmentDirection {
+    pub forward: u32,
+    pub backward: u32,
+    pub bearing: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SegmentHeatmapResult {
+    pub segments: Vec<HeatmapSegment>,
+    pub max_frequency: u32,
+}
+
+// incremental counterpart of create_heatmap_from_tracks, segment counts are kept up to date
+// as tracks arrive so a result can be produced at any point without recounting. the builder
+// is serializable so a server can persist it and only add new activities later
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct HeatmapBuilder {
+    #[serde(default)]
+    options: HeatmapOptions,
+    segment_usage: HashMap<SegmentId, SegmentUsage>,
+    tracks: Vec<Vec<[f64; 2]>>,
+    // in the order they were added, each one owns the next `tracks` entries of tracks
+    #[serde(default)]
+    activities: Vec<ActivityEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+struct SegmentUsage {
+    passes: u32,
+    activities: u32,
+    weighted_passes: f64,
+    weighted_activities: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+struct ActivityEntry {
+    tracks: usize,
+    weight: f64,
+    timestamp_ms: Option<i64>,
+}
+
+impl HeatmapBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // only grid_tolerance, densify_segments, directional, frequency_metric and recency_decay
+    // matter here, the other options apply while preparing tracks
+    // a grid_tolerance that is not a positive number falls back to the default, tiny ones are
+    // raised to MIN_GRID_TOLERANCE so grid indices stay well inside i32
+    pub fn with_options(mut options: HeatmapOptions) -> Self {
+        options.grid_tolerance =
+            if options.grid_tolerance.is_finite() && options.grid_tolerance > 0.0 {
+                options.grid_tolerance.max(MIN_GRID_TOLERANCE)
+            } else {
+                HeatmapOptions::default().grid_tolerance
+            };
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    pub fn options(&self) -> &HeatmapOptions {
+        &self.options
+    }
+
+    // every track is an activity of its own
+    pub fn add_track(&mut self, track: Vec<[f64; 2]>) {
+        self.add_activity(std::iter::once(track));
+    }
+
+    // all tracks of one activity, e.g. the track segments of a single GPX file. a segment
+    // they pass several times still counts as one activity
+    pub fn add_activity<I: IntoIterator<Item = Vec<[f64; 2]>>>(&mut self, tracks: I) {
+        self.add_weighted_activity(tracks, 1.0, None);
+    }
+
+    // weight scales everything the activity adds, e.g. by activity type or a confidence score.
+    // the timestamp is only used for recency decay
+    pub fn add_weighted_activity<I: IntoIterator<Item = Vec<[f64; 2]>>>(
+        &mut self,
+        tracks: I,
+        weight: f64,
+        timestamp_ms: Option<i64>,
+    ) {
+        // a negative weight would take away from other activities' counts
+        let weight = weight.max(0.0);
+        let counted = self.decayed_weight(weight, timestamp_ms);
+        let mut visited: HashSet<SegmentId> = HashSet::new();
+        let mut added = 0;
+
+        for track in tracks {
+            // a single point has no segments and never shows up in the result
+            if track.len() < 2 {
+                continue;
+            }
+
+            for window in track.windows(2) {
+                for_each_unit(&self.options, window[0], window[1], |unit, _, _| {
+                    let usage = self.segment_usage.entry(unit).or_default();
+                    usage.passes += 1;
+                    usage.weighted_passes += counted;
+                    visited.insert(unit);
+                });
+            }
+            self.tracks.push(track);
+            added += 1;
+        }
+
+        for unit in visited {
+            let usage = self.segment_usage.entry(unit).or_default();
+            usage.activities += 1;
+            usage.weighted_activities += counted;
+        }
+        if added > 0 {
+            self.activities.push(ActivityEntry {
+                tracks: added,
+                weight,
+                timestamp_ms,
+            });
+        }
+    }
+
+    pub fn add_tracks<I: IntoIterator<Item = Vec<[f64; 2]>>>(&mut self, tracks: I) {
+        for track in tracks {
+            self.add_track(track);
+        }
+    }
+
+    // combines two shards, the result is the same as adding other's tracks one by one
+    pub fn merge(&mut self, other: HeatmapBuilder) {
+        // counts from a different grid can't be combined, recount those tracks on ours
+        if other.options.grid_tolerance != self.options.grid_tolerance
+            || other.options.densify_segments != self.options.densify_segments
+            || other.options.directional != self.options.directional
+            || other.options.recency_decay != self.options.recency_decay
+        {
+            let mut tracks = other.tracks.into_iter();
+            for activity in other.activities {
+                let activity_tracks: Vec<_> = tracks.by_ref().take(activity.tracks).collect();
+                self.add_weighted_activity(activity_tracks, activity.weight, activity.timestamp_ms);
+            }
+            self.add_tracks(tracks);
+            return;
+        }
+
+        for (segment, usage) in other.segment_usage {
+            let merged = self.segment_usage.entry(segment).or_default();
+            merged.passes += usage.passes;
+            merged.activities += usage.activities;
+            merged.weighted_passes += usage.weighted_passes;
+            merged.weighted_activities += usage.weighted_activities;
+        }
+        self.tracks.extend(other.tracks);
+        self.activities.extend(other.activities);
+    }
+
+    pub fn track_count(&self) -> usize {
+        self.tracks.len()
+    }
+
+    pub fn activity_count(&self) -> usize {
+        self.activities.len()
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segment_usage.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    pub fn build(&self) -> HeatmapResult {
+        let tracks = self
+            .tracks
+            .iter()
+            .map(|track| HeatmapTrack {
+                coordinates: track.clone(),
+                frequency: self.track_frequency(track),
+            })
+            .collect::<Vec<_>>();
+
+        // Find the maximum frequency for normalization
+        let max_frequency = tracks
+            .iter()
+            .map(|track| track.frequency)
+            .max()
+            .unwrap_or(1);
+
+        HeatmapResult {
+            tracks,
+            max_frequency,
+        }
+    }
+
+    // per-segment alternative to build, every counted segment keeps its own count instead
+    // of the track average. a segment shared by several tracks is only emitted for the first
+    // one so overlapping routes aren't drawn on top of each other
+    pub fn build_segments(&self) -> SegmentHeatmapResult {
+        let mut segments = Vec::new();
+        let mut emitted: HashSet<SegmentId> = HashSet::new();
+
+        for track in &self.tracks {
+            let mut run: Option<HeatmapSegment> = None;
+
+            for window in track.windows(2) {
+                // pieces of one segment are collinear, a run only needs the last one's end
+                let mut inside_window = false;
+
+                for_each_unit(&self.options, window[0], window[1], |unit, t0, t1| {
+                    let usage = self.piece_usage(unit);
+
+                    // both directions of a piece are drawn once, by whoever came first
+                    if !emitted.insert(unit.min(reverse_segment(unit))) {
+                        segments.extend(run.take());
+                        return;
+                    }
+
+                    let piece_end = interpolate(window[0], window[1], t1);
+                    let extend_last = std::mem::replace(&mut inside_window, true);
+                    match run.as_mut() {
+                        Some(current) if same_counts(current, &usage) => {
+                            if extend_last {
+                                current.coordinates.pop();
+                            }
+                            current.coordinates.push(piece_end);
+                        }
+                        _ => {
+                            segments.extend(run.replace(HeatmapSegment {
+                                coordinates: vec![interpolate(window[0], window[1], t0), piece_end],
+                                ..usage
+                            }));
+                        }
+                    }
+                });
+            }
+
+            segments.extend(run);
+        }
+
+        for segment in &mut segments {
+            if let Some(direction) = segment.direction.as_mut() {
+                let first = segment.coordinates[0];
+                let last = segment.coordinates[segment.coordinates.len() - 1];
+                direction.bearing = if direction.backward > direction.forward {
+                    bearing(last, first)
+                } else {
+                    bearing(first, last)
+                };
+            }
+        }
+
+        let max_frequency = segments
+            .iter()
+            .map(|segment| segment.frequency)
+            .max()
+            .unwrap_or(1);
+
+        SegmentHeatmapResult {
+            segments,
+            max_frequency,
+        }
+    }
+
+    // the counted units of every track gathered into geohash cells by their middle. a track
+    // entering a cell is a pass and each activity counts once per cell, weighted and decayed
+    // like the segment counts
+    pub fn build_geohash(&self, precision: usize) -> GeohashResult {
+        let precision = precision.clamp(1, MAX_GEOHASH_PRECISION);
+        let mut usage: BTreeMap<String, SegmentUsage> = BTreeMap::new();
+
+        // tracks of builders saved before activities were recorded count on their own
+        let counted_tracks: usize = self.activities.iter().map(|a| a.tracks).sum();
+        let leftover = self.tracks.len().saturating_sub(counted_tracks);
+        let activities = self
+            .activities
+            .iter()
+            .map(|a| (a.tracks, a.weight, a.timestamp_ms))
+            .chain(std::iter::repeat_n((1, 1.0, None), leftover));
+
+        let mut tracks = self.tracks.iter();
+        for (track_count, weight, timestamp_ms) in activities {
+            let counted = self.decayed_weight(weight, timestamp_ms);
+            let mut visited: HashSet<String> = HashSet::new();
+
+            for track in tracks.by_ref().take(track_count) {
+                let mut current: Option<String> = None;
+                for window in track.windows(2) {
+                    for_each_unit(&self.options, window[0], window[1], |_, t0, t1| {
+                        let [lat, lon] = interpolate(window[0], window[1], (t0 + t1) / 2.0);
+                        let hash = encode_geohash(lat, lon, precision);
+                        if current.as_ref() == Some(&hash) {
+                            return;
+                        }
+
+                        let cell = usage.entry(hash.clone()).or_default();
+                        cell.passes += 1;
+                        cell.weighted_passes += counted;
+                        visited.insert(hash.clone());
+                        current = Some(hash);
+                    });
+                }
+            }
+
+            for hash in visited {
+                let cell = usage.entry(hash).or_default();
+                cell.activities += 1;
+                cell.weighted_activities += counted;
+            }
+        }
+
+        let cells: Vec<GeohashCell> = usage
+            .into_iter()
+            .map(|(geohash, usage)| {
+                let weight = self.frequency_weight(&usage);
+                GeohashCell {
+                    center: decode_geohash(&geohash).unwrap_or_default(),
+                    bbox: geohash_bounds(&geohash).unwrap_or_default(),
+                    geohash,
+                    frequency: (weight.round() as u32).max(1),
+                    passes: usage.passes,
+                    activities: usage.activities,
+                    weight,
+                }
+            })
+            .collect();
+
+        GeohashResult {
+            precision,
+            max_frequency: cells.iter().map(|c| c.frequency).max().unwrap_or(1),
+            cells,
+        }
+    }
+
+    // counts of a piece as drawn in the direction it was traversed, without coordinates. the
+    // bearing is filled in once the whole run is known
+    fn piece_usage(&self, unit: SegmentId) -> HeatmapSegment {
+        let usage = |unit| self.segment_usage.get(&unit).copied().unwrap_or_default();
+        let reverse = reverse_segment(unit);
+        // a piece inside a single cell has no direction to tell apart
+        let (forward, backward) = if self.options.directional && reverse != unit {
+            (usage(unit), usage(reverse))
+        } else {
+            (usage(unit), SegmentUsage::default())
+        };
+
+        let forward_weight = self.frequency_weight(&forward);
+        let backward_weight = self.frequency_weight(&backward);
+        let forward_frequency = (forward_weight.round() as u32).max(1);
+        let backward_frequency = backward_weight.round() as u32;
+
+        HeatmapSegment {
+            coordinates: Vec::new(),
+            frequency: forward_frequency + backward_frequency,
+            passes: forward.passes + backward.passes,
+            activities: forward.activities + backward.activities,
+            weight: forward_weight + backward_weight,
+            direction: self.options.directional.then_some(SegmentDirection {
+                forward: forward_frequency,
+                backward: backward_frequency,
+                bearing: 0.0,
+            }),
+        }
+    }
+
+    fn frequency_weight(&self, usage: &SegmentUsage) -> f64 {
+        match self.options.frequency_metric {
+            FrequencyMetric::Passes => usage.weighted_passes,
+            FrequencyMetric::Activities => usage.weighted_activities,
+        }
+    }
+
+    fn decayed_weight(&self, weight: f64, timestamp_ms: Option<i64>) -> f64 {
+        match (self.options.recency_decay, timestamp_ms) {
+            (Some(decay), Some(timestamp_ms)) => weight * decay.factor(timestamp_ms),
+            _ => weight,
+        }
+    }
+
+    // average usage of the track's segments, with minimum of 1. directional, only traffic
+    // going the same way as the track counts
+    fn track_frequency(&self, track: &[[f64; 2]]) -> u32 {
+        let mut total_usage = 0.0;
+        let mut segment_count = 0;
+
+        for window in track.windows(2) {
+            for_each_unit(&self.options, window[0], window[1], |unit, _, _| {
+                if let Some(usage) = self.segment_usage.get(&unit) {
+                    total_usage += self.frequency_weight(usage);
+                    segment_count += 1;
+                }
+            });
+        }
+
+        if segment_count > 0 {
+            ((total_usage / segment_count as f64).round() as u32).max(1)
+        } else {
+            1
+        }
+    }
+}
+
+fn same_counts(a: &HeatmapSegment, b: &HeatmapSegment) -> bool {
+    a.frequency == b.frequency
+        && a.passes == b.passes
+        && a.activities == b.activities
+        && a.weight == b.weight
+        && a.direction == b.direction
+}
+
+// every counted unit of a segment together with the part [t0, t1] of it the unit covers.
+// densified, a unit is the step between two neighbouring cells of the line walk and covers the
+// segment from the middle of one cell to the middle of the next, so the pieces of a segment
+// always add up to the whole of it. a track that never leaves its cell has nothing to count
+// and keeps the minimum frequency, like any other track without usage
+fn for_each_unit(
+    options: &HeatmapOptions,
+    start: [f64; 2],
+    end: [f64; 2],
+    mut f: impl FnMut(SegmentId, f64, f64),
+) {
+    let tolerance = options.grid_tolerance;
+    let unit_id = |from, to| {
+        if options.directional {
+            directed_segment_id(from, to)
+        } else {
+            cell_segment_id(from, to)
+        }
+    };
+
+    if !options.densify_segments {
+        let unit = unit_id(
+            grid_position(start, tolerance),
+            grid_position(end, tolerance),
+        );
+        f(unit, 0.0, 1.0);
+        return;
+    }
+
+    // a step over ±180 is taken the short way, walked as the two pieces either side of the
+    // meridian so the cell indices stay in range
+    let delta = end[1] - start[1];
+    let pieces = if delta.abs() > 180.0 {
+        let edge = if delta < 0.0 { 180.0 } else { -180.0 };
+        let unwrapped = end[1] + 2.0 * edge;
+        let t = (edge - start[1]) / (unwrapped - start[1]);
+        let cross_lat = start[0] + t * (end[0] - start[0]);
+        vec![
+            (start, [cross_lat, edge], 0.0, t),
+            ([cross_lat, -edge], end, t, 1.0),
+        ]
+    } else {
+        vec![(start, end, 0.0, 1.0)]
+    };
+
+    for (from, to, t_from, t_to) in pieces {
+        let scale = |t: f64| t_from + t * (t_to - t_from);
+        let (first, last) = (grid_position(from, tolerance), grid_position(to, tolerance));
+
+        // a walk this long would take ages at a fine tolerance, the piece counts once between
+        // its end cells like it would without densifying
+        let cells =
+            (last.0 - first.0).unsigned_abs() as u64 + (last.1 - first.1).unsigned_abs() as u64;
+        if cells > MAX_DENSIFIED_CELLS {
+            f(unit_id(first, last), t_from, t_to);
+            continue;
+        }
+
+        let mut previous: Option<((i32, i32), f64)> = None;
+        let mut pending: Option<(SegmentId, f64)> = None;
+
+        walk_cells(from, to, tolerance, |cell, entered| {
+            if let Some((previous_cell, previous_entered)) = previous {
+                let middle = (previous_entered + entered) / 2.0;
+                let t0 = match pending.take() {
+                    Some((unit, t0)) => {
+                        let middle = middle.max(t0);
+                        f(unit, scale(t0), scale(middle));
+                        middle
+                    }
+                    None => 0.0,
+                };
+                pending = Some((unit_id(previous_cell, cell), t0));
+            }
+            previous = Some((cell, entered));
+        });
+
+        // a segment that stays inside one cell adds nothing, otherwise densely sampled tracks
+        // would count extra units that a sparse recording of the same street never does
+        if let Some((unit, t0)) = pending {
+            f(unit, scale(t0), t_to);
+        }
+    }
+}
+
+// along the segment the short way, over ±180 when that is shorter
+fn interpolate(start: [f64; 2], end: [f64; 2], t: f64) -> [f64; 2] {
+    if t <= 0.0 {
+        start
+    } else if t >= 1.0 {
+        end
+    } else {
+        let mut delta_lon = end[1] - start[1];
+        if delta_lon > 180.0 {
+            delta_lon -= 360.0;
+        } else if delta_lon < -180.0 {
+            delta_lon += 360.0;
+        }
+        [
+            start[0] + (end[0] - start[0]) * t,
+            normalize_longitude(start[1] + delta_lon * t),
+        ]
+    }
+}
+
+// initial great circle bearing in degrees, 0 is north and 90 east
+fn bearing(start: [f64; 2], end: [f64; 2]) -> f64 {
+    let (lat1, lat2) = (start[0].to_radians(), end[0].to_radians());
+    let delta_lon = (end[1] - start[1]).to_radians();
+
+    let y = delta_lon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * delta_lon.cos();
+    (y.atan2(x).to_degrees() + 360.0) % 360.0
+}
@@ -0,0 +1,278 @@
+// hexagonal binning of track points and distance. hexagons have six equidistant neighbors and
+// no preferred direction, so aggregates don't come out blocky or biased along the grid axes
+// like square snapping. the grid is pointy-top in web mercator meters, cells look regular on
+// a web map and size_m is their circumradius at the equator, shrinking by cos(lat) further
+// north or south. hex_size_for_latitude gives the size for a ground size at some latitude
+use crate::{
+    calculate_distance_between_points, lat_lon_to_mercator, mercator_to_lat_lon, MERCATOR_RADIUS_M,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+const SQRT_3: f64 = 1.732_050_807_568_877_2;
+// width of the projected world in meters
+const WORLD_WIDTH_M: f64 = 2.0 * std::f64::consts::PI * MERCATOR_RADIUS_M;
+
+// meters, smaller sizes are raised to this
+pub const MIN_HEX_SIZE_M: f64 = 1.0;
+// larger disks are cut to this radius, it is already some three million cells
+pub const MAX_HEX_DISK_RADIUS: u32 = 1000;
+// a single step is cut into at most this many pieces, beyond that they grow longer than a
+// quarter cell and cells in between may be skipped, the length is still all counted
+pub const MAX_HEX_PIECES: usize = 100_000;
+
+// axial coordinates, q along a row and r from row to row northwards
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct HexId {
+    pub q: i32,
+    pub r: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct HexbinOptions {
+    pub size_m: f64,
+}
+
+impl Default for HexbinOptions {
+    fn default() -> Self {
+        Self { size_m: 500.0 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HexCell {
+    pub id: HexId,
+    // [lat, lon]
+    pub center: [f64; 2],
+    // closed ring of [lat, lon], the first corner repeated at the end
+    pub boundary: Vec<[f64; 2]>,
+    pub points: u32,
+    // distance along the tracks inside the cell
+    pub length_m: f64,
+    // distinct tracks with a point or some distance in the cell
+    pub tracks: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HexbinResult {
+    pub size_m: f64,
+    // sorted by id
+    pub cells: Vec<HexCell>,
+    pub max_points: u32,
+    pub max_length_m: f64,
+}
+
+// the size_m that gives cells of ground_size_m circumradius around lat
+pub fn hex_size_for_latitude(ground_size_m: f64, lat: f64) -> f64 {
+    ground_size_m / lat.to_radians().cos().max(f64::EPSILON)
+}
+
+pub fn hex_cell(point: [f64; 2], size_m: f64) -> HexId {
+    mercator_cell(lat_lon_to_mercator(point), size_m)
+}
+
+pub fn hex_center(id: HexId, size_m: f64) -> [f64; 2] {
+    mercator_to_lat_lon(center_mercator(id, size_m))
+}
+
+pub fn hex_boundary(id: HexId, size_m: f64) -> Vec<[f64; 2]> {
+    let [cx, cy] = center_mercator(id, size_m);
+    let mut ring: Vec<[f64; 2]> = (0..6)
+        .map(|corner| {
+            let angle = (30.0 + 60.0 * corner as f64).to_radians();
+            mercator_to_lat_lon([cx + size_m * angle.cos(), cy + size_m * angle.sin()])
+        })
+        .collect();
+    ring.push(ring[0]);
+    ring
+}
+
+// clockwise starting east
+pub fn hex_neighbors(id: HexId) -> [HexId; 6] {
+    [(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)].map(|(dq, dr)| HexId {
+        q: id.q + dq,
+        r: id.r + dr,
+    })
+}
+
+// every cell within radius steps of id, id itself included. radius is capped at
+// MAX_HEX_DISK_RADIUS
+pub fn hex_disk(id: HexId, radius: u32) -> Vec<HexId> {
+    let radius = radius.min(MAX_HEX_DISK_RADIUS) as i32;
+    let mut cells = Vec::new();
+    for dq in -radius..=radius {
+        for dr in (-radius).max(-dq - radius)..=radius.min(-dq + radius) {
+            cells.push(HexId {
+                q: id.q + dq,
+                r: id.r + dr,
+            });
+        }
+    }
+    cells
+}
+
+pub fn hex_distance(a: HexId, b: HexId) -> u32 {
+    let (dq, dr) = (a.q - b.q, a.r - b.r);
+    ((dq.abs() + dr.abs() + (dq + dr).abs()) / 2) as u32
+}
+
+// loose points like stops or start locations, only counted
+pub fn hexbin_points(points: &[[f64; 2]], options: &HexbinOptions) -> HexbinResult {
+    bin(&[points.to_vec()], options, false)
+}
+
+// points are counted in the cell they fall in, the distance between them is spread over the
+// cells the line passes through
+pub fn hexbin_tracks(tracks: &[Vec<[f64; 2]>], options: &HexbinOptions) -> HexbinResult {
+    bin(tracks, options, true)
+}
+
+fn bin(tracks: &[Vec<[f64; 2]>], options: &HexbinOptions, with_length: bool) -> HexbinResult {
+    let size = options.size_m.max(MIN_HEX_SIZE_M);
+
+    // points, length, tracks, last track seen
+    let mut bins: BTreeMap<HexId, (u32, f64, u32, usize)> = BTreeMap::new();
+    let mut add = |id: HexId, track: usize, points: u32, length: f64| {
+        let bin = bins.entry(id).or_insert((0, 0.0, 0, usize::MAX));
+        bin.0 += points;
+        bin.1 += length;
+        if bin.3 != track {
+            bin.2 += 1;
+            bin.3 = track;
+        }
+    };
+
+    for (track_index, track) in tracks.iter().enumerate() {
+        let track: Vec<[f64; 2]> = track
+            .iter()
+            .copied()
+            .filter(|p| p[0].is_finite() && p[1].is_finite())
+            .collect();
+
+        for &point in &track {
+            add(hex_cell(point, size), track_index, 1, 0.0);
+        }
+        if !with_length {
+            continue;
+        }
+
+        // pieces of a quarter cell, each credited to the cell around its middle. a step over
+        // the antimeridian goes the short way round, not back across the whole world
+        for window in track.windows(2) {
+            let (a, mut b) = (
+                lat_lon_to_mercator(window[0]),
+                lat_lon_to_mercator(window[1]),
+            );
+            if b[0] - a[0] > WORLD_WIDTH_M / 2.0 {
+                b[0] -= WORLD_WIDTH_M;
+            } else if a[0] - b[0] > WORLD_WIDTH_M / 2.0 {
+                b[0] += WORLD_WIDTH_M;
+            }
+            let length = calculate_distance_between_points(
+                window[0][0],
+                window[0][1],
+                window[1][0],
+                window[1][1],
+            ) * 1000.0;
+            if length <= 0.0 {
+                continue;
+            }
+
+            let span = (b[0] - a[0]).hypot(b[1] - a[1]);
+            let pieces = ((span / (size / 4.0)).ceil() as usize).clamp(1, MAX_HEX_PIECES);
+            for piece in 0..pieces {
+                let t = (piece as f64 + 0.5) / pieces as f64;
+                let mut middle = [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t];
+                if middle[0].abs() > WORLD_WIDTH_M / 2.0 {
+                    middle[0] -= WORLD_WIDTH_M.copysign(middle[0]);
+                }
+                let id = mercator_cell(middle, size);
+                add(id, track_index, 0, length / pieces as f64);
+            }
+        }
+    }
+
+    let cells: Vec<HexCell> = bins
+        .into_iter()
+        .map(|(id, (points, length_m, tracks, _))| HexCell {
+            id,
+            center: hex_center(id, size),
+            boundary: hex_boundary(id, size),
+            points,
+            length_m,
+            tracks,
+        })
+        .collect();
+
+    HexbinResult {
+        size_m: size,
+        max_points: cells.iter().map(|c| c.points).max().unwrap_or(0),
+        max_length_m: cells.iter().map(|c| c.length_m).fold(0.0, f64::max),
+        cells,
+    }
+}
+
+impl HexbinResult {
+    // FeatureCollection of cell polygons with their counts, ready for a choropleth
+    pub fn to_geojson(&self) -> serde_json::Value {
+        let features: Vec<serde_json::Value> = self
+            .cells
+            .iter()
+            .map(|cell| {
+                // geojson uses [lng, lat] order
+                let ring: Vec<[f64; 2]> =
+                    cell.boundary.iter().map(|&[lat, lon]| [lon, lat]).collect();
+                json!({
+                    "type": "Feature",
+                    "geometry": {
+                        "type": "Polygon",
+                        "coordinates": [ring]
+                    },
+                    "properties": {
+                        "q": cell.id.q,
+                        "r": cell.id.r,
+                        "points": cell.points,
+                        "length_m": cell.length_m,
+                        "tracks": cell.tracks
+                    }
+                })
+            })
+            .collect();
+
+        json!({
+            "type": "FeatureCollection",
+            "features": features
+        })
+    }
+}
+
+fn mercator_cell([x, y]: [f64; 2], size_m: f64) -> HexId {
+    let q = (SQRT_3 / 3.0 * x - y / 3.0) / size_m;
+    let r = (2.0 / 3.0 * y) / size_m;
+    round_axial(q, r)
+}
+
+fn center_mercator(id: HexId, size_m: f64) -> [f64; 2] {
+    [
+        size_m * SQRT_3 * (id.q as f64 + id.r as f64 / 2.0),
+        size_m * 1.5 * id.r as f64,
+    ]
+}
+
+// nearest hexagon through cube coordinates, the component that moved most is recomputed
+fn round_axial(q: f64, r: f64) -> HexId {
+    let s = -q - r;
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+    HexId {
+        q: rq as i32,
+        r: rr as i32,
+    }
+}
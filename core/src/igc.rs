@@ -0,0 +1,273 @@
+use crate::{is_valid_coordinate, parse_ddmmyy, round, TrackPoint, MS_PER_DAY};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// IGC flight recorder format reference: https://www.fai.org/sites/default/files/igc_fr_specification_2020-11-25_with_al6.pdf
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct IgcHeader {
+    pub manufacturer: Option<String>, // three character code from the A record
+    pub logger_id: Option<String>,
+    pub date_days: Option<i64>, // days since unix epoch from HFDTE
+    pub pilot: Option<String>,
+    pub copilot: Option<String>,
+    pub glider_type: Option<String>,
+    pub glider_id: Option<String>,
+    pub competition_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct IgcFix {
+    pub lat: f64,
+    pub lon: f64,
+    pub timestamp_ms: Option<i64>, // unix epoch millis, None without an HFDTE date
+    pub pressure_altitude: Option<i32>,
+    pub gnss_altitude: Option<i32>,
+    pub valid: bool, // 'A' = 3D fix, 'V' = 2D fix or no GNSS altitude
+    pub extensions: HashMap<String, String>, // B record extensions declared by the I record
+}
+
+// GNSS altitude is preferred since loggers without a baro sensor write 0 pressure altitude,
+// 2D fixes have no usable GNSS altitude and fall back to pressure altitude
+impl From<&IgcFix> for TrackPoint {
+    fn from(fix: &IgcFix) -> Self {
+        TrackPoint {
+            lat: fix.lat,
+            lon: fix.lon,
+            elevation: fix
+                .gnss_altitude
+                .filter(|_| fix.valid)
+                .or(fix.pressure_altitude)
+                .map(f64::from),
+            timestamp_ms: fix.timestamp_ms,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct IgcFlight {
+    pub header: IgcHeader,
+    pub fixes: Vec<IgcFix>,
+}
+
+// extension declared in an I record, byte positions are 1-based and inclusive
+#[derive(Clone, Debug, PartialEq)]
+pub struct IgcExtension {
+    pub start: usize,
+    pub end: usize,
+    pub code: String,
+}
+
+pub struct IgcParser {
+    pub data: Vec<u8>,
+    pub extensions: Vec<IgcExtension>,
+}
+
+impl IgcParser {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            extensions: Vec::new(),
+        }
+    }
+
+    // rounded to 5 decimals like it always was, the fixes keep full precision
+    pub fn parse_gps_coordinates(&mut self) -> Vec<[f64; 2]> {
+        self.parse_flight()
+            .fixes
+            .iter()
+            .map(|fix| [round(fix.lat), round(fix.lon)])
+            .collect()
+    }
+
+    pub fn parse_flight(&mut self) -> IgcFlight {
+        let text = String::from_utf8_lossy(&self.data).into_owned();
+        let mut flight = IgcFlight::default();
+        self.extensions.clear();
+
+        let mut current_day: Option<i64> = None;
+        let mut previous_tod: Option<u32> = None;
+
+        for line in text.lines() {
+            let line = line.trim_end();
+
+            match line.as_bytes().first() {
+                Some(b'A') => parse_a_record(line, &mut flight.header),
+                Some(b'H') => {
+                    parse_h_record(line, &mut flight.header);
+                    if current_day.is_none() {
+                        current_day = flight.header.date_days;
+                    }
+                }
+                Some(b'I') => self.extensions = parse_i_record(line),
+                Some(b'B') => {
+                    let (tod, mut fix) = match parse_b_record(line, &self.extensions) {
+                        Some(parsed) => parsed,
+                        None => continue,
+                    };
+
+                    // B records are chronological, going backwards means we passed midnight UTC
+                    if previous_tod.is_some_and(|prev| tod < prev) {
+                        current_day = current_day.map(|day| day + 1);
+                    }
+                    previous_tod = Some(tod);
+
+                    fix.timestamp_ms = current_day.map(|day| day * MS_PER_DAY + tod as i64);
+                    if is_valid_coordinate(fix.lat, fix.lon) {
+                        flight.fixes.push(fix);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        flight
+    }
+}
+
+pub fn is_igc_file(data: &[u8]) -> bool {
+    // every IGC file starts with the A record, followed by H header records
+    let head = &data[..data.len().min(4096)];
+    let text = String::from_utf8_lossy(head);
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+
+    let a_record = match lines.next() {
+        Some(line) => line.trim_start_matches('\u{feff}'),
+        None => return false,
+    };
+    if !a_record.starts_with('A') {
+        return false;
+    }
+    // by bytes, the first line may be anything, e.g. a CSV header in another language
+    match a_record.get(1..4) {
+        Some(code) if code.bytes().all(|b| b.is_ascii_alphanumeric()) => {}
+        _ => return false,
+    }
+
+    lines.any(|line| line.starts_with('H'))
+}
+
+// AXXXyyyyyy: manufacturer code followed by the logger serial
+fn parse_a_record(line: &str, header: &mut IgcHeader) {
+    if let Some(manufacturer) = line.get(1..4) {
+        header.manufacturer = Some(manufacturer.to_string());
+        header.logger_id = line
+            .get(4..)
+            .map(|rest| rest.trim().to_string())
+            .filter(|rest| !rest.is_empty());
+    }
+}
+
+// HFDTE010124 / HFDTEDATE:010124,01 / HFPLTPILOTINCHARGE:Jane Doe
+fn parse_h_record(line: &str, header: &mut IgcHeader) {
+    let code = match line.get(2..5) {
+        Some(code) => code,
+        None => return,
+    };
+
+    // long names before the colon are optional and vary between loggers
+    let value = match line.split_once(':') {
+        Some((_, value)) => value.trim(),
+        None => line.get(5..).unwrap_or("").trim(),
+    };
+
+    let text = if value.is_empty() || value.eq_ignore_ascii_case("not set") {
+        None
+    } else {
+        Some(value.to_string())
+    };
+
+    match code {
+        "DTE" => {
+            let date = value.split(',').next().unwrap_or("");
+            header.date_days = date.get(..6).and_then(parse_ddmmyy);
+        }
+        "PLT" => header.pilot = text,
+        "CM2" => header.copilot = text,
+        "GTY" => header.glider_type = text,
+        "GID" => header.glider_id = text,
+        "CID" => header.competition_id = text,
+        _ => {}
+    }
+}
+
+// INNSSEECCC...: count then start byte, end byte and three letter code per extension
+fn parse_i_record(line: &str) -> Vec<IgcExtension> {
+    let count: usize = match line.get(1..3).and_then(|n| n.parse().ok()) {
+        Some(count) => count,
+        None => return Vec::new(),
+    };
+
+    (0..count)
+        .filter_map(|i| {
+            let entry = line.get(3 + i * 7..10 + i * 7)?;
+            let start: usize = entry.get(0..2)?.parse().ok()?;
+            let end: usize = entry.get(2..4)?.parse().ok()?;
+            if start == 0 || end < start {
+                return None;
+            }
+            Some(IgcExtension {
+                start,
+                end,
+                code: entry.get(4..7)?.to_string(),
+            })
+        })
+        .collect()
+}
+
+// BHHMMSSDDMMmmmNDDDMMmmmEVPPPPPGGGGG[extensions]
+fn parse_b_record(line: &str, extensions: &[IgcExtension]) -> Option<(u32, IgcFix)> {
+    if line.len() < 35 || !line.is_ascii() {
+        return None;
+    }
+
+    let hours: u32 = line[1..3].parse().ok()?;
+    let minutes: u32 = line[3..5].parse().ok()?;
+    let seconds: u32 = line[5..7].parse().ok()?;
+    if hours > 23 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+    let tod = (hours * 3600 + minutes * 60 + seconds) * 1000;
+
+    let lat = parse_igc_coordinate(&line[7..14], 2, &line[14..15])?;
+    let lon = parse_igc_coordinate(&line[15..23], 3, &line[23..24])?;
+
+    let extensions = extensions
+        .iter()
+        .filter_map(|ext| {
+            let value = line.get(ext.start - 1..ext.end)?;
+            Some((ext.code.clone(), value.to_string()))
+        })
+        .collect();
+
+    Some((
+        tod,
+        IgcFix {
+            lat,
+            lon,
+            timestamp_ms: None,
+            pressure_altitude: line[25..30].parse().ok(),
+            gnss_altitude: line[30..35].parse().ok(),
+            valid: &line[24..25] == "A",
+            extensions,
+        },
+    ))
+}
+
+// DDMMmmm / DDDMMmmm with thousandths of minutes, plus hemisphere
+fn parse_igc_coordinate(value: &str, degree_digits: usize, hemisphere: &str) -> Option<f64> {
+    let degrees: f64 = value[..degree_digits].parse().ok()?;
+    let minutes: f64 = value[degree_digits..degree_digits + 2].parse().ok()?;
+    let thousandths: f64 = value[degree_digits + 2..].parse().ok()?;
+    let minutes = minutes + thousandths / 1000.0;
+    if minutes >= 60.0 {
+        return None;
+    }
+
+    let decimal = degrees + minutes / 60.0;
+    match hemisphere {
+        "N" | "E" => Some(decimal),
+        "S" | "W" => Some(-decimal),
+        _ => None,
+    }
+}
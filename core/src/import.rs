@@ -0,0 +1,348 @@
+use crate::{
+    detect_csv_mapping, is_fit_file, is_igc_file, is_nmea_file, is_valid_coordinate, parse_csv,
+    parse_strava_streams, parse_timestamp_ms, CsvOptions, FitParser, IgcParser, NmeaParser,
+    TrackPoint,
+};
+use flate2::read::MultiGzDecoder;
+use gpx::read;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{Cursor, Read};
+
+// upper bound for decompressed input, guards against gzip bombs
+pub const MAX_DECOMPRESSED_SIZE: usize = 256 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum FileFormat {
+    Gpx,
+    Fit,
+    Igc,
+    Nmea,
+    Csv,
+    StravaStreams,
+}
+
+impl FileFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileFormat::Gpx => "gpx",
+            FileFormat::Fit => "fit",
+            FileFormat::Igc => "igc",
+            FileFormat::Nmea => "nmea",
+            FileFormat::Csv => "csv",
+            FileFormat::StravaStreams => "strava_streams",
+        }
+    }
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    UnknownFormat,
+    Parse { format: FileFormat, message: String },
+    Decompress(String),
+    Archive(String),
+    Encode(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnknownFormat => f.write_str("unrecognized file format"),
+            Error::Parse { format, message } => {
+                write!(f, "failed to parse {format} file: {message}")
+            }
+            Error::Decompress(message) => write!(f, "failed to decompress file: {message}"),
+            Error::Archive(message) => write!(f, "failed to read archive: {message}"),
+            Error::Encode(message) => write!(f, "failed to encode output: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// everything one file yields, independent of the format it came from
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Activity {
+    pub format: FileFormat,
+    pub tracks: Vec<Vec<TrackPoint>>,
+    pub metadata: serde_json::Value,
+    pub warnings: Vec<String>,
+}
+
+impl Activity {
+    pub fn coordinates(&self) -> Vec<Vec<[f64; 2]>> {
+        self.tracks
+            .iter()
+            .map(|track| track.iter().map(|p| [p.lat, p.lon]).collect())
+            .collect()
+    }
+
+    pub fn point_count(&self) -> usize {
+        self.tracks.iter().map(Vec::len).sum()
+    }
+
+    pub fn start_time_ms(&self) -> Option<i64> {
+        self.tracks
+            .iter()
+            .flatten()
+            .filter_map(|p| p.timestamp_ms)
+            .min()
+    }
+
+    pub fn end_time_ms(&self) -> Option<i64> {
+        self.tracks
+            .iter()
+            .flatten()
+            .filter_map(|p| p.timestamp_ms)
+            .max()
+    }
+}
+
+// binary signatures first, then the text formats from most to least specific
+pub fn detect_format(data: &[u8]) -> Option<FileFormat> {
+    if is_fit_file(data) {
+        return Some(FileFormat::Fit);
+    }
+
+    let head = String::from_utf8_lossy(&data[..data.len().min(4096)]);
+    if head.contains("<gpx") {
+        return Some(FileFormat::Gpx);
+    }
+    if is_igc_file(data) {
+        return Some(FileFormat::Igc);
+    }
+    if is_nmea_file(data) {
+        return Some(FileFormat::Nmea);
+    }
+
+    let trimmed = head.trim_start_matches('\u{feff}').trim_start();
+    if (trimmed.starts_with('{') || trimmed.starts_with('[')) && head.contains("\"latlng\"") {
+        return Some(FileFormat::StravaStreams);
+    }
+    if !head.contains('\0') && detect_csv_mapping(data, &CsvOptions::default()).is_ok() {
+        return Some(FileFormat::Csv);
+    }
+
+    None
+}
+
+pub fn is_gzip_file(data: &[u8]) -> bool {
+    data.starts_with(&[0x1f, 0x8b])
+}
+
+pub fn decompress_gzip(data: &[u8], max_size: usize) -> Result<Vec<u8>, Error> {
+    let mut output = Vec::new();
+    // read one byte past the limit so an exactly full buffer isn't mistaken for overflow
+    MultiGzDecoder::new(data)
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut output)
+        .map_err(|e| Error::Decompress(e.to_string()))?;
+
+    if output.len() > max_size {
+        return Err(Error::Decompress(format!(
+            "decompressed size exceeds {max_size} bytes"
+        )));
+    }
+    Ok(output)
+}
+
+// strips a gzip wrapper (.fit.gz, .gpx.gz) when present, otherwise borrows the input
+pub fn decompress_if_gzip(data: &[u8]) -> Result<Cow<'_, [u8]>, Error> {
+    if is_gzip_file(data) {
+        decompress_gzip(data, MAX_DECOMPRESSED_SIZE).map(Cow::Owned)
+    } else {
+        Ok(Cow::Borrowed(data))
+    }
+}
+
+pub fn parse_file(data: &[u8]) -> Result<Activity, Error> {
+    let data = decompress_if_gzip(data)?;
+    let data = data.as_ref();
+    let format = detect_format(data).ok_or(Error::UnknownFormat)?;
+
+    let mut activity = match format {
+        FileFormat::Gpx => parse_gpx_activity(data)?,
+        FileFormat::Fit => parse_fit_activity(data),
+        FileFormat::Igc => parse_igc_activity(data),
+        FileFormat::Nmea => parse_nmea_activity(data),
+        FileFormat::Csv => parse_csv_activity(data)?,
+        FileFormat::StravaStreams => parse_strava_activity(data)?,
+    };
+
+    activity.tracks.retain(|track| !track.is_empty());
+    if activity.tracks.is_empty() {
+        activity
+            .warnings
+            .push("file contains no track points".to_string());
+    }
+
+    Ok(activity)
+}
+
+fn parse_gpx_activity(data: &[u8]) -> Result<Activity, Error> {
+    let gpx = read(Cursor::new(data)).map_err(|e| Error::Parse {
+        format: FileFormat::Gpx,
+        message: e.to_string(),
+    })?;
+
+    let mut tracks = Vec::new();
+    let mut dropped = 0;
+
+    for track in &gpx.tracks {
+        for segment in &track.segments {
+            let mut points = Vec::new();
+
+            for point in &segment.points {
+                // left unrounded, the heatmap pipeline rounds to HeatmapOptions::coordinate_precision
+                let lat = point.point().y();
+                let lon = point.point().x();
+
+                // Validate coordinates to prevent globe-spanning lines
+                if !is_valid_coordinate(lat, lon) {
+                    dropped += 1;
+                    continue;
+                }
+
+                points.push(TrackPoint {
+                    lat,
+                    lon,
+                    elevation: point.elevation,
+                    timestamp_ms: point
+                        .time
+                        .as_ref()
+                        .and_then(|time| time.format().ok())
+                        .and_then(|time| parse_timestamp_ms(&time)),
+                });
+            }
+
+            tracks.push(points);
+        }
+    }
+
+    let mut warnings = Vec::new();
+    if dropped > 0 {
+        warnings.push(format!(
+            "{dropped} points with invalid coordinates were dropped"
+        ));
+    }
+
+    Ok(Activity {
+        format: FileFormat::Gpx,
+        tracks,
+        metadata: serde_json::json!({
+            "name": gpx.metadata.as_ref().and_then(|m| m.name.clone()),
+            "creator": gpx.creator,
+            "version": format!("{:?}", gpx.version),
+            "tracks": gpx.tracks.iter().map(|t| serde_json::json!({
+                "name": t.name,
+                "description": t.description,
+                "segment_count": t.segments.len()
+            })).collect::<Vec<_>>()
+        }),
+        warnings,
+    })
+}
+
+fn parse_fit_activity(data: &[u8]) -> Activity {
+    let mut parser = FitParser::new(data.to_vec());
+    let points = parser.parse_track_points();
+    let start_ms = points.iter().find_map(|point| point.timestamp_ms);
+
+    Activity {
+        format: FileFormat::Fit,
+        tracks: vec![points],
+        metadata: serde_json::json!({ "start_timestamp_ms": start_ms }),
+        warnings: Vec::new(),
+    }
+}
+
+fn parse_igc_activity(data: &[u8]) -> Activity {
+    let mut parser = IgcParser::new(data.to_vec());
+    let flight = parser.parse_flight();
+
+    let mut warnings = Vec::new();
+    if flight.header.date_days.is_none() {
+        warnings.push("missing HFDTE date header, fixes have no timestamps".to_string());
+    }
+
+    Activity {
+        format: FileFormat::Igc,
+        tracks: vec![flight.fixes.iter().map(TrackPoint::from).collect()],
+        metadata: serde_json::json!({
+            "header": flight.header,
+            "extensions": parser.extensions.iter().map(|ext| ext.code.clone()).collect::<Vec<_>>()
+        }),
+        warnings,
+    }
+}
+
+fn parse_nmea_activity(data: &[u8]) -> Activity {
+    let mut parser = NmeaParser::new(data.to_vec());
+    let fixes = parser.parse_fixes();
+
+    let mut warnings = Vec::new();
+    if parser.checksum_errors > 0 {
+        warnings.push(format!(
+            "{} sentences failed checksum validation",
+            parser.checksum_errors
+        ));
+    }
+    if fixes.iter().any(|fix| fix.timestamp_ms.is_none()) {
+        warnings.push("no dated RMC sentence, fixes have no timestamps".to_string());
+    }
+
+    Activity {
+        format: FileFormat::Nmea,
+        tracks: vec![fixes.iter().map(TrackPoint::from).collect()],
+        metadata: serde_json::json!({
+            "sentence_count": parser.sentence_count,
+            "checksum_errors": parser.checksum_errors
+        }),
+        warnings,
+    }
+}
+
+fn parse_csv_activity(data: &[u8]) -> Result<Activity, Error> {
+    let options = CsvOptions::default();
+    let to_error = |message: String| Error::Parse {
+        format: FileFormat::Csv,
+        message,
+    };
+
+    let mapping = detect_csv_mapping(data, &options).map_err(to_error)?;
+    let tracks = parse_csv(data, &options).map_err(to_error)?;
+
+    Ok(Activity {
+        format: FileFormat::Csv,
+        metadata: serde_json::json!({
+            "delimiter": mapping.delimiter.to_string(),
+            "decimal_comma": mapping.decimal_comma,
+            "track_ids": tracks.iter().map(|t| t.id.clone()).collect::<Vec<_>>()
+        }),
+        tracks: tracks.into_iter().map(|track| track.points).collect(),
+        warnings: Vec::new(),
+    })
+}
+
+fn parse_strava_activity(data: &[u8]) -> Result<Activity, Error> {
+    let json = String::from_utf8_lossy(data);
+    let points = parse_strava_streams(&json, None).map_err(|message| Error::Parse {
+        format: FileFormat::StravaStreams,
+        message,
+    })?;
+
+    Ok(Activity {
+        format: FileFormat::StravaStreams,
+        tracks: vec![points.iter().map(TrackPoint::from).collect()],
+        metadata: serde_json::json!({}),
+        warnings: vec!["stream times are relative, points have no timestamps".to_string()],
+    })
+}
@@ -0,0 +1,257 @@
+// kernel density estimation over raw track points. unlike the segment counts this shows where
+// time was spent, stops, starts and finishes stand out because the receiver kept logging points
+// there. the grid is a plain lat/lon raster with square cells in meters around its middle latitude
+use crate::raster::colorize;
+use crate::{RasterImage, RenderOptions};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+// meters, below what a GPS receiver can tell apart. smaller options are raised to these
+pub const MIN_KDE_CELL_SIZE_M: f64 = 1.0;
+pub const MIN_KDE_BANDWIDTH_M: f64 = 1.0;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Kernel {
+    #[default]
+    Gaussian,
+    // zero beyond the bandwidth, so far cheaper on big grids
+    Epanechnikov,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct KdeOptions {
+    pub kernel: Kernel,
+    // standard deviation for the gaussian kernel, radius for epanechnikov
+    pub bandwidth_m: f64,
+    pub cell_size_m: f64,
+    // [min_lat, min_lon, max_lat, max_lon], the points plus the kernel's reach when missing
+    pub bbox: Option<[f64; 4]>,
+    // cells are made larger when the grid would have more than this
+    pub max_cells: usize,
+}
+
+impl Default for KdeOptions {
+    fn default() -> Self {
+        Self {
+            kernel: Kernel::Gaussian,
+            bandwidth_m: 100.0,
+            cell_size_m: 25.0,
+            bbox: None,
+            max_cells: 4_000_000,
+        }
+    }
+}
+
+// row major from the north west corner. values are estimated points per square kilometer
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DensityGrid {
+    pub bbox: [f64; 4],
+    pub columns: u32,
+    pub rows: u32,
+    pub cell_size_m: f64,
+    pub values: Vec<f64>,
+    pub max_value: f64,
+}
+
+impl DensityGrid {
+    pub fn value(&self, column: u32, row: u32) -> f64 {
+        self.values[row as usize * self.columns as usize + column as usize]
+    }
+
+    // [min_lat, min_lon, max_lat, max_lon] of one cell
+    pub fn cell_bounds(&self, column: u32, row: u32) -> [f64; 4] {
+        let [min_lat, min_lon, max_lat, max_lon] = self.bbox;
+        let cell_lat = (max_lat - min_lat) / self.rows.max(1) as f64;
+        let cell_lon = (max_lon - min_lon) / self.columns.max(1) as f64;
+        let north = max_lat - row as f64 * cell_lat;
+        let west = min_lon + column as f64 * cell_lon;
+        [north - cell_lat, west, north, west + cell_lon]
+    }
+
+    pub fn cell_center(&self, column: u32, row: u32) -> [f64; 2] {
+        let [south, west, north, east] = self.cell_bounds(column, row);
+        [(south + north) / 2.0, (west + east) / 2.0]
+    }
+
+    // one pixel per cell, colored like the raster heatmaps. width, height, bbox, padding and
+    // line_width of the options don't apply
+    pub fn to_image(&self, options: &RenderOptions) -> RasterImage {
+        let accumulation: Vec<f32> = self.values.iter().map(|&v| v as f32).collect();
+        let saturation = options.saturation.unwrap_or(self.max_value) as f32;
+
+        RasterImage {
+            width: self.columns,
+            height: self.rows,
+            pixels: colorize(&accumulation, saturation, options),
+        }
+    }
+
+    // a polygon feature with a density property for every cell above min_value
+    pub fn to_geojson(&self, min_value: f64) -> serde_json::Value {
+        let mut features = Vec::new();
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let density = self.value(column, row);
+                if density <= min_value {
+                    continue;
+                }
+
+                let [south, west, north, east] = self.cell_bounds(column, row);
+                features.push(json!({
+                    "type": "Feature",
+                    "geometry": {
+                        "type": "Polygon",
+                        "coordinates": [[
+                            [west, south],
+                            [east, south],
+                            [east, north],
+                            [west, north],
+                            [west, south],
+                        ]]
+                    },
+                    "properties": { "density": density }
+                }));
+            }
+        }
+
+        json!({
+            "type": "FeatureCollection",
+            "features": features
+        })
+    }
+}
+
+pub fn kernel_density_from_tracks(tracks: &[Vec<[f64; 2]>], options: &KdeOptions) -> DensityGrid {
+    let points: Vec<[f64; 2]> = tracks.iter().flatten().copied().collect();
+    kernel_density(&points, options)
+}
+
+pub fn kernel_density(points: &[[f64; 2]], options: &KdeOptions) -> DensityGrid {
+    let points: Vec<[f64; 2]> = points
+        .iter()
+        .copied()
+        .filter(|p| p[0].is_finite() && p[1].is_finite())
+        .collect();
+    let bandwidth = options.bandwidth_m.max(MIN_KDE_BANDWIDTH_M);
+    let reach = match options.kernel {
+        Kernel::Gaussian => 3.0 * bandwidth,
+        Kernel::Epanechnikov => bandwidth,
+    };
+
+    let bbox = match options.bbox {
+        Some(bbox) => bbox,
+        None if points.is_empty() => {
+            return DensityGrid {
+                bbox: [0.0; 4],
+                columns: 0,
+                rows: 0,
+                cell_size_m: options.cell_size_m,
+                values: Vec::new(),
+                max_value: 0.0,
+            }
+        }
+        None => {
+            let mut bbox = [f64::MAX, f64::MAX, f64::MIN, f64::MIN];
+            for &[lat, lon] in &points {
+                bbox = [
+                    bbox[0].min(lat),
+                    bbox[1].min(lon),
+                    bbox[2].max(lat),
+                    bbox[3].max(lon),
+                ];
+            }
+            let middle = (bbox[0] + bbox[2]) / 2.0;
+            let reach_lat = reach / METERS_PER_DEGREE;
+            let reach_lon = reach / meters_per_degree_lon(middle);
+            [
+                bbox[0] - reach_lat,
+                bbox[1] - reach_lon,
+                bbox[2] + reach_lat,
+                bbox[3] + reach_lon,
+            ]
+        }
+    };
+
+    let [min_lat, min_lon, max_lat, max_lon] = bbox;
+    let meters_lon = meters_per_degree_lon((min_lat + max_lat) / 2.0);
+    let extent = [
+        ((max_lon - min_lon) * meters_lon).max(0.0),
+        ((max_lat - min_lat) * METERS_PER_DEGREE).max(0.0),
+    ];
+
+    // counted in f64, a continent at a fine cell size overflows a 32-bit usize
+    let max_cells = options.max_cells.max(1) as f64;
+    let mut cell = options.cell_size_m.max(MIN_KDE_CELL_SIZE_M);
+    let size = |cell: f64| {
+        (
+            (extent[0] / cell).ceil().max(1.0),
+            (extent[1] / cell).ceil().max(1.0),
+        )
+    };
+    let (mut columns, mut rows) = size(cell);
+    while columns * rows > max_cells {
+        cell *= (columns * rows / max_cells).sqrt().max(1.01);
+        (columns, rows) = size(cell);
+    }
+    let (columns, rows) = (columns as usize, rows as usize);
+
+    // anchored at the north west corner, the grid ends up covering at least the bbox
+    let bbox = [
+        max_lat - rows as f64 * cell / METERS_PER_DEGREE,
+        min_lon,
+        max_lat,
+        min_lon + columns as f64 * cell / meters_lon,
+    ];
+
+    // per square kilometer, so the sum over all cells times their area is the point count
+    let norm = match options.kernel {
+        Kernel::Gaussian => 1e6 / (2.0 * std::f64::consts::PI * bandwidth * bandwidth),
+        Kernel::Epanechnikov => 1e6 * 2.0 / (std::f64::consts::PI * bandwidth * bandwidth),
+    };
+    let kernel = |distance_squared: f64| match options.kernel {
+        Kernel::Gaussian => norm * (-distance_squared / (2.0 * bandwidth * bandwidth)).exp(),
+        Kernel::Epanechnikov => norm * (1.0 - distance_squared / (bandwidth * bandwidth)).max(0.0),
+    };
+
+    let mut values = vec![0.0; columns * rows];
+    let reach_cells = reach / cell;
+    for &[lat, lon] in &points {
+        // position in cells from the north west corner
+        let x = (lon - min_lon) * meters_lon / cell;
+        let y = (max_lat - lat) * METERS_PER_DEGREE / cell;
+
+        let first_row = (y - reach_cells).floor().max(0.0) as usize;
+        let last_row = ((y + reach_cells).ceil().max(0.0) as usize).min(rows);
+        let first_column = (x - reach_cells).floor().max(0.0) as usize;
+        let last_column = ((x + reach_cells).ceil().max(0.0) as usize).min(columns);
+
+        for row in first_row..last_row {
+            let dy = (row as f64 + 0.5 - y) * cell;
+            for column in first_column..last_column {
+                let dx = (column as f64 + 0.5 - x) * cell;
+                let distance_squared = dx * dx + dy * dy;
+                if distance_squared <= reach * reach {
+                    values[row * columns + column] += kernel(distance_squared);
+                }
+            }
+        }
+    }
+
+    let max_value = values.iter().copied().fold(0.0, f64::max);
+    DensityGrid {
+        bbox,
+        columns: columns as u32,
+        rows: rows as u32,
+        cell_size_m: cell,
+        values,
+        max_value,
+    }
+}
+
+fn meters_per_degree_lon(lat: f64) -> f64 {
+    (METERS_PER_DEGREE * lat.to_radians().cos()).max(1.0)
+}
@@ -1,26 +1,69 @@
-#[cfg(target_arch = "wasm32")]
-use gpx::read;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-#[cfg(target_arch = "wasm32")]
-use std::io::Cursor;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+mod antimeridian;
+mod archive;
+mod coverage;
+mod csv;
+mod geohash;
+mod grid;
+mod heatmap;
+mod hexbin;
+mod igc;
+mod import;
+mod kde;
+mod mvt;
+mod nmea;
+mod pmtiles;
+mod projection;
+mod raster;
+mod strava;
+mod tiles;
+
+pub use antimeridian::*;
+pub use archive::*;
+pub use coverage::*;
+pub use csv::*;
+pub use geohash::*;
+pub use grid::*;
+pub use heatmap::*;
+pub use hexbin::*;
+pub use igc::*;
+pub use import::*;
+pub use kde::*;
+pub use mvt::*;
+pub use nmea::*;
+pub use pmtiles::*;
+pub use projection::*;
+pub use raster::*;
+pub use strava::*;
+pub use tiles::*;
+
 // DATA STRUCTURES
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct HeatmapTrack {
     pub coordinates: Vec<[f64; 2]>,
     pub frequency: u32,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct HeatmapResult {
     pub tracks: Vec<HeatmapTrack>,
     pub max_frequency: u32,
 }
 
+// a single time-aware track point shared by the text based importers
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TrackPoint {
+    pub lat: f64,
+    pub lon: f64,
+    pub elevation: Option<f64>,
+    pub timestamp_ms: Option<i64>, // unix epoch millis
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ValidationResult {
     valid_count: u32,
@@ -145,23 +188,18 @@ pub fn decode_polyline_string(encoded: &str) -> JsValue {
 
 // polyline processingstep
 pub fn process_polyline(polyline_str: &str) -> Vec<[f64; 2]> {
+    filter_unrealistic_jumps(&parse_polyline_coordinates(polyline_str))
+}
+
+// raw coordinates of a polyline without any filtering
+fn parse_polyline_coordinates(polyline_str: &str) -> Vec<[f64; 2]> {
     // First try to parse as JSON (RideWithGPS format)
     if let Ok(json_coords) = serde_json::from_str::<Vec<[f64; 2]>>(polyline_str) {
-        // It's a JSON array of coordinates
-        return if !json_coords.is_empty() {
-            filter_unrealistic_jumps(&json_coords)
-        } else {
-            Vec::new()
-        };
+        return json_coords;
     }
 
     // If JSON parsing fails, treat as encoded polyline (Strava format)
-    let coords = decode_polyline(polyline_str);
-    if !coords.is_empty() {
-        filter_unrealistic_jumps(&coords)
-    } else {
-        Vec::new()
-    }
+    decode_polyline(polyline_str)
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -187,200 +225,359 @@ pub fn process_polylines(polylines: js_sys::Array) -> JsValue {
     serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
 }
 
-// Helper function to create heatmap from coordinate arrays
-pub fn create_heatmap_from_tracks(all_tracks: Vec<Vec<[f64; 2]>>) -> HeatmapResult {
-    // Create a segment usage map to count overlapping segments
-    let mut segment_usage: HashMap<String, u32> = HashMap::new();
-
-    // Break each track into segments and count usage
-    for track in &all_tracks {
-        for window in track.windows(2) {
-            if let [start, end] = window {
-                let segment_key = create_segment_key(*start, *end);
-                *segment_usage.entry(segment_key).or_insert(0) += 1;
-            }
-        }
-    }
+// options is a partial HeatmapOptions object, missing fields keep their defaults
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn process_polylines_with_options(polylines: js_sys::Array, options: JsValue) -> JsValue {
+    let options = heatmap_options_from_js(options);
+    let polylines: Vec<String> = polylines.iter().filter_map(|p| p.as_string()).collect();
 
-    // Calculate frequency for each track based on its segments
-    let mut heatmap_tracks = Vec::new();
+    let result = process_polylines_with_options_rust(&polylines, &options);
+    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+}
 
-    for track in all_tracks {
-        if track.len() < 2 {
-            continue;
-        }
+pub fn process_polylines_with_options_rust(
+    polylines: &[String],
+    options: &HeatmapOptions,
+) -> HeatmapResult {
+    let tracks = polylines
+        .iter()
+        .map(|polyline| parse_polyline_coordinates(polyline))
+        .collect();
 
-        // Calculate track frequency as the average frequency of its segments
-        let mut total_usage = 0;
-        let mut segment_count = 0;
+    process_tracks_with_options(tracks, options)
+}
 
-        for window in track.windows(2) {
-            if let [start, end] = window {
-                let segment_key = create_segment_key(*start, *end);
-                if let Some(&usage) = segment_usage.get(&segment_key) {
-                    total_usage += usage;
-                    segment_count += 1;
-                }
-            }
-        }
+// jump filtering, rounding and simplification followed by counting, all driven by options
+pub fn process_tracks_with_options(
+    tracks: Vec<Vec<[f64; 2]>>,
+    options: &HeatmapOptions,
+) -> HeatmapResult {
+    let mut builder = HeatmapBuilder::with_options(options.clone());
+    builder.add_tracks(
+        tracks
+            .iter()
+            .filter_map(|track| prepare_heatmap_track(track, options)),
+    );
+    builder.build()
+}
+
+// per-segment output driven by options, the only way to get directional counts
+pub fn process_tracks_by_segment_with_options(
+    tracks: Vec<Vec<[f64; 2]>>,
+    options: &HeatmapOptions,
+) -> SegmentHeatmapResult {
+    let mut builder = HeatmapBuilder::with_options(options.clone());
+    builder.add_tracks(
+        tracks
+            .iter()
+            .filter_map(|track| prepare_heatmap_track(track, options)),
+    );
+    builder.build_segments()
+}
 
-        // Use average usage, with minimum of 1
-        let track_frequency = if segment_count > 0 {
-            (total_usage as f64 / segment_count as f64).round() as u32
-        } else {
-            1
+// activities are a list of {tracks, weight, timestamp_ms}, weight defaults to 1 and
+// timestamp_ms is only needed for recency_decay in options
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn process_weighted_activities(activities: JsValue, options: JsValue) -> JsValue {
+    let options = heatmap_options_from_js(options);
+    let activities: Vec<WeightedActivity> = match serde_wasm_bindgen::from_value(activities) {
+        Ok(activities) => activities,
+        Err(_) => return JsValue::NULL,
+    };
+
+    let result = process_weighted_activities_rust(activities, &options);
+    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn process_weighted_activities_by_segment(activities: JsValue, options: JsValue) -> JsValue {
+    let options = heatmap_options_from_js(options);
+    let activities: Vec<WeightedActivity> = match serde_wasm_bindgen::from_value(activities) {
+        Ok(activities) => activities,
+        Err(_) => return JsValue::NULL,
+    };
+
+    let result = process_weighted_activities_by_segment_rust(activities, &options);
+    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+}
+
+pub fn process_weighted_activities_rust(
+    activities: Vec<WeightedActivity>,
+    options: &HeatmapOptions,
+) -> HeatmapResult {
+    weighted_builder(activities, options).build()
+}
+
+pub fn process_weighted_activities_by_segment_rust(
+    activities: Vec<WeightedActivity>,
+    options: &HeatmapOptions,
+) -> SegmentHeatmapResult {
+    weighted_builder(activities, options).build_segments()
+}
+
+fn weighted_builder(activities: Vec<WeightedActivity>, options: &HeatmapOptions) -> HeatmapBuilder {
+    let mut builder = HeatmapBuilder::with_options(options.clone());
+    for activity in activities {
+        builder.add_weighted_activity(
+            activity
+                .tracks
+                .iter()
+                .filter_map(|track| prepare_heatmap_track(track, options)),
+            activity.weight,
+            activity.timestamp_ms,
+        );
+    }
+    builder
+}
+
+#[cfg(target_arch = "wasm32")]
+fn heatmap_options_from_js(options: JsValue) -> HeatmapOptions {
+    if options.is_undefined() || options.is_null() {
+        return HeatmapOptions::default();
+    }
+    serde_wasm_bindgen::from_value(options).unwrap_or_default()
+}
+
+// full resolution Strava activity streams instead of the coarse summary_polyline,
+// each entry is either the streams JSON string or the already parsed object
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn process_strava_streams(activities: js_sys::Array) -> JsValue {
+    let mut all_tracks: Vec<Vec<[f64; 2]>> = Vec::new();
+
+    for activity in activities.iter() {
+        let points = match activity.as_string() {
+            Some(json) => parse_strava_streams(&json, None),
+            None => match serde_wasm_bindgen::from_value::<serde_json::Value>(activity) {
+                Ok(value) => strava_streams_from_value(&value, None),
+                Err(_) => continue,
+            },
         };
 
-        heatmap_tracks.push(HeatmapTrack {
-            coordinates: track,
-            frequency: track_frequency,
-        });
+        if let Ok(points) = points {
+            let coords: Vec<[f64; 2]> = points.iter().map(|p| [p.lat, p.lon]).collect();
+            if let Some(track) = prepare_heatmap_track(&coords, &HeatmapOptions::default()) {
+                all_tracks.push(track);
+            }
+        }
     }
 
-    // Find the maximum frequency for normalization
-    let max_frequency = heatmap_tracks
-        .iter()
-        .map(|track| track.frequency)
-        .max()
-        .unwrap_or(1);
+    let result = create_heatmap_from_tracks(all_tracks);
+
+    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+}
 
-    HeatmapResult {
-        tracks: heatmap_tracks,
-        max_frequency,
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn strava_streams_to_track(streams_json: &str, start_time_ms: Option<f64>) -> JsValue {
+    match parse_strava_streams(streams_json, start_time_ms.map(|ms| ms as i64)) {
+        Ok(points) => serde_wasm_bindgen::to_value(&points).unwrap_or(JsValue::NULL),
+        Err(_) => JsValue::NULL,
     }
 }
 
+// Helper function to create heatmap from coordinate arrays
+pub fn create_heatmap_from_tracks(all_tracks: Vec<Vec<[f64; 2]>>) -> HeatmapResult {
+    let mut builder = HeatmapBuilder::new();
+    builder.add_tracks(all_tracks);
+    builder.build()
+}
+
+// same counting as create_heatmap_from_tracks, but colored per segment run
+pub fn create_segment_heatmap_from_tracks(all_tracks: Vec<Vec<[f64; 2]>>) -> SegmentHeatmapResult {
+    let mut builder = HeatmapBuilder::new();
+    builder.add_tracks(all_tracks);
+    builder.build_segments()
+}
+
+// same counting as create_heatmap_from_tracks, gathered into geohash cells
+pub fn create_geohash_heatmap_from_tracks(
+    all_tracks: Vec<Vec<[f64; 2]>>,
+    precision: usize,
+) -> GeohashResult {
+    let mut builder = HeatmapBuilder::new();
+    builder.add_tracks(all_tracks);
+    builder.build_geohash(precision)
+}
+
+pub fn process_tracks_by_geohash_with_options(
+    tracks: Vec<Vec<[f64; 2]>>,
+    precision: usize,
+    options: &HeatmapOptions,
+) -> GeohashResult {
+    let mut builder = HeatmapBuilder::with_options(options.clone());
+    builder.add_tracks(
+        tracks
+            .iter()
+            .filter_map(|track| prepare_heatmap_track(track, options)),
+    );
+    builder.build_geohash(precision)
+}
+
 pub fn round(value: f64) -> f64 {
-    (value * 100000.0).round() / 100000.0
+    round_to(value, 5)
+}
+
+pub fn round_to(value: f64, decimals: u32) -> f64 {
+    let factor = 10f64.powi(decimals as i32);
+    (value * factor).round() / factor
 }
 
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub fn process_gpx_files(files: js_sys::Array) -> JsValue {
-    let mut all_tracks: Vec<Vec<[f64; 2]>> = Vec::new();
+    let mut all_files: Vec<Vec<u8>> = Vec::new();
 
-    // Parse all GPX and FIT files and extract tracks
     for file_bytes in files.iter() {
         let array = js_sys::Uint8Array::new(&file_bytes);
-        let bytes = array.to_vec();
+        all_files.push(array.to_vec());
+    }
 
-        // Try to parse as GPX first
-        if let Ok(gpx) = read(Cursor::new(&bytes)) {
-            for track in gpx.tracks {
-                for segment in track.segments {
-                    let mut track_coords = Vec::new();
+    let result = process_gpx_files_rust(&all_files);
 
-                    for point in segment.points {
-                        let lat = round(point.point().y());
-                        let lon = round(point.point().x());
+    serde_wasm_bindgen::to_value(&result).unwrap()
+}
 
-                        // Validate coordinates to prevent globe-spanning lines
-                        if is_valid_coordinate(lat, lon) {
-                            track_coords.push([lat, lon]);
-                        }
-                    }
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn process_gpx_files_with_options(files: js_sys::Array, options: JsValue) -> JsValue {
+    let options = heatmap_options_from_js(options);
+    let all_files: Vec<Vec<u8>> = files
+        .iter()
+        .map(|file_bytes| js_sys::Uint8Array::new(&file_bytes).to_vec())
+        .collect();
 
-                    if track_coords.len() > 1 {
-                        // Filter out tracks with unrealistic jumps
-                        let filtered_coords = filter_unrealistic_jumps(&track_coords);
+    let result = process_gpx_files_with_options_rust(&all_files, &options);
+    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+}
 
-                        if filtered_coords.len() > 1 {
-                            // Less aggressive simplification to preserve track shape
-                            let simplified = simplify_track(&filtered_coords, 0.00005);
-                            if simplified.len() > 1 {
-                                all_tracks.push(simplified);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        // Try to parse as FIT file if GPX parsing fails
-        else if is_fit_file(&bytes) {
-            // Custom FIT file parser for extracting GPS coordinates
-            let mut fit_parser = FitParser::new(bytes);
-            let fit_coordinates = fit_parser.parse_gps_coordinates();
-
-            // Apply the same validation and filtering as GPX
-            if fit_coordinates.len() > 1 {
-                let filtered_coords = filter_unrealistic_jumps(&fit_coordinates);
-
-                if filtered_coords.len() > 1 {
-                    let simplified = simplify_track(&filtered_coords, 0.00005);
-                    if simplified.len() > 1 {
-                        all_tracks.push(simplified);
-                    }
-                }
-            }
-        }
-        // Skip files that aren't GPX or FIT
-        else {
-            continue;
-        }
-    }
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn process_gpx_files_by_segment(files: js_sys::Array) -> JsValue {
+    let all_files: Vec<Vec<u8>> = files
+        .iter()
+        .map(|file_bytes| js_sys::Uint8Array::new(&file_bytes).to_vec())
+        .collect();
 
-    // create segment usage map to count overlapping segments
-    let mut segment_usage: HashMap<String, u32> = HashMap::new();
+    let result = process_gpx_files_by_segment_rust(&all_files);
 
-    // break each track into segments and count usage
-    for track in &all_tracks {
-        for window in track.windows(2) {
-            if let [start, end] = window {
-                let segment_key = create_segment_key(*start, *end);
-                *segment_usage.entry(segment_key).or_insert(0) += 1;
-            }
+    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn process_gpx_files_by_segment_with_options(
+    files: js_sys::Array,
+    options: JsValue,
+) -> JsValue {
+    let options = heatmap_options_from_js(options);
+    let all_files: Vec<Vec<u8>> = files
+        .iter()
+        .map(|file_bytes| js_sys::Uint8Array::new(&file_bytes).to_vec())
+        .collect();
+
+    let result = process_gpx_files_by_segment_with_options_rust(&all_files, &options);
+    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+}
+
+// per-segment heatmap for tracks that were already decoded and cleaned up
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn create_segment_heatmap(tracks: js_sys::Array) -> JsValue {
+    let mut track_list = Vec::new();
+
+    for i in 0..tracks.length() {
+        if let Ok(track) = serde_wasm_bindgen::from_value::<Vec<[f64; 2]>>(tracks.get(i)) {
+            track_list.push(track);
         }
     }
 
-    // calculate frequency for each track based on its segments
-    let mut heatmap_tracks = Vec::new();
-    let mut max_frequency = 0;
+    let result = create_segment_heatmap_from_tracks(track_list);
+    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+}
 
-    for track in all_tracks {
-        if track.len() < 2 {
-            continue;
-        }
+// heatmap from raw file contents in any supported format, unreadable files are skipped
+pub fn process_gpx_files_rust(files: &[Vec<u8>]) -> HeatmapResult {
+    process_gpx_files_with_options_rust(files, &HeatmapOptions::default())
+}
 
-        // calculate track frequency as the average frequency of its segments
-        let mut total_usage = 0;
-        let mut segment_count = 0;
+pub fn process_gpx_files_with_options_rust(
+    files: &[Vec<u8>],
+    options: &HeatmapOptions,
+) -> HeatmapResult {
+    let mut builder = HeatmapBuilder::with_options(options.clone());
+    add_file_activities(&mut builder, files, options);
+    builder.build()
+}
 
-        for window in track.windows(2) {
-            if let [start, end] = window {
-                let segment_key = create_segment_key(*start, *end);
-                if let Some(&usage) = segment_usage.get(&segment_key) {
-                    total_usage += usage;
-                    segment_count += 1;
-                }
-            }
-        }
+pub fn process_gpx_files_by_segment_rust(files: &[Vec<u8>]) -> SegmentHeatmapResult {
+    process_gpx_files_by_segment_with_options_rust(files, &HeatmapOptions::default())
+}
 
-        // Use average usage, with minimum of 1
-        let track_frequency = if segment_count > 0 {
-            (total_usage as f64 / segment_count as f64).round() as u32
-        } else {
-            1
+pub fn process_gpx_files_by_segment_with_options_rust(
+    files: &[Vec<u8>],
+    options: &HeatmapOptions,
+) -> SegmentHeatmapResult {
+    let mut builder = HeatmapBuilder::with_options(options.clone());
+    add_file_activities(&mut builder, files, options);
+    builder.build_segments()
+}
+
+// every readable file is one activity, all of its tracks count towards it
+fn add_file_activities(builder: &mut HeatmapBuilder, files: &[Vec<u8>], options: &HeatmapOptions) {
+    for bytes in files {
+        let activity = match parse_file(bytes) {
+            Ok(activity) => activity,
+            Err(_) => continue,
         };
 
-        max_frequency = max_frequency.max(track_frequency);
+        builder.add_weighted_activity(
+            activity
+                .coordinates()
+                .iter()
+                .filter_map(|track| prepare_heatmap_track(track, options)),
+            1.0,
+            activity.start_time_ms(),
+        );
+    }
+}
 
-        heatmap_tracks.push(HeatmapTrack {
-            coordinates: track,
-            frequency: track_frequency,
-        });
+// jump filtering, rounding and simplification applied to every parsed track before counting
+fn prepare_heatmap_track(coords: &[[f64; 2]], options: &HeatmapOptions) -> Option<Vec<[f64; 2]>> {
+    if coords.len() < 2 {
+        return None;
     }
 
-    let result = HeatmapResult {
-        tracks: heatmap_tracks,
-        max_frequency,
-    };
+    let filtered_coords: Vec<[f64; 2]> = filter_unrealistic_jumps_with(coords, options.max_jump_km)
+        .into_iter()
+        .map(|[lat, lon]| {
+            [
+                round_to(lat, options.coordinate_precision),
+                round_to(lon, options.coordinate_precision),
+            ]
+        })
+        .collect();
+    if filtered_coords.len() < 2 {
+        return None;
+    }
 
-    serde_wasm_bindgen::to_value(&result).unwrap()
+    let simplified = simplify_track(&filtered_coords, options.simplify_tolerance);
+    if simplified.len() > 1 {
+        Some(simplified)
+    } else {
+        None
+    }
 }
 
+// human readable segment key, the heatmap itself counts packed segment_id values
 pub fn create_segment_key(start: [f64; 2], end: [f64; 2]) -> String {
-    // change to a larger tolerance for less aggressive matching
-    let tolerance = 0.001; // About 100 meters
+    segment_key(start, end, HeatmapOptions::default().grid_tolerance)
+}
+
+pub fn segment_key(start: [f64; 2], end: [f64; 2], tolerance: f64) -> String {
     let snap_start = snap_to_grid(start, tolerance);
     let snap_end = snap_to_grid(end, tolerance);
 
@@ -391,7 +588,12 @@ pub fn create_segment_key(start: [f64; 2], end: [f64; 2]) -> String {
         (snap_end, snap_start)
     };
 
-    format!("{:.4},{:.4}-{:.4},{:.4}", p1[0], p1[1], p2[0], p2[1])
+    // one decimal more than the grid resolution so neighbouring cells never print the same
+    let decimals = ((-tolerance.log10()).ceil().max(0.0) as usize + 1).max(4);
+    format!(
+        "{:.*},{:.*}-{:.*},{:.*}",
+        decimals, p1[0], decimals, p1[1], decimals, p2[0], decimals, p2[1]
+    )
 }
 
 pub fn snap_to_grid(point: [f64; 2], tolerance: f64) -> [f64; 2] {
@@ -449,12 +651,15 @@ pub fn is_valid_coordinate(lat: f64, lon: f64) -> bool {
 }
 
 pub fn filter_unrealistic_jumps(coords: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    filter_unrealistic_jumps_with(coords, HeatmapOptions::default().max_jump_km)
+}
+
+pub fn filter_unrealistic_jumps_with(coords: &[[f64; 2]], max_jump_km: f64) -> Vec<[f64; 2]> {
     if coords.len() <= 1 {
         return coords.to_vec();
     }
 
     let mut filtered = vec![coords[0]];
-    let max_jump_km = 100.0;
     let mut consecutive_bad_points = 0;
     const MAX_CONSECUTIVE_BAD: usize = 10; // Allow up to 10 consecutive bad points
 
@@ -558,8 +763,13 @@ pub struct FitParser {
     pub data: Vec<u8>,
     pub pos: usize,
     pub message_definitions: HashMap<u8, MessageDefinition>,
+    // raw timestamp of the last record, compressed timestamp headers count on from it
+    pub last_timestamp: Option<u32>,
 }
 
+// seconds from the unix epoch to the FIT epoch, 1989-12-31T00:00:00Z
+const FIT_EPOCH_OFFSET_S: i64 = 631_065_600;
+
 #[derive(Clone)]
 pub struct MessageDefinition {
     pub global_message_number: u16,
@@ -579,6 +789,7 @@ impl FitParser {
             data,
             pos: 0,
             message_definitions: HashMap::new(),
+            last_timestamp: None,
         }
     }
 
@@ -636,7 +847,17 @@ impl FitParser {
         self.pos = (self.pos + bytes).min(self.data.len());
     }
 
+    // rounded to 5 decimals like it always was, parse_track_points keeps full precision
     pub fn parse_gps_coordinates(&mut self) -> Vec<[f64; 2]> {
+        self.parse_track_points()
+            .iter()
+            .map(|point| [round(point.lat), round(point.lon)])
+            .collect()
+    }
+
+    // record positions with their timestamps and altitude, lap and session positions only
+    // carry the coordinates. left unrounded for prepare_heatmap_track
+    pub fn parse_track_points(&mut self) -> Vec<TrackPoint> {
         let mut coordinates = Vec::new();
 
         // Check FIT file header
@@ -707,8 +928,19 @@ impl FitParser {
                 None => break, // End of data
             };
 
-            let is_definition = (record_header & 0x40) != 0;
-            let local_message_type = record_header & 0x0F;
+            // a compressed timestamp header holds the local type in bits 5-6 and the low five
+            // bits of the timestamp, which count on from the last full one
+            let (is_definition, local_message_type, header_timestamp) = if record_header & 0x80 != 0
+            {
+                let offset = (record_header & 0x1F) as u32;
+                let timestamp = self.last_timestamp.map(|last| {
+                    let rolled = if offset >= last & 0x1F { 0 } else { 0x20 };
+                    (last & !0x1F) + offset + rolled
+                });
+                (false, (record_header >> 5) & 0x03, timestamp)
+            } else {
+                (record_header & 0x40 != 0, record_header & 0x0F, None)
+            };
 
             let parse_success = if is_definition {
                 // Parse definition message
@@ -742,27 +974,39 @@ impl FitParser {
                     match definition.global_message_number {
                         20 => {
                             // Record message (primary GPS data)
-                            if let Some(coord) = self.parse_record_message(&definition) {
-                                if is_valid_coordinate(coord[0], coord[1]) {
-                                    coordinates.push(coord);
+                            if let Some(point) =
+                                self.parse_record_point(&definition, header_timestamp)
+                            {
+                                if is_valid_coordinate(point.lat, point.lon) {
+                                    coordinates.push(point);
                                 }
                             }
                             true
                         }
                         19 => {
                             // Lap message (might contain GPS data)
-                            if let Some(coord) = self.parse_flexible_gps_message(&definition) {
-                                if is_valid_coordinate(coord[0], coord[1]) {
-                                    coordinates.push(coord);
+                            if let Some([lat, lon]) = self.flexible_gps_position(&definition) {
+                                if is_valid_coordinate(lat, lon) {
+                                    coordinates.push(TrackPoint {
+                                        lat,
+                                        lon,
+                                        elevation: None,
+                                        timestamp_ms: None,
+                                    });
                                 }
                             }
                             true
                         }
                         18 => {
                             // Session message (might contain GPS data)
-                            if let Some(coord) = self.parse_flexible_gps_message(&definition) {
-                                if is_valid_coordinate(coord[0], coord[1]) {
-                                    coordinates.push(coord);
+                            if let Some([lat, lon]) = self.flexible_gps_position(&definition) {
+                                if is_valid_coordinate(lat, lon) {
+                                    coordinates.push(TrackPoint {
+                                        lat,
+                                        lon,
+                                        elevation: None,
+                                        timestamp_ms: None,
+                                    });
                                 }
                             }
                             true
@@ -870,8 +1114,21 @@ impl FitParser {
     }
 
     pub fn parse_record_message(&mut self, definition: &MessageDefinition) -> Option<[f64; 2]> {
+        self.parse_record_point(definition, None)
+            .map(|point| [round(point.lat), round(point.lon)])
+    }
+
+    // header_timestamp comes from a compressed timestamp header, a timestamp field wins over it
+    pub fn parse_record_point(
+        &mut self,
+        definition: &MessageDefinition,
+        header_timestamp: Option<u32>,
+    ) -> Option<TrackPoint> {
         let mut lat: Option<f64> = None;
         let mut lon: Option<f64> = None;
+        let mut altitude: Option<f64> = None;
+        let mut enhanced_altitude: Option<f64> = None;
+        let mut timestamp = header_timestamp;
 
         for field in &definition.fields {
             // More defensive bounds checking
@@ -885,35 +1142,51 @@ impl FitParser {
                 continue;
             }
 
-            match field.field_def_num {
-                0 => {
+            match (field.field_def_num, field.size) {
+                (0, 4) => {
                     // Latitude field
-                    if field.size == 4 {
-                        if let Some(lat_raw) = self.read_i32_le() {
-                            if lat_raw != 0x7FFFFFFF && lat_raw != 0 {
-                                let lat_degrees = lat_raw as f64 * (180.0 / 2147483648.0);
-                                if lat_degrees.abs() <= 90.0 {
-                                    lat = Some(lat_degrees);
-                                }
+                    if let Some(lat_raw) = self.read_i32_le() {
+                        if lat_raw != 0x7FFFFFFF && lat_raw != 0 {
+                            let lat_degrees = lat_raw as f64 * (180.0 / 2147483648.0);
+                            if lat_degrees.abs() <= 90.0 {
+                                lat = Some(lat_degrees);
                             }
                         }
-                    } else {
-                        self.skip(field.size as usize);
                     }
                 }
-                1 => {
+                (1, 4) => {
                     // Longitude field
-                    if field.size == 4 {
-                        if let Some(lon_raw) = self.read_i32_le() {
-                            if lon_raw != 0x7FFFFFFF && lon_raw != 0 {
-                                let lon_degrees = lon_raw as f64 * (180.0 / 2147483648.0);
-                                if lon_degrees.abs() <= 180.0 {
-                                    lon = Some(lon_degrees);
-                                }
+                    if let Some(lon_raw) = self.read_i32_le() {
+                        if lon_raw != 0x7FFFFFFF && lon_raw != 0 {
+                            let lon_degrees = lon_raw as f64 * (180.0 / 2147483648.0);
+                            if lon_degrees.abs() <= 180.0 {
+                                lon = Some(lon_degrees);
                             }
                         }
-                    } else {
-                        self.skip(field.size as usize);
+                    }
+                }
+                (2, 2) => {
+                    // altitude, 5 per meter from -500 m
+                    if let Some(raw) = self.read_u16_le() {
+                        if raw != 0xFFFF {
+                            altitude = Some(raw as f64 / 5.0 - 500.0);
+                        }
+                    }
+                }
+                (78, 4) => {
+                    // enhanced_altitude, same scale with more range
+                    if let Some(raw) = self.read_u32_le() {
+                        if raw != 0xFFFFFFFF {
+                            enhanced_altitude = Some(raw as f64 / 5.0 - 500.0);
+                        }
+                    }
+                }
+                (253, 4) => {
+                    // timestamp, seconds since the FIT epoch
+                    if let Some(raw) = self.read_u32_le() {
+                        if raw != 0xFFFFFFFF {
+                            timestamp = Some(raw);
+                        }
                     }
                 }
                 _ => {
@@ -923,8 +1196,20 @@ impl FitParser {
             }
         }
 
+        if timestamp.is_some() {
+            self.last_timestamp = timestamp;
+        }
+
         if let (Some(lat_val), Some(lon_val)) = (lat, lon) {
-            Some([round(lat_val), round(lon_val)])
+            Some(TrackPoint {
+                lat: lat_val,
+                lon: lon_val,
+                elevation: enhanced_altitude.or(altitude),
+                // smaller values count from when the device was switched on, not a date
+                timestamp_ms: timestamp
+                    .filter(|&raw| raw >= 0x10000000)
+                    .map(|raw| (raw as i64 + FIT_EPOCH_OFFSET_S) * 1000),
+            })
         } else {
             None
         }
@@ -934,6 +1219,11 @@ impl FitParser {
         &mut self,
         definition: &MessageDefinition,
     ) -> Option<[f64; 2]> {
+        self.flexible_gps_position(definition)
+            .map(|[lat, lon]| [round(lat), round(lon)])
+    }
+
+    fn flexible_gps_position(&mut self, definition: &MessageDefinition) -> Option<[f64; 2]> {
         let mut lat: Option<f64> = None;
         let mut lon: Option<f64> = None;
         let mut potential_coords = Vec::new();
@@ -976,7 +1266,7 @@ impl FitParser {
         }
 
         if let (Some(lat_val), Some(lon_val)) = (lat, lon) {
-            Some([round(lat_val), round(lon_val)])
+            Some([lat_val, lon_val])
         } else {
             None
         }
@@ -1057,14 +1347,13 @@ pub fn filter_coordinates_by_bounds(coords: js_sys::Array, bounds: js_sys::Array
     let max_lat = bounds.get(2).as_f64().unwrap_or(90.0);
     let max_lon = bounds.get(3).as_f64().unwrap_or(180.0);
 
+    let bbox = [min_lat, min_lon, max_lat, max_lon];
     let mut filtered = Vec::new();
 
     for i in 0..coords.length() {
         if let Ok(coord_array) = serde_wasm_bindgen::from_value::<[f64; 2]>(coords.get(i)) {
-            let [lat, lon] = coord_array;
-
-            if lat >= min_lat && lat <= max_lat && lon >= min_lon && lon <= max_lon {
-                filtered.push([lat, lon]);
+            if bbox_contains(bbox, coord_array) {
+                filtered.push(coord_array);
             }
         }
     }
@@ -1072,13 +1361,11 @@ pub fn filter_coordinates_by_bounds(coords: js_sys::Array, bounds: js_sys::Array
     serde_wasm_bindgen::to_value(&filtered).unwrap_or(JsValue::NULL)
 }
 
-// bounds filtering
+// bounds filtering, bounds with min_lng > max_lng span the antimeridian
 pub fn filter_coordinates_by_bounds_rust(coords: &[[f64; 2]], bounds: [f64; 4]) -> Vec<[f64; 2]> {
-    let [min_lat, min_lon, max_lat, max_lon] = bounds;
-
     coords
         .iter()
-        .filter(|&&[lat, lon]| lat >= min_lat && lat <= max_lat && lon >= min_lon && lon <= max_lon)
+        .filter(|&&point| bbox_contains(bounds, point))
         .copied()
         .collect()
 }
@@ -1091,21 +1378,10 @@ pub fn calculate_track_statistics(coords: js_sys::Array) -> JsValue {
     }
 
     let mut coordinates = Vec::new();
-    let mut min_lat = f64::MAX;
-    let mut max_lat = f64::MIN;
-    let mut min_lng = f64::MAX;
-    let mut max_lng = f64::MIN;
 
-    // extract coordinates and calculate bounds
     for i in 0..coords.length() {
         if let Ok(coord_array) = serde_wasm_bindgen::from_value::<[f64; 2]>(coords.get(i)) {
-            let [lat, lon] = coord_array;
-            coordinates.push([lat, lon]);
-
-            min_lat = min_lat.min(lat);
-            max_lat = max_lat.max(lat);
-            min_lng = min_lng.min(lon);
-            max_lng = max_lng.max(lon);
+            coordinates.push(coord_array);
         }
     }
 
@@ -1119,7 +1395,7 @@ pub fn calculate_track_statistics(coords: js_sys::Array) -> JsValue {
     let result = TrackStatistics {
         distance_km: total_distance,
         point_count: coordinates.len() as u32,
-        bounding_box: [min_lat, min_lng, max_lat, max_lng],
+        bounding_box: calculate_bounding_box(&coordinates),
         elevation_gain: None, // would require elevation data
         average_speed: None,  // would require timestamp data
     };
@@ -1249,8 +1525,7 @@ pub fn coordinates_to_geojson(coords: js_sys::Array, properties: JsValue) -> JsV
 
     for i in 0..coords.length() {
         if let Ok(coord_array) = serde_wasm_bindgen::from_value::<[f64; 2]>(coords.get(i)) {
-            // geojson uses [lng, lat] order
-            coordinates.push([coord_array[1], coord_array[0]]);
+            coordinates.push(coord_array);
         }
     }
 
@@ -1260,34 +1535,37 @@ pub fn coordinates_to_geojson(coords: js_sys::Array, properties: JsValue) -> JsV
         serde_wasm_bindgen::from_value(properties).unwrap_or(serde_json::json!({}))
     };
 
-    let geojson = serde_json::json!({
-        "type": "Feature",
-        "geometry": {
-            "type": "LineString",
-            "coordinates": coordinates
-        },
-        "properties": props
-    });
-
+    let geojson = coordinates_to_geojson_rust(&coordinates, props);
     serde_wasm_bindgen::to_value(&geojson).unwrap_or(JsValue::NULL)
 }
 
-// coordinates to geojson (returns json value)
+// coordinates to geojson (returns json value). a track crossing the antimeridian is cut
+// there into a MultiLineString (RFC 7946 section 3.1.9)
 pub fn coordinates_to_geojson_rust(
     coords: &[[f64; 2]],
     properties: serde_json::Value,
 ) -> serde_json::Value {
-    let coordinates: Vec<[f64; 2]> = coords
-        .iter()
-        .map(|&[lat, lon]| [lon, lat]) // geojson uses [lng, lat] order
+    // geojson uses [lng, lat] order
+    let mut parts: Vec<Vec<[f64; 2]>> = split_at_antimeridian(coords)
+        .into_iter()
+        .map(|part| part.into_iter().map(|[lat, lon]| [lon, lat]).collect())
         .collect();
 
+    let geometry = if parts.len() > 1 {
+        serde_json::json!({
+            "type": "MultiLineString",
+            "coordinates": parts
+        })
+    } else {
+        serde_json::json!({
+            "type": "LineString",
+            "coordinates": parts.pop().unwrap_or_default()
+        })
+    };
+
     serde_json::json!({
         "type": "Feature",
-        "geometry": {
-            "type": "LineString",
-            "coordinates": coordinates
-        },
+        "geometry": geometry,
         "properties": properties
     })
 }
@@ -1350,6 +1628,58 @@ pub fn export_to_gpx_rust(tracks: &[Vec<[f64; 2]>]) -> String {
     gpx_content
 }
 
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn export_to_csv(tracks: js_sys::Array) -> String {
+    let mut track_list = Vec::new();
+
+    for i in 0..tracks.length() {
+        if let Ok(track) = serde_wasm_bindgen::from_value::<Vec<[f64; 2]>>(tracks.get(i)) {
+            track_list.push(track);
+        }
+    }
+
+    export_to_csv_rust(&track_list)
+}
+
+// csv export
+pub fn export_to_csv_rust(tracks: &[Vec<[f64; 2]>]) -> String {
+    let csv_tracks: Vec<CsvTrack> = tracks
+        .iter()
+        .enumerate()
+        .map(|(i, track)| CsvTrack {
+            id: Some((i + 1).to_string()),
+            points: track
+                .iter()
+                .map(|&[lat, lon]| TrackPoint {
+                    lat,
+                    lon,
+                    elevation: None,
+                    timestamp_ms: None,
+                })
+                .collect(),
+        })
+        .collect();
+
+    write_csv(&csv_tracks, &CsvOptions::default())
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn parse_csv_file(file_bytes: js_sys::Uint8Array, options: JsValue) -> JsValue {
+    let bytes = file_bytes.to_vec();
+    let options: CsvOptions = if options.is_null() || options.is_undefined() {
+        CsvOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options).unwrap_or_default()
+    };
+
+    match parse_csv(&bytes, &options) {
+        Ok(tracks) => serde_wasm_bindgen::to_value(&tracks).unwrap_or(JsValue::NULL),
+        Err(_) => JsValue::NULL,
+    }
+}
+
 // #################################################
 //
 //      c    TRACK ANALYSIS FUNCTIONS
@@ -1386,41 +1716,33 @@ pub fn find_track_intersections_rust(
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub fn calculate_coverage_area(tracks: js_sys::Array) -> JsValue {
-    let mut all_points = Vec::new();
+    let mut track_list = Vec::new();
 
     for i in 0..tracks.length() {
         if let Ok(track) = serde_wasm_bindgen::from_value::<Vec<[f64; 2]>>(tracks.get(i)) {
-            all_points.extend(track);
+            track_list.push(track);
         }
     }
 
-    if all_points.is_empty() {
-        return JsValue::NULL;
-    }
-
-    let bbox = calculate_bounding_box(&all_points);
-    let area_km2 = calculate_area_km2(&bbox);
+    let coverage = match track_coverage(&track_list, &CoverageOptions::default()) {
+        Some(coverage) => coverage,
+        None => return JsValue::NULL,
+    };
 
     let result = serde_json::json!({
-        "bounding_box": bbox,
-        "area_km2": area_km2,
-        "point_count": all_points.len()
+        "bounding_box": coverage.bbox,
+        "area_km2": coverage.area_km2,
+        "point_count": coverage.point_count,
+        "polygon": coverage.to_geojson()["geometry"]
     });
 
     serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
 }
-// calculate coverage area
+// calculate coverage area, the area within 25 m of any track rather than of their bounding
+// box. track_coverage takes other widths and returns the polygon as well
 pub fn calculate_coverage_area_rust(tracks: &[Vec<[f64; 2]>]) -> Option<([f64; 4], f64, usize)> {
-    let all_points: Vec<[f64; 2]> = tracks.iter().flatten().copied().collect();
-
-    if all_points.is_empty() {
-        return None;
-    }
-
-    let bbox = calculate_bounding_box(&all_points);
-    let area_km2 = calculate_area_km2(&bbox);
-
-    Some((bbox, area_km2, all_points.len()))
+    let coverage = track_coverage(tracks, &CoverageOptions::default())?;
+    Some((coverage.bbox, coverage.area_km2, coverage.point_count))
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -1528,27 +1850,18 @@ pub fn get_file_info(file_bytes: js_sys::Uint8Array) -> JsValue {
         file_size: bytes.len() as u32,
     };
 
-    // check for gpx
-    if let Ok(gpx) = read(Cursor::new(&bytes)) {
-        info.format = "gpx".to_string();
-        info.valid = true;
-        info.track_count = gpx.tracks.len() as u32;
-
-        for track in gpx.tracks {
-            for segment in track.segments {
-                info.point_count += segment.points.len() as u32;
-            }
+    match parse_file(&bytes) {
+        Ok(activity) => {
+            info.format = activity.format.to_string();
+            info.valid = true;
+            info.track_count = activity.tracks.len() as u32;
+            info.point_count = activity.point_count() as u32;
         }
-    }
-    // check for fit
-    else if is_fit_file(&bytes) {
-        info.format = "fit".to_string();
-        info.valid = true;
-        info.track_count = 1; // assume single track for fit files
-
-        let mut parser = FitParser::new(bytes);
-        let coords = parser.parse_gps_coordinates();
-        info.point_count = coords.len() as u32;
+        // recognised but unreadable files still report their format
+        Err(Error::Parse { format, .. }) => info.format = format.to_string(),
+        // corrupt or oversized archives
+        Err(Error::Decompress(_)) => info.format = "gzip".to_string(),
+        Err(Error::UnknownFormat | Error::Archive(_) | Error::Encode(_)) => {}
     }
 
     serde_wasm_bindgen::to_value(&info).unwrap_or(JsValue::NULL)
@@ -1559,31 +1872,428 @@ pub fn get_file_info(file_bytes: js_sys::Uint8Array) -> JsValue {
 pub fn extract_file_metadata(file_bytes: js_sys::Uint8Array) -> JsValue {
     let bytes = file_bytes.to_vec();
 
-    if let Ok(gpx) = read(Cursor::new(&bytes)) {
-        let metadata = serde_json::json!({
-            "format": "gpx",
-            "creator": gpx.creator,
-            "version": format!("{:?}", gpx.version),
-            "tracks": gpx.tracks.iter().map(|t| serde_json::json!({
-                "name": t.name,
-                "description": t.description,
-                "segment_count": t.segments.len()
-            })).collect::<Vec<_>>()
-        });
+    let activity = match parse_file(&bytes) {
+        Ok(activity) => activity,
+        Err(_) => return JsValue::NULL,
+    };
 
-        return serde_wasm_bindgen::to_value(&metadata).unwrap_or(JsValue::NULL);
+    let mut metadata = activity.metadata.clone();
+    if let Some(fields) = metadata.as_object_mut() {
+        fields.insert("format".to_string(), activity.format.as_str().into());
+        fields.insert("file_size".to_string(), bytes.len().into());
+        fields.insert("start_time".to_string(), activity.start_time_ms().into());
+        fields.insert("end_time".to_string(), activity.end_time_ms().into());
+        fields.insert("warnings".to_string(), activity.warnings.clone().into());
     }
 
-    if is_fit_file(&bytes) {
-        let metadata = serde_json::json!({
-            "format": "fit",
-            "file_size": bytes.len()
-        });
+    serde_wasm_bindgen::to_value(&metadata).unwrap_or(JsValue::NULL)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn parse_activity_file(file_bytes: js_sys::Uint8Array) -> JsValue {
+    match parse_file(&file_bytes.to_vec()) {
+        Ok(activity) => serde_wasm_bindgen::to_value(&activity).unwrap_or(JsValue::NULL),
+        Err(_) => JsValue::NULL,
+    }
+}
+
+// Strava "Download your data" or Garmin export zip -> activities with metadata and tracks
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn read_export_archive(file_bytes: js_sys::Uint8Array) -> JsValue {
+    match parse_export_archive(&file_bytes.to_vec()) {
+        Ok(archive) => serde_wasm_bindgen::to_value(&archive).unwrap_or(JsValue::NULL),
+        Err(_) => JsValue::NULL,
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn process_export_archive(file_bytes: js_sys::Uint8Array) -> JsValue {
+    let archive = match parse_export_archive(&file_bytes.to_vec()) {
+        Ok(archive) => archive,
+        Err(_) => return JsValue::NULL,
+    };
+
+    let options = HeatmapOptions::default();
+    let mut builder = HeatmapBuilder::new();
+    for activity in &archive.activities {
+        builder.add_weighted_activity(
+            activity
+                .coordinates()
+                .iter()
+                .filter_map(|track| prepare_heatmap_track(track, &options)),
+            1.0,
+            activity.start_time_ms,
+        );
+    }
+    let result = builder.build();
+
+    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+}
+
+// png bytes as a Uint8Array, options is a partial RenderOptions object
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn render_heatmap_png(result: JsValue, options: JsValue) -> JsValue {
+    let options = render_options_from_js(options);
+    let result: HeatmapResult = match serde_wasm_bindgen::from_value(result) {
+        Ok(result) => result,
+        Err(_) => return JsValue::NULL,
+    };
+
+    png_to_js(render_heatmap(&result, &options))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn render_tracks_png(tracks: js_sys::Array, options: JsValue) -> JsValue {
+    let options = render_options_from_js(options);
+    png_to_js(render_tracks(&tracks_from_js(tracks), &options))
+}
+
+// [{z, x, y, png}] for every tile with data, options is a partial TileOptions object
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn render_heatmap_tiles_png(result: JsValue, options: JsValue) -> JsValue {
+    let options: TileOptions = if options.is_undefined() || options.is_null() {
+        TileOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options).unwrap_or_default()
+    };
+    let result: HeatmapResult = match serde_wasm_bindgen::from_value(result) {
+        Ok(result) => result,
+        Err(_) => return JsValue::NULL,
+    };
+
+    // encoded as they are drawn, only the png bytes are kept
+    let tiles = js_sys::Array::new();
+    let Ok(()) = for_each_heatmap_tile(&result, &options, |tile| {
+        let entry = js_sys::Object::new();
+        let _ = js_sys::Reflect::set(&entry, &"z".into(), &tile.z.into());
+        let _ = js_sys::Reflect::set(&entry, &"x".into(), &tile.x.into());
+        let _ = js_sys::Reflect::set(&entry, &"y".into(), &tile.y.into());
+        let _ = js_sys::Reflect::set(&entry, &"png".into(), &png_to_js(tile.image));
+        tiles.push(&entry);
+        Ok::<_, std::convert::Infallible>(())
+    });
+    tiles.into()
+}
+
+// DensityGrid object, options is a partial KdeOptions object
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn kernel_density_grid(tracks: js_sys::Array, options: JsValue) -> JsValue {
+    let grid = kernel_density_from_tracks(&tracks_from_js(tracks), &kde_options_from_js(options));
+    serde_wasm_bindgen::to_value(&grid).unwrap_or(JsValue::NULL)
+}
+
+// png bytes as a Uint8Array with one pixel per grid cell
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn kernel_density_png(
+    tracks: js_sys::Array,
+    options: JsValue,
+    render_options: JsValue,
+) -> JsValue {
+    let grid = kernel_density_from_tracks(&tracks_from_js(tracks), &kde_options_from_js(options));
+    png_to_js(grid.to_image(&render_options_from_js(render_options)))
+}
+
+// FeatureCollection of cell polygons with a density property, empty cells left out
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn kernel_density_geojson(tracks: js_sys::Array, options: JsValue) -> JsValue {
+    let grid = kernel_density_from_tracks(&tracks_from_js(tracks), &kde_options_from_js(options));
+    serde_wasm_bindgen::to_value(&grid.to_geojson(0.0)).unwrap_or(JsValue::NULL)
+}
+
+// HexbinResult object, options is a partial HexbinOptions object
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn hexbin(tracks: js_sys::Array, options: JsValue) -> JsValue {
+    let result = hexbin_tracks(&tracks_from_js(tracks), &hexbin_options_from_js(options));
+    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+}
+
+// FeatureCollection of hexagons with points, length_m and tracks properties
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn hexbin_geojson(tracks: js_sys::Array, options: JsValue) -> JsValue {
+    let result = hexbin_tracks(&tracks_from_js(tracks), &hexbin_options_from_js(options));
+    serde_wasm_bindgen::to_value(&result.to_geojson()).unwrap_or(JsValue::NULL)
+}
+
+// [{q, r}] of the cells within radius steps, radius 1 gives the cell and its six neighbors
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn hex_neighborhood(q: i32, r: i32, radius: u32) -> JsValue {
+    serde_wasm_bindgen::to_value(&hex_disk(HexId { q, r }, radius)).unwrap_or(JsValue::NULL)
+}
 
-        return serde_wasm_bindgen::to_value(&metadata).unwrap_or(JsValue::NULL);
+#[cfg(target_arch = "wasm32")]
+fn hexbin_options_from_js(options: JsValue) -> HexbinOptions {
+    if options.is_undefined() || options.is_null() {
+        return HexbinOptions::default();
+    }
+    serde_wasm_bindgen::from_value(options).unwrap_or_default()
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn geohash_encode(lat: f64, lon: f64, precision: usize) -> String {
+    encode_geohash(lat, lon, precision)
+}
+
+// {center: [lat, lon], bbox: [min_lat, min_lon, max_lat, max_lon]}, null for invalid hashes
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn geohash_decode(hash: &str) -> JsValue {
+    match (decode_geohash(hash), geohash_bounds(hash)) {
+        (Some(center), Some(bbox)) => serde_wasm_bindgen::to_value(&serde_json::json!({
+            "center": center,
+            "bbox": bbox,
+        }))
+        .unwrap_or(JsValue::NULL),
+        _ => JsValue::NULL,
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn geohash_adjacent(hash: &str) -> JsValue {
+    match geohash_neighbors(hash) {
+        Some(neighbors) => serde_wasm_bindgen::to_value(&neighbors).unwrap_or(JsValue::NULL),
+        None => JsValue::NULL,
     }
+}
 
-    JsValue::NULL
+// bbox is [min_lat, min_lon, max_lat, max_lon], null when the cover would have more than
+// MAX_GEOHASH_COVER_CELLS cells
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn geohash_cover_bbox(bbox: Vec<f64>, precision: usize) -> JsValue {
+    let bbox: [f64; 4] = match bbox.try_into() {
+        Ok(bbox) => bbox,
+        Err(_) => return JsValue::NULL,
+    };
+    serde_wasm_bindgen::to_value(&geohash_cover(bbox, precision)).unwrap_or(JsValue::NULL)
+}
+
+// GeohashResult object, options is a partial HeatmapOptions object
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn process_tracks_by_geohash(
+    tracks: js_sys::Array,
+    precision: usize,
+    options: JsValue,
+) -> JsValue {
+    let options = heatmap_options_from_js(options);
+    let result =
+        process_tracks_by_geohash_with_options(tracks_from_js(tracks), precision, &options);
+    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+}
+
+// [x, y] in EPSG:3857 meters for a lat/lon
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn to_web_mercator(lat: f64, lon: f64) -> Vec<f64> {
+    lat_lon_to_mercator([lat, lon]).to_vec()
+}
+
+// [lat, lon] for EPSG:3857 meters
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn from_web_mercator(x: f64, y: f64) -> Vec<f64> {
+    mercator_to_lat_lon([x, y]).to_vec()
+}
+
+// [x, y] global pixel coordinates, zoom may be fractional
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn to_world_pixel(lat: f64, lon: f64, zoom: f64, tile_size: u32) -> Vec<f64> {
+    lat_lon_to_pixel([lat, lon], zoom, tile_size).to_vec()
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn from_world_pixel(x: f64, y: f64, zoom: f64, tile_size: u32) -> Vec<f64> {
+    pixel_to_lat_lon([x, y], zoom, tile_size).to_vec()
+}
+
+// [x, y] of the tile containing the point
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn tile_at(lat: f64, lon: f64, zoom: u8) -> Vec<u32> {
+    let (x, y) = lat_lon_to_tile([lat, lon], zoom);
+    vec![x, y]
+}
+
+// [min_lat, min_lon, max_lat, max_lon] of a tile
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn tile_bbox(z: u8, x: u32, y: u32) -> Vec<f64> {
+    tile_bounds(z, x, y).to_vec()
+}
+
+// [min_x, min_y, max_x, max_y] of the tiles a [min_lat, min_lon, max_lat, max_lon] viewport
+// touches, min_x > max_x when it crosses the antimeridian. null for a bbox that isn't 4 numbers
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn tiles_for_bbox(bbox: Vec<f64>, zoom: u8) -> Option<Vec<u32>> {
+    let bbox: [f64; 4] = bbox.try_into().ok()?;
+    Some(tile_range(bbox, zoom).to_vec())
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn ground_resolution(lat: f64, zoom: f64, tile_size: u32) -> f64 {
+    meters_per_pixel(lat, zoom, tile_size)
+}
+
+// Coverage object, options is a partial CoverageOptions object
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn coverage(tracks: js_sys::Array, options: JsValue) -> JsValue {
+    match track_coverage(&tracks_from_js(tracks), &coverage_options_from_js(options)) {
+        Some(coverage) => serde_wasm_bindgen::to_value(&coverage).unwrap_or(JsValue::NULL),
+        None => JsValue::NULL,
+    }
+}
+
+// Feature with the covered MultiPolygon and area_km2, point_count and width_m properties
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn coverage_geojson(tracks: js_sys::Array, options: JsValue) -> JsValue {
+    match track_coverage(&tracks_from_js(tracks), &coverage_options_from_js(options)) {
+        Some(coverage) => {
+            serde_wasm_bindgen::to_value(&coverage.to_geojson()).unwrap_or(JsValue::NULL)
+        }
+        None => JsValue::NULL,
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn coverage_options_from_js(options: JsValue) -> CoverageOptions {
+    if options.is_undefined() || options.is_null() {
+        return CoverageOptions::default();
+    }
+    serde_wasm_bindgen::from_value(options).unwrap_or_default()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn kde_options_from_js(options: JsValue) -> KdeOptions {
+    if options.is_undefined() || options.is_null() {
+        return KdeOptions::default();
+    }
+    serde_wasm_bindgen::from_value(options).unwrap_or_default()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn tracks_from_js(tracks: js_sys::Array) -> Vec<Vec<[f64; 2]>> {
+    tracks
+        .iter()
+        .filter_map(|track| serde_wasm_bindgen::from_value(track).ok())
+        .collect()
+}
+
+// a single z/x/y vector tile as a Uint8Array, empty when nothing falls inside the tile.
+// options is a partial MvtOptions object
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn heatmap_to_mvt(result: JsValue, z: u8, x: u32, y: u32, options: JsValue) -> JsValue {
+    let options = mvt_options_from_js(options);
+    let result: HeatmapResult = match serde_wasm_bindgen::from_value(result) {
+        Ok(result) => result,
+        Err(_) => return JsValue::NULL,
+    };
+
+    js_sys::Uint8Array::from(heatmap_mvt_tile(&result, z, x, y, &options).as_slice()).into()
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn segments_to_mvt(result: JsValue, z: u8, x: u32, y: u32, options: JsValue) -> JsValue {
+    let options = mvt_options_from_js(options);
+    let result: SegmentHeatmapResult = match serde_wasm_bindgen::from_value(result) {
+        Ok(result) => result,
+        Err(_) => return JsValue::NULL,
+    };
+
+    js_sys::Uint8Array::from(segment_mvt_tile(&result, z, x, y, &options).as_slice()).into()
+}
+
+// a whole PMTiles archive as a Uint8Array, ready to upload to static hosting. the options
+// are partial MvtOptions and PmtilesOptions objects
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn heatmap_to_pmtiles_mvt(result: JsValue, mvt_options: JsValue, options: JsValue) -> JsValue {
+    let mvt_options = mvt_options_from_js(mvt_options);
+    let options = pmtiles_options_from_js(options);
+    let result: HeatmapResult = match serde_wasm_bindgen::from_value(result) {
+        Ok(result) => result,
+        Err(_) => return JsValue::NULL,
+    };
+
+    let tiles = heatmap_mvt_tiles(&result, &mvt_options);
+    match vector_tiles_to_pmtiles(&tiles, &mvt_options, &options) {
+        Ok(archive) => js_sys::Uint8Array::from(archive.as_slice()).into(),
+        Err(_) => JsValue::NULL,
+    }
+}
+
+// tile_options is a partial TileOptions object
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn heatmap_to_pmtiles_png(result: JsValue, tile_options: JsValue, options: JsValue) -> JsValue {
+    let tile_options: TileOptions = if tile_options.is_undefined() || tile_options.is_null() {
+        TileOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(tile_options).unwrap_or_default()
+    };
+    let options = pmtiles_options_from_js(options);
+    let result: HeatmapResult = match serde_wasm_bindgen::from_value(result) {
+        Ok(result) => result,
+        Err(_) => return JsValue::NULL,
+    };
+
+    match heatmap_to_pmtiles_raster(&result, &tile_options, &options) {
+        Ok(archive) => js_sys::Uint8Array::from(archive.as_slice()).into(),
+        Err(_) => JsValue::NULL,
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn pmtiles_options_from_js(options: JsValue) -> PmtilesOptions {
+    if options.is_undefined() || options.is_null() {
+        return PmtilesOptions::default();
+    }
+    serde_wasm_bindgen::from_value(options).unwrap_or_default()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn mvt_options_from_js(options: JsValue) -> MvtOptions {
+    if options.is_undefined() || options.is_null() {
+        return MvtOptions::default();
+    }
+    serde_wasm_bindgen::from_value(options).unwrap_or_default()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn render_options_from_js(options: JsValue) -> RenderOptions {
+    if options.is_undefined() || options.is_null() {
+        return RenderOptions::default();
+    }
+    serde_wasm_bindgen::from_value(options).unwrap_or_default()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn png_to_js(image: RasterImage) -> JsValue {
+    match image.to_png() {
+        Ok(png) => js_sys::Uint8Array::from(png.as_slice()).into(),
+        Err(_) => JsValue::NULL,
+    }
 }
 
 // #################################################
@@ -1592,6 +2302,144 @@ pub fn extract_file_metadata(file_bytes: js_sys::Uint8Array) -> JsValue {
 //
 // #################################################
 
+pub(crate) const MS_PER_DAY: i64 = 86_400_000;
+
+// ddmmyy as used by NMEA RMC and IGC HFDTE records -> days since unix epoch
+pub(crate) fn parse_ddmmyy(value: &str) -> Option<i64> {
+    if value.len() != 6 || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let day: u32 = value[0..2].parse().ok()?;
+    let month: u32 = value[2..4].parse().ok()?;
+    let year: i64 = value[4..6].parse().ok()?;
+    if !(1..=31).contains(&day) || !(1..=12).contains(&month) {
+        return None;
+    }
+
+    // two digit years, GPS receivers did not exist before 1980
+    let year = if year < 80 { 2000 + year } else { 1900 + year };
+    Some(days_from_civil(year, month, day))
+}
+
+// days since 1970-01-01 for a proleptic gregorian date
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+// inverse of days_from_civil -> (year, month, day)
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+// ISO 8601 date-times ("2024-03-31T23:59:58.5Z", "2024-03-31 23:59:58+02:00", "2024-03-31")
+// or plain unix seconds / millis -> unix epoch millis
+pub(crate) fn parse_timestamp_ms(value: &str) -> Option<i64> {
+    let value = value.trim();
+
+    if let Ok(number) = value.parse::<f64>() {
+        if !number.is_finite() {
+            return None;
+        }
+        // anything past the year 5138 in seconds is assumed to already be millis
+        return if number.abs() >= 1e11 {
+            Some(number.round() as i64)
+        } else {
+            Some((number * 1000.0).round() as i64)
+        };
+    }
+
+    if value.len() < 10 || !value.is_ascii() {
+        return None;
+    }
+
+    let year: i64 = value[0..4].parse().ok()?;
+    let month: u32 = value[5..7].parse().ok()?;
+    let day: u32 = value[8..10].parse().ok()?;
+    if &value[4..5] != "-" || &value[7..8] != "-" {
+        return None;
+    }
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let mut millis = days_from_civil(year, month, day) * MS_PER_DAY;
+    let rest = &value[10..];
+    if rest.is_empty() {
+        return Some(millis);
+    }
+
+    let rest = rest.strip_prefix(['T', 't', ' '])?;
+    if rest.len() < 8 || &rest[2..3] != ":" || &rest[5..6] != ":" {
+        return None;
+    }
+
+    let hours: i64 = rest[0..2].parse().ok()?;
+    let minutes: i64 = rest[3..5].parse().ok()?;
+    let zone_start = rest[6..]
+        .find(['Z', 'z', '+', '-'])
+        .map(|i| i + 6)
+        .unwrap_or(rest.len());
+    let seconds: f64 = rest[6..zone_start].parse().ok()?;
+    if hours > 23 || minutes > 59 || !(0.0..61.0).contains(&seconds) {
+        return None;
+    }
+    millis += hours * 3_600_000 + minutes * 60_000 + (seconds * 1000.0).round() as i64;
+
+    // no zone designator is treated as UTC
+    let zone = &rest[zone_start..];
+    if zone.is_empty() || zone.eq_ignore_ascii_case("z") {
+        return Some(millis);
+    }
+
+    let sign = if zone.starts_with('-') { -1 } else { 1 };
+    let offset = zone[1..].replace(':', "");
+    if offset.len() != 4 && offset.len() != 2 {
+        return None;
+    }
+    let offset_hours: i64 = offset[0..2].parse().ok()?;
+    let offset_minutes: i64 = offset.get(2..4).unwrap_or("0").parse().ok()?;
+
+    Some(millis - sign * (offset_hours * 3_600_000 + offset_minutes * 60_000))
+}
+
+// unix epoch millis -> "2024-03-31T23:59:58Z", with millis only when present
+pub(crate) fn format_timestamp_ms(millis: i64) -> String {
+    let days = millis.div_euclid(MS_PER_DAY);
+    let ms_of_day = millis.rem_euclid(MS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+
+    let hours = ms_of_day / 3_600_000;
+    let minutes = ms_of_day / 60_000 % 60;
+    let seconds = ms_of_day / 1000 % 60;
+    let fraction = ms_of_day % 1000;
+
+    if fraction == 0 {
+        format!("{year:04}-{month:02}-{day:02}T{hours:02}:{minutes:02}:{seconds:02}Z")
+    } else {
+        format!("{year:04}-{month:02}-{day:02}T{hours:02}:{minutes:02}:{seconds:02}.{fraction:03}Z")
+    }
+}
+
 fn encode_polyline(coordinates: &[[f64; 2]]) -> String {
     let mut encoded = String::new();
     let mut prev_lat = 0i32;
@@ -1715,41 +2563,38 @@ fn split_by_spatial_gaps(coordinates: &[[f64; 2]], max_gap_km: f64) -> Vec<Vec<[
     tracks
 }
 
+const INTERSECTION_RESOLUTION: f64 = 0.0001;
+
 fn find_intersections(tracks: &[Vec<[f64; 2]>], tolerance: f64) -> Vec<IntersectionPoint> {
     let mut intersections = Vec::new();
-    let mut intersection_map: HashMap<String, Vec<u32>> = HashMap::new();
+    let mut intersection_map: HashMap<CellId, Vec<u32>> = HashMap::new();
 
-    // find points that are close to each other across different tracks
+    // find points that are close to each other across different tracks. intersections are
+    // reported to 4 decimals, so cells of a finer tolerance that land on the same reported
+    // coordinate count as one, the way the old string keys grouped them
     for (i, track1) in tracks.iter().enumerate() {
         for point1 in track1 {
-            let grid_key = format!(
-                "{:.4},{:.4}",
-                (point1[0] / tolerance).round() * tolerance,
-                (point1[1] / tolerance).round() * tolerance
-            );
-
-            intersection_map.entry(grid_key).or_default().push(i as u32);
+            let mut cell = grid_cell(*point1, tolerance);
+            if tolerance < INTERSECTION_RESOLUTION {
+                cell = grid_cell(cell_coordinate(cell, tolerance), INTERSECTION_RESOLUTION);
+            }
+            intersection_map.entry(cell).or_default().push(i as u32);
         }
     }
 
     // collect intersections where multiple tracks meet
-    for (key_str, track_indices) in intersection_map {
+    for (cell, track_indices) in intersection_map {
         if track_indices.len() > 1 {
-            let coords: Vec<f64> = key_str
-                .split(',')
-                .map(|s| s.parse().unwrap_or(0.0))
-                .collect();
-            if coords.len() == 2 {
-                let mut unique_tracks = track_indices;
-                unique_tracks.sort();
-                unique_tracks.dedup();
-
-                if unique_tracks.len() > 1 {
-                    intersections.push(IntersectionPoint {
-                        coordinate: [coords[0], coords[1]],
-                        track_indices: unique_tracks,
-                    });
-                }
+            let mut unique_tracks = track_indices;
+            unique_tracks.sort();
+            unique_tracks.dedup();
+
+            if unique_tracks.len() > 1 {
+                let [lat, lon] = cell_coordinate(cell, tolerance.max(INTERSECTION_RESOLUTION));
+                intersections.push(IntersectionPoint {
+                    coordinate: [round_to(lat, 4), round_to(lon, 4)],
+                    track_indices: unique_tracks,
+                });
             }
         }
     }
@@ -1758,28 +2603,7 @@ fn find_intersections(tracks: &[Vec<[f64; 2]>], tolerance: f64) -> Vec<Intersect
 }
 
 fn calculate_bounding_box(coordinates: &[[f64; 2]]) -> [f64; 4] {
-    let mut min_lat = f64::MAX;
-    let mut max_lat = f64::MIN;
-    let mut min_lng = f64::MAX;
-    let mut max_lng = f64::MIN;
-
-    for coord in coordinates {
-        min_lat = min_lat.min(coord[0]);
-        max_lat = max_lat.max(coord[0]);
-        min_lng = min_lng.min(coord[1]);
-        max_lng = max_lng.max(coord[1]);
-    }
-
-    [min_lat, min_lng, max_lat, max_lng]
-}
-fn calculate_area_km2(bbox: &[f64; 4]) -> f64 {
-    let [min_lat, min_lng, max_lat, max_lng] = *bbox;
-
-    // approximate area calculation using haversine for edges
-    let width_km = haversine_distance(min_lat, min_lng, min_lat, max_lng);
-    let height_km = haversine_distance(min_lat, min_lng, max_lat, min_lng);
-
-    width_km * height_km
+    points_bounding_box(coordinates)
 }
 
 fn cluster_similar_tracks(
@@ -0,0 +1,397 @@
+// mapbox vector tile (MVT 2.1) encoding of heatmap tracks and segments. every track or segment
+// becomes a line feature carrying its frequency, clipped to the tile plus a buffer and
+// quantized to the tile extent. the protobuf is written by hand, the schema is small
+use crate::projection::project_track;
+use crate::tiles::tile_index;
+use crate::{HeatmapResult, SegmentHeatmapResult, MAX_TILE_ZOOM};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct MvtOptions {
+    pub layer_name: String,
+    // coordinate units per tile side
+    pub extent: u32,
+    // extent units kept beyond the tile edge so lines join up across tiles
+    pub buffer: u32,
+    // zoom range for the *_mvt_tiles functions
+    pub min_zoom: u8,
+    pub max_zoom: u8,
+}
+
+impl Default for MvtOptions {
+    fn default() -> Self {
+        Self {
+            layer_name: "heatmap".to_string(),
+            extent: 4096,
+            buffer: 64,
+            min_zoom: 0,
+            max_zoom: 14,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VectorTile {
+    pub z: u8,
+    pub x: u32,
+    pub y: u32,
+    pub data: Vec<u8>,
+}
+
+// a line feature before it is cut into tiles, mercator coordinates cut at ±180 plus its
+// attributes
+struct MvtFeature {
+    parts: Vec<Vec<[f64; 2]>>,
+    attributes: Vec<(&'static str, MvtValue)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MvtValue {
+    Uint(u64),
+    Double(f64),
+}
+
+pub fn heatmap_mvt_tile(
+    result: &HeatmapResult,
+    z: u8,
+    x: u32,
+    y: u32,
+    options: &MvtOptions,
+) -> Vec<u8> {
+    let features = heatmap_features(result);
+    encode_tile(&features, 0..features.len(), z, x, y, options)
+}
+
+pub fn segment_mvt_tile(
+    result: &SegmentHeatmapResult,
+    z: u8,
+    x: u32,
+    y: u32,
+    options: &MvtOptions,
+) -> Vec<u8> {
+    let features = segment_features(result);
+    encode_tile(&features, 0..features.len(), z, x, y, options)
+}
+
+// every tile with at least one feature over options' zoom range, sorted by zoom, x, y
+pub fn heatmap_mvt_tiles(result: &HeatmapResult, options: &MvtOptions) -> Vec<VectorTile> {
+    encode_tiles(&heatmap_features(result), options)
+}
+
+pub fn segment_mvt_tiles(result: &SegmentHeatmapResult, options: &MvtOptions) -> Vec<VectorTile> {
+    encode_tiles(&segment_features(result), options)
+}
+
+fn heatmap_features(result: &HeatmapResult) -> Vec<MvtFeature> {
+    result
+        .tracks
+        .iter()
+        .map(|track| MvtFeature {
+            parts: project_track(&track.coordinates),
+            attributes: vec![("frequency", MvtValue::Uint(track.frequency as u64))],
+        })
+        .collect()
+}
+
+fn segment_features(result: &SegmentHeatmapResult) -> Vec<MvtFeature> {
+    result
+        .segments
+        .iter()
+        .map(|segment| {
+            let mut attributes = vec![
+                ("frequency", MvtValue::Uint(segment.frequency as u64)),
+                ("passes", MvtValue::Uint(segment.passes as u64)),
+                ("activities", MvtValue::Uint(segment.activities as u64)),
+            ];
+            if let Some(direction) = &segment.direction {
+                attributes.push(("forward", MvtValue::Uint(direction.forward as u64)));
+                attributes.push(("backward", MvtValue::Uint(direction.backward as u64)));
+                attributes.push(("bearing", MvtValue::Double(direction.bearing)));
+            }
+
+            MvtFeature {
+                parts: project_track(&segment.coordinates),
+                attributes,
+            }
+        })
+        .collect()
+}
+
+fn encode_tiles(features: &[MvtFeature], options: &MvtOptions) -> Vec<VectorTile> {
+    let min_zoom = options.min_zoom.min(MAX_TILE_ZOOM);
+    let max_zoom = options.max_zoom.clamp(min_zoom, MAX_TILE_ZOOM);
+    let margin = options.buffer as f64 / options.extent.max(1) as f64;
+
+    let mut tiles = Vec::new();
+    for zoom in min_zoom..=max_zoom {
+        let index = tile_index(features.iter().map(|f| f.parts.as_slice()), zoom, margin);
+
+        for ((x, y), mut members) in index {
+            members.dedup_by_key(|(feature, _, _)| *feature);
+            let members = members.into_iter().map(|(feature, _, _)| feature);
+            let data = encode_tile(features, members, zoom, x, y, options);
+            // features near the edge of a candidate tile can still clip away entirely
+            if !data.is_empty() {
+                tiles.push(VectorTile {
+                    z: zoom,
+                    x,
+                    y,
+                    data,
+                });
+            }
+        }
+    }
+    tiles
+}
+
+fn encode_tile(
+    features: &[MvtFeature],
+    members: impl IntoIterator<Item = usize>,
+    z: u8,
+    x: u32,
+    y: u32,
+    options: &MvtOptions,
+) -> Vec<u8> {
+    let extent = options.extent.max(1) as f64;
+    let tiles_per_axis = (1u64 << z.min(MAX_TILE_ZOOM)) as f64;
+    let to_tile = |p: &[f64; 2]| {
+        [
+            (p[0] * tiles_per_axis - x as f64) * extent,
+            (p[1] * tiles_per_axis - y as f64) * extent,
+        ]
+    };
+    let bounds = [-(options.buffer as f64), extent + options.buffer as f64];
+
+    let mut layer = LayerBuilder::default();
+    for i in members {
+        let feature = &features[i];
+        let parts = quantize(
+            feature
+                .parts
+                .iter()
+                .flat_map(|part| clip_line(&part.iter().map(to_tile).collect::<Vec<_>>(), bounds))
+                .collect(),
+        );
+        if parts.is_empty() {
+            continue;
+        }
+        layer.add_feature(i as u64 + 1, &feature.attributes, &parts);
+    }
+
+    if layer.features.is_empty() {
+        return Vec::new();
+    }
+
+    let mut tile = Vec::new();
+    write_bytes(
+        &mut tile,
+        3,
+        &layer.finish(&options.layer_name, options.extent),
+    );
+    tile
+}
+
+// the pieces of a line inside a square, split where the line leaves it
+fn clip_line(points: &[[f64; 2]], [min, max]: [f64; 2]) -> Vec<Vec<[f64; 2]>> {
+    let mut parts = Vec::new();
+    let mut current: Vec<[f64; 2]> = Vec::new();
+
+    for window in points.windows(2) {
+        let (a, b) = (window[0], window[1]);
+        let Some((t0, t1)) = clip_segment(a, b, min, max) else {
+            if current.len() > 1 {
+                parts.push(std::mem::take(&mut current));
+            }
+            current.clear();
+            continue;
+        };
+
+        let at = |t: f64| [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t];
+        if current.is_empty() || t0 > 0.0 {
+            if current.len() > 1 {
+                parts.push(std::mem::take(&mut current));
+            }
+            current = vec![at(t0)];
+        }
+        current.push(at(t1));
+
+        if t1 < 1.0 {
+            parts.push(std::mem::take(&mut current));
+        }
+    }
+    if current.len() > 1 {
+        parts.push(current);
+    }
+    parts
+}
+
+// liang-barsky, the parameter range of a->b inside the square
+fn clip_segment(a: [f64; 2], b: [f64; 2], min: f64, max: f64) -> Option<(f64, f64)> {
+    let delta = [b[0] - a[0], b[1] - a[1]];
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+
+    for axis in 0..2 {
+        for (p, q) in [(-delta[axis], a[axis] - min), (delta[axis], max - a[axis])] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return None;
+                }
+            } else {
+                let r = q / p;
+                if p < 0.0 {
+                    t0 = t0.max(r);
+                } else {
+                    t1 = t1.min(r);
+                }
+            }
+        }
+    }
+    (t0 <= t1).then_some((t0, t1))
+}
+
+// integer coordinates, repeated points dropped, parts that collapse to a point dropped
+fn quantize(parts: Vec<Vec<[f64; 2]>>) -> Vec<Vec<[i32; 2]>> {
+    parts
+        .into_iter()
+        .map(|part| {
+            let mut quantized: Vec<[i32; 2]> = Vec::with_capacity(part.len());
+            for [x, y] in part {
+                let point = [x.round() as i32, y.round() as i32];
+                if quantized.last() != Some(&point) {
+                    quantized.push(point);
+                }
+            }
+            quantized
+        })
+        .filter(|part| part.len() > 1)
+        .collect()
+}
+
+#[derive(Default)]
+struct LayerBuilder {
+    features: Vec<Vec<u8>>,
+    keys: Vec<&'static str>,
+    values: Vec<MvtValue>,
+    key_index: HashMap<&'static str, u32>,
+    value_index: HashMap<(u8, u64), u32>,
+}
+
+impl LayerBuilder {
+    fn add_feature(
+        &mut self,
+        id: u64,
+        attributes: &[(&'static str, MvtValue)],
+        parts: &[Vec<[i32; 2]>],
+    ) {
+        let mut tags = Vec::with_capacity(attributes.len() * 2);
+        for &(key, value) in attributes {
+            tags.push(self.key(key));
+            tags.push(self.value(value));
+        }
+
+        let mut feature = Vec::new();
+        write_varint_field(&mut feature, 1, id);
+        write_packed(&mut feature, 2, &tags);
+        write_varint_field(&mut feature, 3, 2); // LINESTRING
+        write_packed(&mut feature, 4, &line_geometry(parts));
+        self.features.push(feature);
+    }
+
+    fn key(&mut self, key: &'static str) -> u32 {
+        *self.key_index.entry(key).or_insert_with(|| {
+            self.keys.push(key);
+            self.keys.len() as u32 - 1
+        })
+    }
+
+    fn value(&mut self, value: MvtValue) -> u32 {
+        let identity = match value {
+            MvtValue::Uint(v) => (0, v),
+            MvtValue::Double(v) => (1, v.to_bits()),
+        };
+        *self.value_index.entry(identity).or_insert_with(|| {
+            self.values.push(value);
+            self.values.len() as u32 - 1
+        })
+    }
+
+    fn finish(self, name: &str, extent: u32) -> Vec<u8> {
+        let mut layer = Vec::new();
+        write_varint_field(&mut layer, 15, 2); // version
+        write_bytes(&mut layer, 1, name.as_bytes());
+        for feature in &self.features {
+            write_bytes(&mut layer, 2, feature);
+        }
+        for key in &self.keys {
+            write_bytes(&mut layer, 3, key.as_bytes());
+        }
+        for value in &self.values {
+            let mut encoded = Vec::new();
+            match *value {
+                MvtValue::Uint(v) => write_varint_field(&mut encoded, 5, v),
+                MvtValue::Double(v) => {
+                    write_key(&mut encoded, 3, 1);
+                    encoded.extend_from_slice(&v.to_le_bytes());
+                }
+            }
+            write_bytes(&mut layer, 4, &encoded);
+        }
+        write_varint_field(&mut layer, 5, extent as u64);
+        layer
+    }
+}
+
+// MoveTo / LineTo commands with zigzag encoded deltas from the previous point
+fn line_geometry(parts: &[Vec<[i32; 2]>]) -> Vec<u32> {
+    let command = |id: u32, count: usize| (id & 0x7) | ((count as u32) << 3);
+    let zigzag = |v: i32| ((v << 1) ^ (v >> 31)) as u32;
+
+    let mut geometry = Vec::new();
+    let mut cursor = [0i32, 0i32];
+    for part in parts {
+        for (i, point) in part.iter().enumerate() {
+            if i == 0 {
+                geometry.push(command(1, 1));
+            } else if i == 1 {
+                geometry.push(command(2, part.len() - 1));
+            }
+            geometry.push(zigzag(point[0] - cursor[0]));
+            geometry.push(zigzag(point[1] - cursor[1]));
+            cursor = *point;
+        }
+    }
+    geometry
+}
+
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_key(out: &mut Vec<u8>, field: u32, wire_type: u8) {
+    write_varint(out, ((field as u64) << 3) | wire_type as u64);
+}
+
+fn write_varint_field(out: &mut Vec<u8>, field: u32, value: u64) {
+    write_key(out, field, 0);
+    write_varint(out, value);
+}
+
+fn write_bytes(out: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    write_key(out, field, 2);
+    write_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn write_packed(out: &mut Vec<u8>, field: u32, values: &[u32]) {
+    let mut packed = Vec::new();
+    for &value in values {
+        write_varint(&mut packed, value as u64);
+    }
+    write_bytes(out, field, &packed);
+}
@@ -0,0 +1,360 @@
+use crate::{is_valid_coordinate, parse_ddmmyy, round, TrackPoint, MS_PER_DAY};
+use serde::{Deserialize, Serialize};
+
+// NMEA 0183 sentence reference: https://gpsd.gitlab.io/gpsd/NMEA.html
+
+// a time of day that jumps back by more than this is treated as a midnight rollover
+const ROLLOVER_THRESHOLD_MS: u32 = 12 * 3_600_000;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct NmeaFix {
+    pub lat: f64,
+    pub lon: f64,
+    pub timestamp_ms: Option<i64>, // unix epoch millis, None when no RMC date was ever seen
+    pub elevation: Option<f64>,
+    pub fix_quality: Option<u8>,
+    pub hdop: Option<f64>,
+    pub satellites: Option<u8>,
+    pub speed_kmh: Option<f64>,
+    pub course: Option<f64>,
+}
+
+impl From<&NmeaFix> for TrackPoint {
+    fn from(fix: &NmeaFix) -> Self {
+        TrackPoint {
+            lat: fix.lat,
+            lon: fix.lon,
+            elevation: fix.elevation,
+            timestamp_ms: fix.timestamp_ms,
+        }
+    }
+}
+
+pub struct NmeaParser {
+    pub data: Vec<u8>,
+    pub sentence_count: u32,
+    pub checksum_errors: u32,
+}
+
+// all sentences sharing one time of day are merged into a single epoch
+#[derive(Default)]
+struct NmeaEpoch {
+    time_of_day_ms: Option<u32>,
+    date_days: Option<i64>,
+    position: Option<[f64; 2]>,
+    elevation: Option<f64>,
+    fix_quality: Option<u8>,
+    hdop: Option<f64>,
+    satellites: Option<u8>,
+    speed_kmh: Option<f64>,
+    course: Option<f64>,
+    invalid: bool,
+}
+
+impl NmeaParser {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            sentence_count: 0,
+            checksum_errors: 0,
+        }
+    }
+
+    // rounded to 5 decimals like it always was, the fixes keep full precision
+    pub fn parse_gps_coordinates(&mut self) -> Vec<[f64; 2]> {
+        self.parse_fixes()
+            .iter()
+            .map(|fix| [round(fix.lat), round(fix.lon)])
+            .collect()
+    }
+
+    pub fn parse_fixes(&mut self) -> Vec<NmeaFix> {
+        self.sentence_count = 0;
+        self.checksum_errors = 0;
+
+        let text = String::from_utf8_lossy(&self.data).into_owned();
+        let mut epochs: Vec<NmeaEpoch> = Vec::new();
+
+        for line in text.lines() {
+            // some loggers prefix each sentence with their own timestamp
+            let sentence = match line.find('$') {
+                Some(start) => line[start..].trim(),
+                None => continue,
+            };
+
+            let body = match validate_sentence(sentence) {
+                Some(body) => body,
+                None => {
+                    self.checksum_errors += 1;
+                    continue;
+                }
+            };
+
+            let fields: Vec<&str> = body.split(',').collect();
+            let sentence_type = match sentence_type(fields[0]) {
+                Some(kind) => kind,
+                None => continue,
+            };
+            self.sentence_count += 1;
+
+            match sentence_type {
+                "GGA" => apply_gga(&mut epochs, &fields),
+                "RMC" => apply_rmc(&mut epochs, &fields),
+                "GLL" => apply_gll(&mut epochs, &fields),
+                "VTG" => apply_vtg(&mut epochs, &fields),
+                _ => {}
+            }
+        }
+
+        stitch_dates(&mut epochs);
+
+        epochs
+            .into_iter()
+            .filter(|epoch| !epoch.invalid)
+            .filter_map(|epoch| {
+                let [lat, lon] = epoch.position?;
+                if !is_valid_coordinate(lat, lon) {
+                    return None;
+                }
+
+                let timestamp_ms = match (epoch.date_days, epoch.time_of_day_ms) {
+                    (Some(days), Some(tod)) => Some(days * MS_PER_DAY + tod as i64),
+                    _ => None,
+                };
+
+                Some(NmeaFix {
+                    lat,
+                    lon,
+                    timestamp_ms,
+                    elevation: epoch.elevation,
+                    fix_quality: epoch.fix_quality,
+                    hdop: epoch.hdop,
+                    satellites: epoch.satellites,
+                    speed_kmh: epoch.speed_kmh,
+                    course: epoch.course,
+                })
+            })
+            .collect()
+    }
+}
+
+pub fn is_nmea_file(data: &[u8]) -> bool {
+    // only look at the start of the file, a valid log has sentences right away
+    let head = &data[..data.len().min(4096)];
+    let text = String::from_utf8_lossy(head);
+
+    text.lines().any(|line| {
+        let line = line.trim();
+        match line.find('$') {
+            Some(start) => {
+                let address = line[start + 1..].split(',').next().unwrap_or("");
+                sentence_type(address).is_some()
+            }
+            None => false,
+        }
+    })
+}
+
+pub fn nmea_checksum(body: &str) -> u8 {
+    body.bytes().fold(0u8, |acc, b| acc ^ b)
+}
+
+// returns the sentence body between '$' and '*' if the checksum matches (or is absent)
+fn validate_sentence(sentence: &str) -> Option<&str> {
+    let sentence = sentence.strip_prefix('$')?;
+
+    match sentence.split_once('*') {
+        Some((body, checksum)) => {
+            let expected = u8::from_str_radix(checksum.get(..2)?, 16).ok()?;
+            if nmea_checksum(body) == expected {
+                Some(body)
+            } else {
+                None
+            }
+        }
+        // checksums are optional for some talkers, accept the sentence as-is
+        None => Some(sentence),
+    }
+}
+
+fn sentence_type(address: &str) -> Option<&'static str> {
+    // address is a 2 character talker id (GP, GN, GL, ...) followed by the sentence type
+    if address.len() != 5 || !address.is_ascii() || address.starts_with('P') {
+        return None;
+    }
+
+    match &address[2..] {
+        "GGA" => Some("GGA"),
+        "RMC" => Some("RMC"),
+        "GLL" => Some("GLL"),
+        "VTG" => Some("VTG"),
+        _ => None,
+    }
+}
+
+fn field<'a>(fields: &[&'a str], index: usize) -> Option<&'a str> {
+    fields.get(index).copied().filter(|value| !value.is_empty())
+}
+
+fn parse_f64(fields: &[&str], index: usize) -> Option<f64> {
+    field(fields, index)?.parse().ok()
+}
+
+// hhmmss(.sss) -> milliseconds since midnight
+fn parse_time_of_day(value: Option<&str>) -> Option<u32> {
+    let value = value?;
+    if value.len() < 6 || !value.is_ascii() {
+        return None;
+    }
+
+    let hours: u32 = value[0..2].parse().ok()?;
+    let minutes: u32 = value[2..4].parse().ok()?;
+    let seconds: f64 = value[4..].parse().ok()?;
+    if hours > 23 || minutes > 59 || !(0.0..61.0).contains(&seconds) {
+        return None;
+    }
+
+    Some(hours * 3_600_000 + minutes * 60_000 + (seconds * 1000.0).round() as u32)
+}
+
+// (d)ddmm.mmmm + hemisphere -> signed decimal degrees
+fn parse_coordinate(value: Option<&str>, hemisphere: Option<&str>) -> Option<f64> {
+    let raw: f64 = value?.parse().ok()?;
+    let degrees = (raw / 100.0).trunc();
+    let minutes = raw - degrees * 100.0;
+    if minutes >= 60.0 {
+        return None;
+    }
+
+    let decimal = degrees + minutes / 60.0;
+    match hemisphere? {
+        "N" | "E" => Some(decimal),
+        "S" | "W" => Some(-decimal),
+        _ => None,
+    }
+}
+
+fn parse_position(fields: &[&str], lat_index: usize) -> Option<[f64; 2]> {
+    let lat = parse_coordinate(field(fields, lat_index), field(fields, lat_index + 1))?;
+    let lon = parse_coordinate(field(fields, lat_index + 2), field(fields, lat_index + 3))?;
+    Some([lat, lon])
+}
+
+fn epoch_for_time(epochs: &mut Vec<NmeaEpoch>, time_of_day_ms: Option<u32>) -> &mut NmeaEpoch {
+    let starts_new_epoch = match (epochs.last(), time_of_day_ms) {
+        (None, _) => true,
+        (Some(last), Some(tod)) => last.time_of_day_ms.is_some_and(|last_tod| last_tod != tod),
+        (Some(_), None) => false,
+    };
+
+    if starts_new_epoch {
+        epochs.push(NmeaEpoch::default());
+    }
+
+    let epoch = epochs.last_mut().unwrap();
+    if epoch.time_of_day_ms.is_none() {
+        epoch.time_of_day_ms = time_of_day_ms;
+    }
+    epoch
+}
+
+// $--GGA,time,lat,N,lon,E,quality,satellites,hdop,altitude,M,...
+fn apply_gga(epochs: &mut Vec<NmeaEpoch>, fields: &[&str]) {
+    let epoch = epoch_for_time(epochs, parse_time_of_day(field(fields, 1)));
+
+    let quality: Option<u8> = field(fields, 6).and_then(|q| q.parse().ok());
+    if quality == Some(0) {
+        epoch.invalid = true;
+    }
+
+    if epoch.position.is_none() {
+        epoch.position = parse_position(fields, 2);
+    }
+    epoch.fix_quality = quality.or(epoch.fix_quality);
+    epoch.satellites = field(fields, 7)
+        .and_then(|s| s.parse().ok())
+        .or(epoch.satellites);
+    epoch.hdop = parse_f64(fields, 8).or(epoch.hdop);
+    epoch.elevation = parse_f64(fields, 9).or(epoch.elevation);
+}
+
+// $--RMC,time,status,lat,N,lon,E,speed_knots,course,ddmmyy,...
+fn apply_rmc(epochs: &mut Vec<NmeaEpoch>, fields: &[&str]) {
+    let epoch = epoch_for_time(epochs, parse_time_of_day(field(fields, 1)));
+
+    if field(fields, 2) != Some("A") {
+        epoch.invalid = true;
+    }
+
+    if epoch.position.is_none() {
+        epoch.position = parse_position(fields, 3);
+    }
+    epoch.speed_kmh = parse_f64(fields, 7)
+        .map(|knots| knots * 1.852)
+        .or(epoch.speed_kmh);
+    epoch.course = parse_f64(fields, 8).or(epoch.course);
+    epoch.date_days = field(fields, 9).and_then(parse_ddmmyy).or(epoch.date_days);
+}
+
+// $--GLL,lat,N,lon,E,time,status,...
+fn apply_gll(epochs: &mut Vec<NmeaEpoch>, fields: &[&str]) {
+    let epoch = epoch_for_time(epochs, parse_time_of_day(field(fields, 5)));
+
+    if field(fields, 6) == Some("V") {
+        epoch.invalid = true;
+    }
+
+    if epoch.position.is_none() {
+        epoch.position = parse_position(fields, 1);
+    }
+}
+
+// $--VTG,course_true,T,course_magnetic,M,speed_knots,N,speed_kmh,K,...
+// VTG carries no time so it belongs to whichever epoch is currently open
+fn apply_vtg(epochs: &mut Vec<NmeaEpoch>, fields: &[&str]) {
+    let epoch = epoch_for_time(epochs, None);
+
+    epoch.course = parse_f64(fields, 1).or(epoch.course);
+    epoch.speed_kmh = parse_f64(fields, 7)
+        .or_else(|| parse_f64(fields, 5).map(|knots| knots * 1.852))
+        .or(epoch.speed_kmh);
+}
+
+// GGA and GLL only carry a time of day, so the date has to be carried over from
+// the surrounding RMC sentences, rolling over at midnight
+fn stitch_dates(epochs: &mut [NmeaEpoch]) {
+    let first_dated = match epochs.iter().position(|epoch| epoch.date_days.is_some()) {
+        Some(index) => index,
+        None => return,
+    };
+
+    // forward from the first dated epoch
+    let mut current_day = epochs[first_dated].date_days;
+    let mut previous_tod = epochs[first_dated].time_of_day_ms;
+    for epoch in epochs.iter_mut().skip(first_dated + 1) {
+        if epoch.date_days.is_some() {
+            current_day = epoch.date_days;
+        } else {
+            if let (Some(prev), Some(tod)) = (previous_tod, epoch.time_of_day_ms) {
+                if prev > tod && prev - tod > ROLLOVER_THRESHOLD_MS {
+                    current_day = current_day.map(|day| day + 1);
+                }
+            }
+            epoch.date_days = current_day;
+        }
+        previous_tod = epoch.time_of_day_ms.or(previous_tod);
+    }
+
+    // backward for sentences logged before the first RMC
+    let mut current_day = epochs[first_dated].date_days;
+    let mut next_tod = epochs[first_dated].time_of_day_ms;
+    for epoch in epochs[..first_dated].iter_mut().rev() {
+        if let (Some(next), Some(tod)) = (next_tod, epoch.time_of_day_ms) {
+            if tod > next && tod - next > ROLLOVER_THRESHOLD_MS {
+                current_day = current_day.map(|day| day - 1);
+            }
+        }
+        epoch.date_days = current_day;
+        next_tod = epoch.time_of_day_ms.or(next_tod);
+    }
+}
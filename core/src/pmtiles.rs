@@ -0,0 +1,401 @@
+// PMTiles v3 archives, every tile of a heatmap in one file that a map client reads with http
+// range requests, so it can be served from static hosting without a tile server.
+// https://github.com/protomaps/PMTiles/blob/main/spec/v3/spec.md
+use crate::mvt::write_varint;
+use crate::{
+    for_each_heatmap_tile, tile_bounds, Error, HeatmapResult, MvtOptions, RasterTile, TileOptions,
+    VectorTile, MAX_MERCATOR_LAT,
+};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct PmtilesOptions {
+    pub name: String,
+    pub description: String,
+    pub attribution: Option<String>,
+}
+
+impl Default for PmtilesOptions {
+    fn default() -> Self {
+        Self {
+            name: "heatmap".to_string(),
+            description: String::new(),
+            attribution: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PmtilesTileType {
+    Mvt,
+    Png,
+}
+
+// tiles in any order, a tile added twice keeps its last data. identical tiles are stored once
+#[derive(Clone, Debug)]
+pub struct PmtilesWriter {
+    tile_type: PmtilesTileType,
+    tiles: BTreeMap<u64, Vec<u8>>,
+    zooms: Option<(u8, u8)>,
+    metadata: Map<String, Value>,
+}
+
+const HEADER_LENGTH: usize = 127;
+// header and root directory fit in the first 16 KiB a client fetches
+const ROOT_LIMIT: usize = 16_384 - HEADER_LENGTH;
+
+// pmtiles codes
+const COMPRESSION_NONE: u8 = 1;
+const COMPRESSION_GZIP: u8 = 2;
+
+struct Entry {
+    tile_id: u64,
+    offset: u64,
+    length: u32,
+    // 0 points to a leaf directory
+    run_length: u32,
+}
+
+impl PmtilesWriter {
+    pub fn new(tile_type: PmtilesTileType) -> Self {
+        Self {
+            tile_type,
+            tiles: BTreeMap::new(),
+            zooms: None,
+            metadata: Map::new(),
+        }
+    }
+
+    // mvt tiles are expected uncompressed, the writer gzips them
+    pub fn add_tile(&mut self, z: u8, x: u32, y: u32, data: Vec<u8>) {
+        self.zooms = Some(match self.zooms {
+            Some((min, max)) => (min.min(z), max.max(z)),
+            None => (z, z),
+        });
+        self.tiles.insert(zxy_to_tile_id(z, x, y), data);
+    }
+
+    // extra keys for the json metadata, overriding the ones the writer fills in
+    pub fn set_metadata(&mut self, key: &str, value: Value) {
+        self.metadata.insert(key.to_string(), value);
+    }
+
+    pub fn tile_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn finish(self, options: &PmtilesOptions) -> Result<Vec<u8>, Error> {
+        let (min_zoom, max_zoom) = self.zooms.unwrap_or((0, 0));
+        let bounds = self.bounds();
+
+        // tile data in tile id order, repeated contents point back at their first copy and
+        // consecutive repeats share a single run
+        let mut data = Vec::new();
+        let mut entries: Vec<Entry> = Vec::new();
+        let mut stored: HashMap<Vec<u8>, (u64, u32)> = HashMap::new();
+        for (&id, tile) in &self.tiles {
+            let tile = match self.tile_type {
+                PmtilesTileType::Mvt => gzip(tile)?,
+                PmtilesTileType::Png => tile.clone(),
+            };
+
+            let (offset, length) = match stored.get(&tile) {
+                Some(&location) => location,
+                None => {
+                    let location = (data.len() as u64, tile.len() as u32);
+                    data.extend_from_slice(&tile);
+                    stored.insert(tile, location);
+                    location
+                }
+            };
+
+            if let Some(last) = entries.last_mut() {
+                if last.offset == offset && last.tile_id + last.run_length as u64 == id {
+                    last.run_length += 1;
+                    continue;
+                }
+            }
+            entries.push(Entry {
+                tile_id: id,
+                offset,
+                length,
+                run_length: 1,
+            });
+        }
+
+        let (root, leaves) = build_directories(&entries)?;
+
+        let mut metadata = Map::new();
+        metadata.insert("name".into(), json!(options.name));
+        metadata.insert("description".into(), json!(options.description));
+        if let Some(attribution) = &options.attribution {
+            metadata.insert("attribution".into(), json!(attribution));
+        }
+        metadata.insert("type".into(), json!("overlay"));
+        let format = match self.tile_type {
+            PmtilesTileType::Mvt => "pbf",
+            PmtilesTileType::Png => "png",
+        };
+        metadata.insert("format".into(), json!(format));
+        metadata.insert("minzoom".into(), json!(min_zoom));
+        metadata.insert("maxzoom".into(), json!(max_zoom));
+        metadata.insert("bounds".into(), json!(bounds));
+        metadata.extend(self.metadata);
+        let metadata = gzip(Value::Object(metadata).to_string().as_bytes())?;
+
+        let root_offset = HEADER_LENGTH as u64;
+        let metadata_offset = root_offset + root.len() as u64;
+        let leaves_offset = metadata_offset + metadata.len() as u64;
+        let data_offset = leaves_offset + leaves.len() as u64;
+
+        let mut out = Vec::with_capacity(data_offset as usize + data.len());
+        out.extend_from_slice(b"PMTiles");
+        out.push(3);
+        for value in [
+            root_offset,
+            root.len() as u64,
+            metadata_offset,
+            metadata.len() as u64,
+            leaves_offset,
+            leaves.len() as u64,
+            data_offset,
+            data.len() as u64,
+            self.tiles.len() as u64,
+            entries.len() as u64,
+            stored.len() as u64,
+        ] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let tile_compression = match self.tile_type {
+            PmtilesTileType::Mvt => COMPRESSION_GZIP,
+            PmtilesTileType::Png => COMPRESSION_NONE,
+        };
+        let tile_type = match self.tile_type {
+            PmtilesTileType::Mvt => 1,
+            PmtilesTileType::Png => 2,
+        };
+        // clustered, tile data is laid out in tile id order
+        out.extend_from_slice(&[1, COMPRESSION_GZIP, tile_compression, tile_type]);
+        out.extend_from_slice(&[min_zoom, max_zoom]);
+
+        let e7 = |degrees: f64| ((degrees * 1e7).round() as i32).to_le_bytes();
+        let [min_lon, min_lat, max_lon, max_lat] = bounds;
+        for degrees in [min_lon, min_lat, max_lon, max_lat] {
+            out.extend_from_slice(&e7(degrees));
+        }
+        out.push(min_zoom);
+        out.extend_from_slice(&e7((min_lon + max_lon) / 2.0));
+        out.extend_from_slice(&e7((min_lat + max_lat) / 2.0));
+
+        out.extend_from_slice(&root);
+        out.extend_from_slice(&metadata);
+        out.extend_from_slice(&leaves);
+        out.extend_from_slice(&data);
+        Ok(out)
+    }
+
+    // [min_lon, min_lat, max_lon, max_lat] covered by the tiles of the deepest zoom
+    fn bounds(&self) -> [f64; 4] {
+        let Some((_, max_zoom)) = self.zooms else {
+            return [-180.0, -MAX_MERCATOR_LAT, 180.0, MAX_MERCATOR_LAT];
+        };
+
+        let mut bounds = [f64::MAX, f64::MAX, f64::MIN, f64::MIN];
+        for &id in self.tiles.keys() {
+            let (z, x, y) = tile_id_to_zxy(id);
+            if z != max_zoom {
+                continue;
+            }
+            let [min_lat, min_lon, max_lat, max_lon] = tile_bounds(z, x, y);
+            bounds[0] = bounds[0].min(min_lon);
+            bounds[1] = bounds[1].min(min_lat);
+            bounds[2] = bounds[2].max(max_lon);
+            bounds[3] = bounds[3].max(max_lat);
+        }
+        bounds
+    }
+}
+
+pub fn vector_tiles_to_pmtiles(
+    tiles: &[VectorTile],
+    mvt_options: &MvtOptions,
+    options: &PmtilesOptions,
+) -> Result<Vec<u8>, Error> {
+    let mut writer = PmtilesWriter::new(PmtilesTileType::Mvt);
+    for tile in tiles {
+        writer.add_tile(tile.z, tile.x, tile.y, tile.data.clone());
+    }
+
+    // fields of segment tiles, heatmap tiles only carry frequency
+    let (min_zoom, max_zoom) = writer.zooms.unwrap_or((0, 0));
+    writer.set_metadata(
+        "vector_layers",
+        json!([{
+            "id": mvt_options.layer_name,
+            "minzoom": min_zoom,
+            "maxzoom": max_zoom,
+            "fields": {
+                "frequency": "Number",
+                "passes": "Number",
+                "activities": "Number",
+                "forward": "Number",
+                "backward": "Number",
+                "bearing": "Number",
+            },
+        }]),
+    );
+    writer.finish(options)
+}
+
+pub fn raster_tiles_to_pmtiles(
+    tiles: &[RasterTile],
+    options: &PmtilesOptions,
+) -> Result<Vec<u8>, Error> {
+    let mut writer = PmtilesWriter::new(PmtilesTileType::Png);
+    for tile in tiles {
+        writer.add_tile(tile.z, tile.x, tile.y, tile.image.to_png()?);
+    }
+    writer.finish(options)
+}
+
+// renders and packs in one go, each tile is encoded to png as soon as it is drawn so only the
+// compressed tiles are held, not the raw pixels of the whole pyramid
+pub fn heatmap_to_pmtiles_raster(
+    result: &HeatmapResult,
+    tile_options: &TileOptions,
+    options: &PmtilesOptions,
+) -> Result<Vec<u8>, Error> {
+    let mut writer = PmtilesWriter::new(PmtilesTileType::Png);
+    for_each_heatmap_tile(result, tile_options, |tile| {
+        writer.add_tile(tile.z, tile.x, tile.y, tile.image.to_png()?);
+        Ok(())
+    })?;
+    writer.finish(options)
+}
+
+// position along a hilbert curve per zoom level, after all tiles of the lower zooms
+pub fn zxy_to_tile_id(z: u8, x: u32, y: u32) -> u64 {
+    let base = ((1u64 << (2 * z as u32)) - 1) / 3;
+    let n = 1u64 << z;
+    let (mut x, mut y) = (x as u64, y as u64);
+
+    let mut d = 0;
+    let mut s = n / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as u64;
+        let ry = (y & s > 0) as u64;
+        d += s * s * ((3 * rx) ^ ry);
+        (x, y) = rotate(n, x, y, rx, ry);
+        s /= 2;
+    }
+    base + d
+}
+
+pub fn tile_id_to_zxy(id: u64) -> (u8, u32, u32) {
+    let mut z = 0u8;
+    let mut base = 0u64;
+    while base + (1u64 << (2 * z as u32)) <= id {
+        base += 1u64 << (2 * z as u32);
+        z += 1;
+    }
+
+    let n = 1u64 << z;
+    let mut d = id - base;
+    let (mut x, mut y) = (0u64, 0u64);
+    let mut s = 1;
+    while s < n {
+        let rx = 1 & (d / 2);
+        let ry = 1 & (d ^ rx);
+        (x, y) = rotate(s, x, y, rx, ry);
+        x += s * rx;
+        y += s * ry;
+        d /= 4;
+        s *= 2;
+    }
+    (z, x as u32, y as u32)
+}
+
+fn rotate(n: u64, x: u64, y: u64, rx: u64, ry: u64) -> (u64, u64) {
+    if ry != 0 {
+        return (x, y);
+    }
+    if rx == 1 {
+        (n - 1 - y, n - 1 - x)
+    } else {
+        (y, x)
+    }
+}
+
+// a root directory under ROOT_LIMIT, with leaf directories once the entries don't fit
+fn build_directories(entries: &[Entry]) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    let root = encode_directory(entries)?;
+    if root.len() <= ROOT_LIMIT {
+        return Ok((root, Vec::new()));
+    }
+
+    let mut leaf_size = 4096;
+    loop {
+        let mut leaves = Vec::new();
+        let mut root_entries = Vec::new();
+        for chunk in entries.chunks(leaf_size) {
+            let leaf = encode_directory(chunk)?;
+            root_entries.push(Entry {
+                tile_id: chunk[0].tile_id,
+                offset: leaves.len() as u64,
+                length: leaf.len() as u32,
+                run_length: 0,
+            });
+            leaves.extend_from_slice(&leaf);
+        }
+
+        let root = encode_directory(&root_entries)?;
+        if root.len() <= ROOT_LIMIT {
+            return Ok((root, leaves));
+        }
+        leaf_size *= 2;
+    }
+}
+
+// column wise varints: count, tile id deltas, run lengths, lengths, then offsets where 0
+// means directly after the previous entry
+fn encode_directory(entries: &[Entry]) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::new();
+    write_varint(&mut out, entries.len() as u64);
+
+    let mut last_id = 0;
+    for entry in entries {
+        write_varint(&mut out, entry.tile_id - last_id);
+        last_id = entry.tile_id;
+    }
+    for entry in entries {
+        write_varint(&mut out, entry.run_length as u64);
+    }
+    for entry in entries {
+        write_varint(&mut out, entry.length as u64);
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        let follows = i > 0 && {
+            let previous = &entries[i - 1];
+            entry.offset == previous.offset + previous.length as u64
+        };
+        write_varint(&mut out, if follows { 0 } else { entry.offset + 1 });
+    }
+
+    gzip(&out)
+}
+
+fn gzip(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(bytes)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| Error::Encode(e.to_string()))
+}
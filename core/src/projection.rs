@@ -0,0 +1,116 @@
+// EPSG:4326 <-> EPSG:3857 web mercator and the slippy map tile and pixel grids built on it.
+// points are [lat, lon] like everywhere else in the crate, projected ones [x, y] with y
+// growing north in meters and growing south (down the screen) in pixels and tiles
+use crate::{split_at_antimeridian, split_bbox_at_antimeridian, MAX_TILE_ZOOM};
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+// sphere radius of web mercator, the WGS84 semi-major axis
+pub const MERCATOR_RADIUS_M: f64 = 6_378_137.0;
+// latitude where the projected world becomes square, anything further is clamped
+pub const MAX_MERCATOR_LAT: f64 = 85.051_128_779_806_59;
+
+pub fn lat_lon_to_mercator([lat, lon]: [f64; 2]) -> [f64; 2] {
+    let lat = lat.clamp(-MAX_MERCATOR_LAT, MAX_MERCATOR_LAT).to_radians();
+    [
+        MERCATOR_RADIUS_M * lon.to_radians(),
+        MERCATOR_RADIUS_M * (FRAC_PI_4 + lat / 2.0).tan().ln(),
+    ]
+}
+
+pub fn mercator_to_lat_lon([x, y]: [f64; 2]) -> [f64; 2] {
+    let lat = 2.0 * (y / MERCATOR_RADIUS_M).exp().atan() - FRAC_PI_2;
+    [lat.to_degrees(), (x / MERCATOR_RADIUS_M).to_degrees()]
+}
+
+// global pixel coordinates at a zoom, which may be fractional. the world is tile_size * 2^zoom
+// pixels wide with the origin in the north west corner
+pub fn lat_lon_to_pixel([lat, lon]: [f64; 2], zoom: f64, tile_size: u32) -> [f64; 2] {
+    let scale = world_size(zoom, tile_size);
+    [mercator_x(lon) * scale, mercator_y(lat) * scale]
+}
+
+pub fn pixel_to_lat_lon([x, y]: [f64; 2], zoom: f64, tile_size: u32) -> [f64; 2] {
+    let scale = world_size(zoom, tile_size);
+    [mercator_lat(y / scale), mercator_lon(x / scale)]
+}
+
+// (x, y) of the tile containing the point, clamped to the tiles that exist
+pub fn lat_lon_to_tile(point: [f64; 2], zoom: u8) -> (u32, u32) {
+    let zoom = zoom.min(MAX_TILE_ZOOM);
+    let [x, y] = lat_lon_to_pixel(point, zoom as f64, 1);
+    let last = ((1u64 << zoom) - 1) as f64;
+    (
+        x.floor().clamp(0.0, last) as u32,
+        y.floor().clamp(0.0, last) as u32,
+    )
+}
+
+// [min_lat, min_lon, max_lat, max_lon] covered by a tile
+pub fn tile_bounds(z: u8, x: u32, y: u32) -> [f64; 4] {
+    let n = (1u64 << z.min(MAX_TILE_ZOOM)) as f64;
+    [
+        mercator_lat((y as f64 + 1.0) / n),
+        mercator_lon(x as f64 / n),
+        mercator_lat(y as f64 / n),
+        mercator_lon((x as f64 + 1.0) / n),
+    ]
+}
+
+// [min_x, min_y, max_x, max_y], inclusive, of the tiles a bbox [min_lat, min_lon, max_lat,
+// max_lon] touches, e.g. the ones to fetch or draw for a viewport. a bbox over the
+// antimeridian gives min_x > max_x, the columns then run from min_x to the last one and on
+// from 0 to max_x; tile_ranges has them as two plain ranges
+pub fn tile_range(bbox: [f64; 4], zoom: u8) -> [u32; 4] {
+    let [min_lat, min_lon, max_lat, max_lon] = bbox;
+    let (min_x, min_y) = lat_lon_to_tile([max_lat, min_lon], zoom);
+    let (max_x, max_y) = lat_lon_to_tile([min_lat, max_lon], zoom);
+    [min_x, min_y, max_x, max_y]
+}
+
+// tile_range of the one or two boxes either side of the antimeridian, each with min_x <= max_x
+pub fn tile_ranges(bbox: [f64; 4], zoom: u8) -> Vec<[u32; 4]> {
+    split_bbox_at_antimeridian(bbox)
+        .into_iter()
+        .map(|part| tile_range(part, zoom))
+        .collect()
+}
+
+// ground distance one pixel covers at a latitude, mercator stretches it by 1 / cos(lat)
+pub fn meters_per_pixel(lat: f64, zoom: f64, tile_size: u32) -> f64 {
+    let lat = lat.clamp(-MAX_MERCATOR_LAT, MAX_MERCATOR_LAT).to_radians();
+    2.0 * PI * MERCATOR_RADIUS_M * lat.cos() / world_size(zoom, tile_size)
+}
+
+fn world_size(zoom: f64, tile_size: u32) -> f64 {
+    tile_size.max(1) as f64 * zoom.exp2()
+}
+
+// normalized web mercator, the world is the unit square with y growing south
+pub(crate) fn mercator_x(lon: f64) -> f64 {
+    (lon + 180.0) / 360.0
+}
+
+// a track in normalized mercator, cut at ±180 so a step over it isn't drawn across the world
+pub(crate) fn project_track(coordinates: &[[f64; 2]]) -> Vec<Vec<[f64; 2]>> {
+    split_at_antimeridian(coordinates)
+        .iter()
+        .map(|part| {
+            part.iter()
+                .map(|&[lat, lon]| [mercator_x(lon), mercator_y(lat)])
+                .collect()
+        })
+        .collect()
+}
+
+pub(crate) fn mercator_y(lat: f64) -> f64 {
+    let lat = lat.clamp(-MAX_MERCATOR_LAT, MAX_MERCATOR_LAT).to_radians();
+    (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0
+}
+
+pub(crate) fn mercator_lon(x: f64) -> f64 {
+    x * 360.0 - 180.0
+}
+
+pub(crate) fn mercator_lat(y: f64) -> f64 {
+    (PI * (1.0 - 2.0 * y)).sinh().atan().to_degrees()
+}
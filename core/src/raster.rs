@@ -0,0 +1,422 @@
+// canvas-free rendering of heatmaps into RGBA images and PNG files, for places without a
+// browser map like email reports. tracks are drawn as anti-aliased lines in web mercator,
+// overlapping tracks add up and the sum is colored with a ramp
+use crate::projection::{mercator_x, mercator_y};
+use crate::{
+    bbox_crosses_antimeridian, get_bounding_box_rust, Error, HeatmapResult, SegmentHeatmapResult,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct RenderOptions {
+    pub width: u32,
+    pub height: u32,
+    // [min_lat, min_lon, max_lat, max_lon] stretched over the whole image, fitted around the
+    // tracks with the aspect ratio kept when missing
+    pub bbox: Option<[f64; 4]>,
+    // pixels kept free around fitted tracks
+    pub padding: u32,
+    // pixels
+    pub line_width: f64,
+    // evenly spaced stops from the least to the most used pixels
+    pub color_ramp: Vec<[u8; 4]>,
+    pub background: [u8; 4],
+    // summed intensity that reaches the end of the ramp, the busiest pixel when missing
+    pub saturation: Option<f64>,
+    // position on the ramp follows log(1 + intensity), so a track ridden once stays visible
+    // next to one ridden hundreds of times
+    pub log_scale: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            width: 1024,
+            height: 1024,
+            bbox: None,
+            padding: 16,
+            line_width: 2.0,
+            color_ramp: vec![
+                [0, 0, 128, 0],
+                [0, 90, 255, 160],
+                [0, 220, 255, 220],
+                [255, 240, 0, 255],
+                [255, 255, 255, 255],
+            ],
+            background: [0, 0, 0, 0],
+            saturation: None,
+            log_scale: true,
+        }
+    }
+}
+
+// row major, 4 bytes per pixel, not premultiplied
+#[derive(Clone, Debug, PartialEq)]
+pub struct RasterImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl RasterImage {
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        [
+            self.pixels[i],
+            self.pixels[i + 1],
+            self.pixels[i + 2],
+            self.pixels[i + 3],
+        ]
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>, Error> {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.pixels))
+            .map_err(|e| Error::Encode(e.to_string()))?;
+        Ok(png)
+    }
+}
+
+// each track is drawn with its frequency relative to max_frequency
+pub fn render_heatmap(result: &HeatmapResult, options: &RenderOptions) -> RasterImage {
+    let max_frequency = result.max_frequency.max(1) as f64;
+    render(
+        result.tracks.iter().map(|track| {
+            (
+                track.coordinates.as_slice(),
+                track.frequency as f64 / max_frequency,
+            )
+        }),
+        options,
+    )
+}
+
+pub fn render_segment_heatmap(
+    result: &SegmentHeatmapResult,
+    options: &RenderOptions,
+) -> RasterImage {
+    let max_frequency = result.max_frequency.max(1) as f64;
+    render(
+        result.segments.iter().map(|segment| {
+            (
+                segment.coordinates.as_slice(),
+                segment.frequency as f64 / max_frequency,
+            )
+        }),
+        options,
+    )
+}
+
+// every track counts the same, the brightness comes from overlap alone
+pub fn render_tracks(tracks: &[Vec<[f64; 2]>], options: &RenderOptions) -> RasterImage {
+    render(tracks.iter().map(|track| (track.as_slice(), 1.0)), options)
+}
+
+fn render<'a>(
+    tracks: impl Iterator<Item = (&'a [[f64; 2]], f64)> + Clone,
+    options: &RenderOptions,
+) -> RasterImage {
+    let (width, height) = (options.width, options.height);
+    let mut canvas = Canvas::new(width as usize, height as usize);
+
+    let bbox = options.bbox.or_else(|| {
+        let points: Vec<[f64; 2]> = tracks
+            .clone()
+            .flat_map(|(track, _)| track.iter().copied())
+            .collect();
+        (!points.is_empty()).then(|| get_bounding_box_rust(&points))
+    });
+
+    if let Some(bbox) = bbox {
+        let viewport = match options.bbox {
+            Some(_) => Viewport::stretched(bbox, width, height),
+            None => Viewport::fitted(bbox, width, height, options.padding),
+        };
+        let half_width = options.line_width.max(0.0) / 2.0;
+
+        for (track, value) in tracks {
+            let pixels: Vec<[f64; 2]> = track.iter().map(|&point| viewport.pixel(point)).collect();
+            canvas.draw_track(&pixels, half_width, value as f32);
+        }
+    }
+
+    let saturation = options
+        .saturation
+        .map(|s| s as f32)
+        .unwrap_or_else(|| canvas.max_value());
+
+    RasterImage {
+        width,
+        height,
+        pixels: colorize(&canvas.accumulation, saturation, options),
+    }
+}
+
+// accumulated intensities to RGBA with the options' ramp, scale and background
+pub(crate) fn colorize(accumulation: &[f32], saturation: f32, options: &RenderOptions) -> Vec<u8> {
+    let scale = |value: f32| {
+        if options.log_scale {
+            value.ln_1p()
+        } else {
+            value
+        }
+    };
+
+    let mut pixels = Vec::with_capacity(accumulation.len() * 4);
+    for &value in accumulation {
+        let color = if value > 0.0 && saturation > 0.0 {
+            let position = (scale(value) / scale(saturation)).min(1.0);
+            over(
+                ramp_color(&options.color_ramp, position),
+                options.background,
+            )
+        } else {
+            options.background
+        };
+        pixels.extend_from_slice(&color);
+    }
+    pixels
+}
+
+// maps coordinates to pixels through normalized web mercator, y grows downwards
+struct Viewport {
+    origin: [f64; 2],
+    scale: [f64; 2],
+    offset: [f64; 2],
+    // middle x of a bbox across the antimeridian, points are moved by whole worlds to within
+    // half a world of it so the box continues east past 180 instead of running backwards
+    wrap_center: Option<f64>,
+}
+
+impl Viewport {
+    fn stretched(bbox: [f64; 4], width: u32, height: u32) -> Self {
+        let (origin, span, wrap_center) = Self::extent(bbox);
+        let span = [span[0].max(f64::EPSILON), span[1].max(f64::EPSILON)];
+
+        Self {
+            origin,
+            scale: [width as f64 / span[0], height as f64 / span[1]],
+            offset: [0.0, 0.0],
+            wrap_center,
+        }
+    }
+
+    fn fitted(bbox: [f64; 4], width: u32, height: u32, padding: u32) -> Self {
+        let (origin, span, wrap_center) = Self::extent(bbox);
+
+        let inner = [
+            (width as f64 - 2.0 * padding as f64).max(1.0),
+            (height as f64 - 2.0 * padding as f64).max(1.0),
+        ];
+        // a single point or a perfectly straight line has no extent along one axis
+        let scale = match (span[0] > 0.0, span[1] > 0.0) {
+            (true, true) => (inner[0] / span[0]).min(inner[1] / span[1]),
+            (true, false) => inner[0] / span[0],
+            (false, true) => inner[1] / span[1],
+            (false, false) => 1.0,
+        };
+
+        Self {
+            origin,
+            scale: [scale, scale],
+            offset: [
+                (width as f64 - span[0] * scale) / 2.0,
+                (height as f64 - span[1] * scale) / 2.0,
+            ],
+            wrap_center,
+        }
+    }
+
+    // north west corner and size of bbox in normalized mercator
+    fn extent(bbox: [f64; 4]) -> ([f64; 2], [f64; 2], Option<f64>) {
+        let [min_lat, min_lon, max_lat, max_lon] = bbox;
+        let crosses = bbox_crosses_antimeridian(bbox);
+        let origin = [mercator_x(min_lon), mercator_y(max_lat)];
+        // one whole world further east when the box runs over the antimeridian
+        let east = mercator_x(max_lon) + if crosses { 1.0 } else { 0.0 };
+        let span = [east - origin[0], mercator_y(min_lat) - origin[1]];
+        let wrap_center = crosses.then(|| origin[0] + span[0] / 2.0);
+        (origin, span, wrap_center)
+    }
+
+    fn pixel(&self, [lat, lon]: [f64; 2]) -> [f64; 2] {
+        let mut x = mercator_x(lon);
+        if let Some(center) = self.wrap_center {
+            x += (center - x + 0.5).floor();
+        }
+        [
+            (x - self.origin[0]) * self.scale[0] + self.offset[0],
+            (mercator_y(lat) - self.origin[1]) * self.scale[1] + self.offset[1],
+        ]
+    }
+}
+
+pub(crate) struct Canvas {
+    width: usize,
+    height: usize,
+    pub(crate) accumulation: Vec<f32>,
+    // coverage of the track being drawn, so a track's own joints don't add up
+    stamp: Vec<f32>,
+    touched: Vec<usize>,
+}
+
+impl Canvas {
+    pub(crate) fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            accumulation: vec![0.0; width * height],
+            stamp: vec![0.0; width * height],
+            touched: Vec::new(),
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.accumulation.fill(0.0);
+    }
+
+    pub(crate) fn max_value(&self) -> f32 {
+        self.accumulation.iter().copied().fold(0.0, f32::max)
+    }
+
+    // points are in pixels, value is what the track adds where it fully covers a pixel
+    pub(crate) fn draw_track(&mut self, points: &[[f64; 2]], half_width: f64, value: f32) {
+        self.draw_parts([points], half_width, value);
+    }
+
+    // a track drawn as separate lines, a pixel more than one of them covers counts it once
+    pub(crate) fn draw_parts<'a>(
+        &mut self,
+        parts: impl IntoIterator<Item = &'a [[f64; 2]]>,
+        half_width: f64,
+        value: f32,
+    ) {
+        for points in parts {
+            match points {
+                [] => {}
+                [point] => self.draw_segment(*point, *point, half_width),
+                _ => {
+                    for window in points.windows(2) {
+                        self.draw_segment(window[0], window[1], half_width);
+                    }
+                }
+            }
+        }
+
+        for &i in &self.touched {
+            self.accumulation[i] += self.stamp[i] * value;
+            self.stamp[i] = 0.0;
+        }
+        self.touched.clear();
+    }
+
+    // coverage falls off over one pixel at the edge of the line, which is the anti-aliasing
+    fn draw_segment(&mut self, a: [f64; 2], b: [f64; 2], half_width: f64) {
+        if !(a.iter().chain(&b).all(|v| v.is_finite())) {
+            return;
+        }
+
+        let reach = half_width + 0.5;
+        let (min_y, max_y) = (a[1].min(b[1]) - reach, a[1].max(b[1]) + reach);
+        let (min_x, max_x) = (a[0].min(b[0]) - reach, a[0].max(b[0]) + reach);
+        let first_row = min_y.floor().max(0.0) as usize;
+        let last_row = (max_y.ceil().min(self.height as f64) as usize).min(self.height);
+
+        let delta = [b[0] - a[0], b[1] - a[1]];
+        let length = delta[0].hypot(delta[1]);
+
+        for row in first_row..last_row {
+            let center_y = row as f64 + 0.5;
+
+            // only the columns near where the line crosses this row can be covered
+            let (from, to) = if delta[1].abs() > f64::EPSILON {
+                let x = a[0] + (center_y - a[1]) * delta[0] / delta[1];
+                let band = reach * length / delta[1].abs();
+                ((x - band).max(min_x), (x + band).min(max_x))
+            } else {
+                (min_x, max_x)
+            };
+            if from > to {
+                continue;
+            }
+
+            let first_column = from.floor().max(0.0) as usize;
+            let last_column = (to.ceil().min(self.width as f64).max(0.0) as usize).min(self.width);
+            for column in first_column..last_column {
+                let center = [column as f64 + 0.5, center_y];
+                let coverage = (reach - distance_to_segment(center, a, b)).clamp(0.0, 1.0) as f32;
+                if coverage <= 0.0 {
+                    continue;
+                }
+
+                let i = row * self.width + column;
+                if self.stamp[i] == 0.0 {
+                    self.touched.push(i);
+                }
+                self.stamp[i] = self.stamp[i].max(coverage);
+            }
+        }
+    }
+}
+
+fn distance_to_segment(point: [f64; 2], a: [f64; 2], b: [f64; 2]) -> f64 {
+    let delta = [b[0] - a[0], b[1] - a[1]];
+    let length_squared = delta[0] * delta[0] + delta[1] * delta[1];
+    let t = if length_squared > 0.0 {
+        (((point[0] - a[0]) * delta[0] + (point[1] - a[1]) * delta[1]) / length_squared)
+            .clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+
+    let closest = [a[0] + delta[0] * t, a[1] + delta[1] * t];
+    (point[0] - closest[0]).hypot(point[1] - closest[1])
+}
+
+fn ramp_color(ramp: &[[u8; 4]], t: f32) -> [u8; 4] {
+    match ramp {
+        [] => [255, 255, 255, 255],
+        [color] => *color,
+        _ => {
+            let position = t.clamp(0.0, 1.0) * (ramp.len() - 1) as f32;
+            let index = (position.floor() as usize).min(ramp.len() - 2);
+            let fraction = position - index as f32;
+
+            let (from, to) = (ramp[index], ramp[index + 1]);
+            let mut color = [0; 4];
+            for channel in 0..4 {
+                color[channel] = (from[channel] as f32
+                    + (to[channel] as f32 - from[channel] as f32) * fraction)
+                    .round() as u8;
+            }
+            color
+        }
+    }
+}
+
+// source over destination with straight alpha
+fn over(source: [u8; 4], destination: [u8; 4]) -> [u8; 4] {
+    let source_alpha = source[3] as f32 / 255.0;
+    let destination_alpha = destination[3] as f32 / 255.0 * (1.0 - source_alpha);
+    let alpha = source_alpha + destination_alpha;
+    if alpha <= 0.0 {
+        return [0, 0, 0, 0];
+    }
+
+    let mut color = [0; 4];
+    for channel in 0..3 {
+        color[channel] = ((source[channel] as f32 * source_alpha
+            + destination[channel] as f32 * destination_alpha)
+            / alpha)
+            .round() as u8;
+    }
+    color[3] = (alpha * 255.0).round() as u8;
+    color
+}
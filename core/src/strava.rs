@@ -0,0 +1,142 @@
+use crate::{is_valid_coordinate, TrackPoint};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// Strava streams API reference: https://developers.strava.com/docs/reference/#api-Streams
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StravaStreamPoint {
+    pub lat: f64,
+    pub lon: f64,
+    pub elapsed_seconds: Option<u32>, // offset from the activity start, from the time stream
+    pub timestamp_ms: Option<i64>,    // unix epoch millis when the activity start is known
+    pub altitude: Option<f64>,
+    pub distance: Option<f64>, // meters from the start
+    pub heartrate: Option<u32>,
+    pub velocity: Option<f64>, // smoothed, meters per second
+    pub moving: Option<bool>,
+}
+
+impl From<&StravaStreamPoint> for TrackPoint {
+    fn from(point: &StravaStreamPoint) -> Self {
+        TrackPoint {
+            lat: point.lat,
+            lon: point.lon,
+            elevation: point.altitude,
+            timestamp_ms: point.timestamp_ms,
+        }
+    }
+}
+
+// raw streams as returned by GET /activities/{id}/streams, in either the default
+// array shape or the key_by_type=true object shape
+#[derive(Default)]
+struct StravaStreams {
+    latlng: Vec<[f64; 2]>,
+    time: Vec<Option<f64>>,
+    altitude: Vec<Option<f64>>,
+    distance: Vec<Option<f64>>,
+    heartrate: Vec<Option<f64>>,
+    velocity_smooth: Vec<Option<f64>>,
+    moving: Vec<Option<bool>>,
+}
+
+pub fn parse_strava_streams(
+    json: &str,
+    start_time_ms: Option<i64>,
+) -> Result<Vec<StravaStreamPoint>, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("invalid json: {e}"))?;
+    strava_streams_from_value(&value, start_time_ms)
+}
+
+pub fn strava_streams_from_value(
+    value: &Value,
+    start_time_ms: Option<i64>,
+) -> Result<Vec<StravaStreamPoint>, String> {
+    let streams = collect_streams(value)?;
+    if streams.latlng.is_empty() {
+        return Err("activity has no latlng stream".to_string());
+    }
+
+    // every stream shares the same index, points without a position are dropped
+    let points = streams
+        .latlng
+        .iter()
+        .enumerate()
+        .filter(|(_, &[lat, lon])| is_valid_coordinate(lat, lon))
+        .map(|(i, &[lat, lon])| {
+            let elapsed = streams.time.get(i).copied().flatten();
+            StravaStreamPoint {
+                lat,
+                lon,
+                elapsed_seconds: elapsed.map(|seconds| seconds as u32),
+                timestamp_ms: start_time_ms
+                    .zip(elapsed)
+                    .map(|(start, seconds)| start + (seconds * 1000.0) as i64),
+                altitude: streams.altitude.get(i).copied().flatten(),
+                distance: streams.distance.get(i).copied().flatten(),
+                heartrate: streams
+                    .heartrate
+                    .get(i)
+                    .copied()
+                    .flatten()
+                    .map(|bpm| bpm as u32),
+                velocity: streams.velocity_smooth.get(i).copied().flatten(),
+                moving: streams.moving.get(i).copied().flatten(),
+            }
+        })
+        .collect();
+
+    Ok(points)
+}
+
+fn collect_streams(value: &Value) -> Result<StravaStreams, String> {
+    let mut streams = StravaStreams::default();
+
+    let entries: Vec<(&str, &Value)> = match value {
+        // [{"type": "latlng", "data": [...]}, ...]
+        Value::Array(list) => list
+            .iter()
+            .filter_map(|stream| Some((stream.get("type")?.as_str()?, stream.get("data")?)))
+            .collect(),
+        // {"latlng": {"data": [...]}, ...}
+        Value::Object(map) => map
+            .iter()
+            .filter_map(|(kind, stream)| Some((kind.as_str(), stream.get("data")?)))
+            .collect(),
+        _ => return Err("expected a streams array or object".to_string()),
+    };
+
+    for (kind, data) in entries {
+        let data = match data.as_array() {
+            Some(data) => data,
+            None => continue,
+        };
+
+        match kind {
+            "latlng" => {
+                streams.latlng = data
+                    .iter()
+                    .map(|pair| {
+                        let lat = pair.get(0).and_then(Value::as_f64).unwrap_or(f64::NAN);
+                        let lon = pair.get(1).and_then(Value::as_f64).unwrap_or(f64::NAN);
+                        [lat, lon]
+                    })
+                    .collect()
+            }
+            "time" => streams.time = numbers(data),
+            "altitude" => streams.altitude = numbers(data),
+            "distance" => streams.distance = numbers(data),
+            "heartrate" => streams.heartrate = numbers(data),
+            "velocity_smooth" => streams.velocity_smooth = numbers(data),
+            "moving" => streams.moving = data.iter().map(Value::as_bool).collect(),
+            _ => {}
+        }
+    }
+
+    Ok(streams)
+}
+
+fn numbers(data: &[Value]) -> Vec<Option<f64>> {
+    data.iter().map(Value::as_f64).collect()
+}
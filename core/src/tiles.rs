@@ -0,0 +1,284 @@
+// heatmaps cut into z/x/y web mercator raster tiles, to be served as a plain tile layer
+// instead of shipping every coordinate to the browser
+use crate::projection::project_track;
+use crate::raster::{colorize, Canvas};
+use crate::{HeatmapResult, RasterImage, RenderOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::ops::Range;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct TileOptions {
+    pub min_zoom: u8,
+    pub max_zoom: u8,
+    pub tile_size: u32,
+    // line width in pixels at min_zoom, growing linearly to render.line_width at max_zoom
+    pub min_line_width: f64,
+    // ramp, scale and background, width, height, bbox and padding don't apply to tiles. a
+    // fixed saturation is used as is, otherwise every zoom level is normalized to its own
+    // busiest pixel so neighbouring tiles match
+    pub render: RenderOptions,
+}
+
+impl Default for TileOptions {
+    fn default() -> Self {
+        Self {
+            min_zoom: 0,
+            max_zoom: 14,
+            tile_size: 256,
+            min_line_width: 1.0,
+            render: RenderOptions::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RasterTile {
+    pub z: u8,
+    pub x: u32,
+    pub y: u32,
+    pub image: RasterImage,
+}
+
+// index and value of every pixel a tile has drawn, what's kept of it until it can be colored
+type DrawnPixels = Vec<(u32, f32)>;
+
+// deepest zoom tiles can be cut for, 2^z tiles per axis still fit a u32
+pub const MAX_TILE_ZOOM: u8 = 24;
+
+// every tile of every level in memory at once, fine for a handful of zooms. for a full
+// z0-14 pyramid use for_each_heatmap_tile and encode or write each tile as it comes
+pub fn render_heatmap_tiles(result: &HeatmapResult, options: &TileOptions) -> Vec<RasterTile> {
+    let mut tiles = Vec::new();
+    let Ok(()) = for_each_heatmap_tile(result, options, |tile| {
+        tiles.push(tile);
+        Ok::<_, Infallible>(())
+    });
+    tiles
+}
+
+pub fn render_track_tiles(tracks: &[Vec<[f64; 2]>], options: &TileOptions) -> Vec<RasterTile> {
+    let mut tiles = Vec::new();
+    let Ok(()) = for_each_track_tile(tracks, options, |tile| {
+        tiles.push(tile);
+        Ok::<_, Infallible>(())
+    });
+    tiles
+}
+
+// hands every tile to emit as soon as its level is finished, an error from emit stops
+// rendering and is returned
+pub fn for_each_heatmap_tile<E>(
+    result: &HeatmapResult,
+    options: &TileOptions,
+    emit: impl FnMut(RasterTile) -> Result<(), E>,
+) -> Result<(), E> {
+    let max_frequency = result.max_frequency.max(1) as f64;
+    render_tiles(
+        result
+            .tracks
+            .iter()
+            .map(|track| {
+                (
+                    track.coordinates.as_slice(),
+                    track.frequency as f64 / max_frequency,
+                )
+            })
+            .collect(),
+        options,
+        emit,
+    )
+}
+
+pub fn for_each_track_tile<E>(
+    tracks: &[Vec<[f64; 2]>],
+    options: &TileOptions,
+    emit: impl FnMut(RasterTile) -> Result<(), E>,
+) -> Result<(), E> {
+    render_tiles(
+        tracks.iter().map(|track| (track.as_slice(), 1.0)).collect(),
+        options,
+        emit,
+    )
+}
+
+// sorted by zoom, then x, then y. tiles without a single drawn pixel are left out. each tile
+// is drawn once; unless the saturation is fixed a level's tiles are kept as their drawn
+// pixels only until its busiest pixel is known, then colored and emitted
+fn render_tiles<E>(
+    tracks: Vec<(&[[f64; 2]], f64)>,
+    options: &TileOptions,
+    mut emit: impl FnMut(RasterTile) -> Result<(), E>,
+) -> Result<(), E> {
+    let min_zoom = options.min_zoom.min(MAX_TILE_ZOOM);
+    let max_zoom = options.max_zoom.clamp(min_zoom, MAX_TILE_ZOOM);
+    let size = options.tile_size.max(1);
+
+    // normalized mercator, projected once for every zoom level
+    let projected: Vec<(Vec<Vec<[f64; 2]>>, f32)> = tracks
+        .iter()
+        .map(|(track, value)| (project_track(track), *value as f32))
+        .collect();
+
+    let mut canvas = Canvas::new(size as usize, size as usize);
+    let fixed_saturation = options
+        .render
+        .saturation
+        .map(|saturation| saturation as f32);
+    let tile = |z, (x, y), canvas: &Canvas, saturation| RasterTile {
+        z,
+        x,
+        y,
+        image: RasterImage {
+            width: size,
+            height: size,
+            pixels: colorize(&canvas.accumulation, saturation, &options.render),
+        },
+    };
+
+    for zoom in min_zoom..=max_zoom {
+        let half_width = line_width(options, zoom, min_zoom, max_zoom) / 2.0;
+        let margin = (half_width + 1.0) / size as f64;
+        let index = tile_index(
+            projected.iter().map(|(parts, _)| parts.as_slice()),
+            zoom,
+            margin,
+        );
+
+        let mut drawn: Vec<((u32, u32), DrawnPixels)> = Vec::new();
+        let mut busiest: f32 = 0.0;
+
+        for (&(x, y), members) in &index {
+            canvas.clear();
+            let scale = (1u64 << zoom) as f64 * size as f64;
+            // only the runs of each track that reach this tile, drawn together so a pixel two
+            // runs cover still counts the track once
+            for runs in members.chunk_by(|a, b| a.0 == b.0) {
+                let (parts, value) = &projected[runs[0].0];
+                let pixels: Vec<Vec<[f64; 2]>> = runs
+                    .iter()
+                    .map(|(_, part, points)| {
+                        parts[*part][points.clone()]
+                            .iter()
+                            .map(|p| {
+                                [
+                                    p[0] * scale - x as f64 * size as f64,
+                                    p[1] * scale - y as f64 * size as f64,
+                                ]
+                            })
+                            .collect()
+                    })
+                    .collect();
+                canvas.draw_parts(pixels.iter().map(Vec::as_slice), half_width, *value);
+            }
+
+            let max_value = canvas.max_value();
+            if max_value <= 0.0 {
+                continue;
+            }
+            match fixed_saturation {
+                Some(saturation) => emit(tile(zoom, (x, y), &canvas, saturation))?,
+                None => {
+                    busiest = busiest.max(max_value);
+                    let pixels = canvas
+                        .accumulation
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, value)| **value > 0.0)
+                        .map(|(i, value)| (i as u32, *value))
+                        .collect();
+                    drawn.push(((x, y), pixels));
+                }
+            }
+        }
+
+        for (position, pixels) in drawn {
+            canvas.clear();
+            for (i, value) in pixels {
+                canvas.accumulation[i as usize] = value;
+            }
+            emit(tile(zoom, position, &canvas, busiest))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn line_width(options: &TileOptions, zoom: u8, min_zoom: u8, max_zoom: u8) -> f64 {
+    let max_width = options.render.line_width.max(0.0);
+    let min_width = options.min_line_width.max(0.0);
+    if max_zoom == min_zoom {
+        return max_width;
+    }
+
+    let t = (zoom - min_zoom) as f64 / (max_zoom - min_zoom) as f64;
+    min_width + (max_width - min_width) * t
+}
+
+// a run of points of one track that touches a tile: the track, its part and the points of the
+// part, neighbouring segments in the same tile merged into one run
+pub(crate) type TileMember = (usize, usize, Range<usize>);
+
+// tracks, in normalized mercator and cut at ±180 into parts, that may touch each tile. every
+// segment is cut into the rows of tiles it crosses and only the columns its line, widened by
+// margin tiles, spans in that row are marked. a tile's members are sorted by track
+pub(crate) fn tile_index<'a>(
+    tracks: impl IntoIterator<Item = &'a [Vec<[f64; 2]>]>,
+    zoom: u8,
+    margin: f64,
+) -> BTreeMap<(u32, u32), Vec<TileMember>> {
+    let tiles_per_axis = (1u64 << zoom) as f64;
+    let tile = |value: f64| value.floor().clamp(0.0, tiles_per_axis - 1.0) as u32;
+
+    let mut index: BTreeMap<(u32, u32), Vec<TileMember>> = BTreeMap::new();
+    for (i, parts) in tracks.into_iter().enumerate() {
+        // every segment with the range of points it spans
+        let pairs = parts
+            .iter()
+            .enumerate()
+            .flat_map(|(part, points)| match points.as_slice() {
+                [point] => vec![(part, 0..1, *point, *point)],
+                _ => points
+                    .windows(2)
+                    .enumerate()
+                    .map(|(j, w)| (part, j..j + 2, w[0], w[1]))
+                    .collect(),
+            });
+
+        for (part, points, a, b) in pairs {
+            let a = [a[0] * tiles_per_axis, a[1] * tiles_per_axis];
+            let b = [b[0] * tiles_per_axis, b[1] * tiles_per_axis];
+            if !a.iter().chain(&b).all(|v| v.is_finite()) {
+                continue;
+            }
+
+            for y in tile(a[1].min(b[1]) - margin)..=tile(a[1].max(b[1]) + margin) {
+                // the part of the segment within this row, widened by the margin
+                let (top, bottom) = (y as f64 - margin, y as f64 + 1.0 + margin);
+                let (t0, t1) = if a[1] == b[1] {
+                    (0.0, 1.0)
+                } else {
+                    let ta = ((top - a[1]) / (b[1] - a[1])).clamp(0.0, 1.0);
+                    let tb = ((bottom - a[1]) / (b[1] - a[1])).clamp(0.0, 1.0);
+                    (ta.min(tb), ta.max(tb))
+                };
+                let (xa, xb) = (a[0] + (b[0] - a[0]) * t0, a[0] + (b[0] - a[0]) * t1);
+
+                for x in tile(xa.min(xb) - margin)..=tile(xa.max(xb) + margin) {
+                    let members = index.entry((x, y)).or_default();
+                    match members.last_mut() {
+                        Some((track, last_part, run))
+                            if *track == i && *last_part == part && run.end > points.start =>
+                        {
+                            run.end = run.end.max(points.end);
+                        }
+                        _ => members.push((i, part, points.clone())),
+                    }
+                }
+            }
+        }
+    }
+    index
+}
@@ -0,0 +1,113 @@
+#[cfg(test)]
+mod activity_count_tests {
+    use fastgeotoolkit::*;
+
+    // roughly a 400 m running track
+    fn lap() -> Vec<[f64; 2]> {
+        vec![
+            [51.5000, -0.1000],
+            [51.5010, -0.1000],
+            [51.5010, -0.0980],
+            [51.5000, -0.0980],
+            [51.5000, -0.1000],
+        ]
+    }
+
+    fn interval_session(laps: usize) -> Vec<[f64; 2]> {
+        let mut track = lap();
+        for _ in 1..laps {
+            track.extend_from_slice(&lap()[1..]);
+        }
+        track
+    }
+
+    fn builder_with(metric: FrequencyMetric) -> HeatmapBuilder {
+        let mut builder = HeatmapBuilder::with_options(HeatmapOptions {
+            frequency_metric: metric,
+            ..HeatmapOptions::default()
+        });
+        builder.add_track(interval_session(25));
+        builder.add_tracks(vec![lap(), lap(), lap()]);
+        builder
+    }
+
+    #[test]
+    fn test_passes_and_activities() {
+        let passes = builder_with(FrequencyMetric::Passes);
+        assert_eq!(passes.activity_count(), 4);
+        assert_eq!(passes.build().max_frequency, 28);
+
+        let activities = builder_with(FrequencyMetric::Activities);
+        let result = activities.build();
+        assert_eq!(result.max_frequency, 4);
+        assert!(result.tracks.iter().all(|track| track.frequency == 4));
+    }
+
+    #[test]
+    fn test_segments_report_both_counts() {
+        for metric in [FrequencyMetric::Passes, FrequencyMetric::Activities] {
+            let result = builder_with(metric).build_segments();
+
+            assert!(!result.segments.is_empty());
+            for segment in &result.segments {
+                assert_eq!((segment.passes, segment.activities), (28, 4));
+                let expected = match metric {
+                    FrequencyMetric::Passes => 28,
+                    FrequencyMetric::Activities => 4,
+                };
+                assert_eq!(segment.frequency, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_activity_with_several_tracks() {
+        let mut builder = HeatmapBuilder::with_options(HeatmapOptions {
+            frequency_metric: FrequencyMetric::Activities,
+            ..HeatmapOptions::default()
+        });
+        // a recording paused and resumed halfway along the same street
+        builder.add_activity(vec![lap(), lap(), vec![[51.5000, -0.1000]]]);
+
+        assert_eq!(builder.track_count(), 2);
+        assert_eq!(builder.activity_count(), 1);
+        assert_eq!(builder.build().max_frequency, 1);
+        assert_eq!(builder.build_segments().segments[0].passes, 2);
+    }
+
+    #[test]
+    fn test_merge_keeps_activities_together() {
+        let fine = HeatmapOptions {
+            grid_tolerance: 0.0005,
+            frequency_metric: FrequencyMetric::Activities,
+            ..HeatmapOptions::default()
+        };
+        let mut builder = HeatmapBuilder::with_options(fine.clone());
+        builder.add_track(lap());
+
+        let mut other = HeatmapBuilder::new();
+        other.add_activity(vec![lap(), lap()]);
+        other.add_track(lap());
+        builder.merge(other);
+
+        let mut single = HeatmapBuilder::with_options(fine);
+        single.add_track(lap());
+        single.add_activity(vec![lap(), lap()]);
+        single.add_track(lap());
+
+        assert_eq!(builder, single);
+        assert_eq!(builder.activity_count(), 3);
+        assert_eq!(builder.build().max_frequency, 3);
+    }
+
+    #[test]
+    fn test_metric_from_json() {
+        let options: HeatmapOptions =
+            serde_json::from_str(r#"{"frequency_metric": "activities"}"#).unwrap();
+        assert_eq!(options.frequency_metric, FrequencyMetric::Activities);
+        assert_eq!(
+            HeatmapOptions::default().frequency_metric,
+            FrequencyMetric::Passes
+        );
+    }
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::manual_range_contains)]
+
 #[cfg(test)]
 mod additional_coverage_tests {
     use fastgeotoolkit::*;
@@ -0,0 +1,141 @@
+#[cfg(test)]
+mod antimeridian_tests {
+    use fastgeotoolkit::*;
+
+    // fiji eastwards over the antimeridian towards samoa
+    const PACIFIC: [[f64; 2]; 4] = [
+        [-17.8, 178.0],
+        [-17.5, 179.9],
+        [-17.3, -179.9],
+        [-17.0, -178.0],
+    ];
+
+    #[test]
+    fn test_bounding_box_across_the_antimeridian() {
+        let bbox = get_bounding_box_rust(&PACIFIC);
+        assert_eq!(bbox, [-17.8, 178.0, -17.0, -178.0]);
+        assert!(bbox_crosses_antimeridian(bbox));
+        assert!((bbox_longitude_span(bbox) - 4.0).abs() < 1e-9);
+
+        // tracks away from it keep the plain box
+        let london = [[51.5, -0.2], [51.6, 0.1]];
+        assert_eq!(get_bounding_box_rust(&london), [51.5, -0.2, 51.6, 0.1]);
+        let wide = [[0.0, -100.0], [0.0, 0.0], [0.0, 100.0]];
+        assert_eq!(get_bounding_box_rust(&wide), [0.0, -100.0, 0.0, 100.0]);
+    }
+
+    #[test]
+    fn test_coverage_area_across_the_antimeridian() {
+        // the ~20 km step over the meridian itself
+        let crossing = PACIFIC[1..3].to_vec();
+        let (bbox, area_km2, points) = calculate_coverage_area_rust(&[crossing]).unwrap();
+        assert_eq!(points, 2);
+        assert!(bbox_crosses_antimeridian(bbox));
+
+        // a 50 m wide strip along the crossing, not the rest of the world
+        let length_km = calculate_distance_between_points(-17.5, 179.9, -17.3, -179.9);
+        assert!(
+            (area_km2 - length_km * 0.05).abs() < length_km * 0.05 * 0.05,
+            "{area_km2}"
+        );
+    }
+
+    #[test]
+    fn test_filtering_with_a_crossing_bbox() {
+        let bbox = [-18.0, 179.0, -17.0, -179.0];
+        let filtered = filter_coordinates_by_bounds_rust(&PACIFIC, bbox);
+        assert_eq!(filtered, vec![[-17.5, 179.9], [-17.3, -179.9]]);
+
+        assert!(bbox_contains(bbox, [-17.5, 180.0]));
+        assert!(!bbox_contains(bbox, [-17.5, 0.0]));
+        assert!(!bbox_contains(bbox, [-16.0, 179.5]));
+        assert_eq!(
+            split_bbox_at_antimeridian(bbox),
+            vec![[-18.0, 179.0, -17.0, 180.0], [-18.0, -180.0, -17.0, -179.0]]
+        );
+    }
+
+    #[test]
+    fn test_tracks_are_cut_at_the_antimeridian() {
+        let parts = split_at_antimeridian(&PACIFIC);
+        assert_eq!(parts.len(), 2);
+
+        // both sides end on the meridian at the interpolated latitude, halfway between
+        let [lat, lon] = *parts[0].last().unwrap();
+        assert_eq!(lon, 180.0);
+        assert!((lat - -17.4).abs() < 1e-9);
+        assert_eq!(parts[1][0], [lat, -180.0]);
+        assert_eq!(parts[0].len() + parts[1].len(), PACIFIC.len() + 2);
+
+        // and going west the other way round
+        let westward: Vec<[f64; 2]> = PACIFIC.iter().rev().copied().collect();
+        let parts = split_at_antimeridian(&westward);
+        assert_eq!(parts[0].last().unwrap()[1], -180.0);
+        assert_eq!(parts[1][0][1], 180.0);
+
+        // a track starting on the meridian doesn't leave a lone point behind
+        let parts = split_at_antimeridian(&[[0.0, 180.0], [0.0, -179.0]]);
+        assert_eq!(parts, vec![vec![[0.0, -180.0], [0.0, -179.0]]]);
+
+        assert_eq!(split_at_antimeridian(&[[51.5, -0.2], [51.6, 0.1]]).len(), 1);
+        assert!(split_at_antimeridian(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_geojson_multilinestring() {
+        let geojson =
+            coordinates_to_geojson_rust(&PACIFIC, serde_json::json!({"name": "crossing"}));
+        assert_eq!(geojson["geometry"]["type"], "MultiLineString");
+        assert_eq!(geojson["properties"]["name"], "crossing");
+
+        let lines = geojson["geometry"]["coordinates"].as_array().unwrap();
+        assert_eq!(lines.len(), 2);
+        // lng first, no coordinate strays outside ±180
+        assert_eq!(lines[0][0][0], 178.0);
+        assert_eq!(lines[0].as_array().unwrap().last().unwrap()[0], 180.0);
+        assert_eq!(lines[1][0][0], -180.0);
+        for line in lines {
+            for point in line.as_array().unwrap() {
+                assert!(point[0].as_f64().unwrap().abs() <= 180.0);
+            }
+        }
+
+        let plain =
+            coordinates_to_geojson_rust(&[[51.5, -0.2], [51.6, 0.1]], serde_json::json!({}));
+        assert_eq!(plain["geometry"]["type"], "LineString");
+    }
+
+    #[test]
+    fn test_geohash_cover_across_the_antimeridian() {
+        let bbox = [-17.5, 179.5, -17.0, -179.5];
+        let cover = geohash_cover(bbox, 4).unwrap();
+        assert!(!cover.is_empty());
+        for point in [[-17.2, 179.8], [-17.2, -179.8]] {
+            assert!(cover.contains(&encode_geohash(point[0], point[1], 4)));
+        }
+        // nothing from the far side of the world
+        assert!(!cover.contains(&encode_geohash(-17.2, 0.0, 4)));
+    }
+
+    #[test]
+    fn test_normalize_longitude() {
+        assert_eq!(normalize_longitude(190.0), -170.0);
+        assert_eq!(normalize_longitude(-190.0), 170.0);
+        assert_eq!(normalize_longitude(180.0), 180.0);
+        assert_eq!(normalize_longitude(540.0), -180.0);
+        assert_eq!(normalize_longitude(12.5), 12.5);
+    }
+
+    #[test]
+    fn test_tile_range_across_the_antimeridian() {
+        let bbox = [-17.5, 179.5, -17.0, -179.5];
+
+        // wrapped, the columns run from min_x over the edge of the world to max_x
+        let [min_x, min_y, max_x, max_y] = tile_range(bbox, 4);
+        assert_eq!([min_x, min_y, max_x, max_y], [15, 8, 0, 8]);
+
+        assert_eq!(tile_ranges(bbox, 4), vec![[15, 8, 15, 8], [0, 8, 0, 8]]);
+        let plain = [51.28, -0.51, 51.69, 0.33];
+        assert_eq!(tile_ranges(plain, 10), vec![tile_range(plain, 10)]);
+    }
+}
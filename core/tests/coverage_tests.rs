@@ -0,0 +1,174 @@
+#[cfg(test)]
+mod coverage_tests {
+    use fastgeotoolkit::*;
+
+    fn length_km(track: &[[f64; 2]]) -> f64 {
+        track
+            .windows(2)
+            .map(|w| calculate_distance_between_points(w[0][0], w[0][1], w[1][0], w[1][1]))
+            .sum()
+    }
+
+    // twice the signed area on the plain lng/lat plane, positive counter clockwise
+    fn orientation(ring: &[[f64; 2]]) -> f64 {
+        ring.windows(2)
+            .map(|w| w[0][1] * w[1][0] - w[1][1] * w[0][0])
+            .sum()
+    }
+
+    #[test]
+    fn test_straight_track_is_a_strip() {
+        // about 2 km along a parallel
+        let track = vec![[51.5, -0.15], [51.5, -0.121]];
+        // the length times the width plus the round caps at both ends
+        let expected = length_km(&track) * 0.05 + std::f64::consts::PI * 0.025 * 0.025;
+        let coverage = track_coverage(&[track], &CoverageOptions::default()).unwrap();
+        assert!(
+            (coverage.area_km2 - expected).abs() < expected * 0.03,
+            "{} vs {expected}",
+            coverage.area_km2
+        );
+
+        assert_eq!(coverage.polygons.len(), 1);
+        let outline = &coverage.polygons[0][0];
+        assert_eq!(outline.first(), outline.last());
+        assert!(orientation(outline) > 0.0);
+        assert_eq!(coverage.point_count, 2);
+        assert_eq!(coverage.bbox, [51.5, -0.15, 51.5, -0.121]);
+    }
+
+    #[test]
+    fn test_commutes_far_apart_only_count_themselves() {
+        let north = vec![[51.55, -0.20], [51.55, -0.17]];
+        let south = vec![[51.45, -0.02], [51.45, 0.01]];
+        let tracks = vec![north.clone(), south.clone()];
+        let coverage = track_coverage(&tracks, &CoverageOptions::default()).unwrap();
+
+        assert_eq!(coverage.polygons.len(), 2);
+        let strips = (length_km(&north) + length_km(&south)) * 0.05;
+        assert!((coverage.area_km2 - strips).abs() < strips * 0.05);
+
+        // the old bounding box answer was the whole city in between
+        let [min_lat, min_lon, max_lat, max_lon] = coverage.bbox;
+        let bbox_km2 = calculate_distance_between_points(min_lat, min_lon, max_lat, min_lon)
+            * calculate_distance_between_points(min_lat, min_lon, min_lat, max_lon);
+        assert!(coverage.area_km2 < bbox_km2 / 100.0);
+
+        let (_, area_km2, points) = calculate_coverage_area_rust(&tracks).unwrap();
+        assert_eq!((area_km2, points), (coverage.area_km2, 4));
+    }
+
+    #[test]
+    fn test_overlaps_are_counted_once() {
+        let track = vec![[51.5, -0.15], [51.51, -0.13], [51.5, -0.11]];
+        let options = CoverageOptions::default();
+        let thrice = track_coverage(&vec![track.clone(); 3], &options).unwrap();
+        let once = track_coverage(&[track], &options).unwrap();
+        assert_eq!(once.area_km2, thrice.area_km2);
+        assert_eq!(once.polygons, thrice.polygons);
+        assert_eq!(thrice.point_count, 9);
+    }
+
+    #[test]
+    fn test_loop_leaves_a_hole() {
+        // a lap around a block about 500 m across
+        let lap = vec![
+            [51.500, -0.130],
+            [51.500, -0.123],
+            [51.5045, -0.123],
+            [51.5045, -0.130],
+            [51.500, -0.130],
+        ];
+        let expected = length_km(&lap) * 0.05;
+        let coverage = track_coverage(&[lap], &CoverageOptions::default()).unwrap();
+
+        assert_eq!(coverage.polygons.len(), 1);
+        let rings = &coverage.polygons[0];
+        assert_eq!(rings.len(), 2);
+        assert!(orientation(&rings[0]) > 0.0);
+        assert!(orientation(&rings[1]) < 0.0);
+
+        // the block itself isn't covered, only the roads around it
+        assert!(
+            (coverage.area_km2 - expected).abs() < expected * 0.05,
+            "{} vs {expected}",
+            coverage.area_km2
+        );
+    }
+
+    #[test]
+    fn test_width_and_cell_size() {
+        let tracks = vec![vec![[40.7, -74.01], [40.72, -73.99]]];
+        let narrow = track_coverage(&tracks, &CoverageOptions::default()).unwrap();
+        let wide = track_coverage(
+            &tracks,
+            &CoverageOptions {
+                width_m: 100.0,
+                ..CoverageOptions::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(wide.width_m, 100.0);
+        let ratio = wide.area_km2 / narrow.area_km2;
+        assert!(ratio > 1.9 && ratio < 2.1, "{ratio}");
+
+        // too many cells for the limit, the grid gets coarser instead
+        let coarse = track_coverage(
+            &tracks,
+            &CoverageOptions {
+                max_cells: 1000,
+                ..CoverageOptions::default()
+            },
+        )
+        .unwrap();
+        assert!(coarse.cell_size_m > 5.0);
+        assert!(coarse.area_km2 > 0.0);
+    }
+
+    #[test]
+    fn test_polygons_are_cut_at_the_antimeridian() {
+        let crossing = vec![[-17.5, 179.99], [-17.5, -179.99]];
+        let expected = length_km(&crossing) * 0.05 + std::f64::consts::PI * 0.025 * 0.025;
+        let coverage = track_coverage(&[crossing], &CoverageOptions::default()).unwrap();
+
+        assert_eq!(coverage.polygons.len(), 2);
+        let lons: Vec<f64> = coverage
+            .polygons
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|p| p[1])
+            .collect();
+        assert!(lons.iter().all(|lon| lon.abs() <= 180.0));
+        assert!(lons.contains(&180.0) && lons.contains(&-180.0));
+
+        assert!(
+            (coverage.area_km2 - expected).abs() < expected * 0.05,
+            "{} vs {expected}",
+            coverage.area_km2
+        );
+    }
+
+    #[test]
+    fn test_geojson_and_empty_input() {
+        let track = vec![[51.5, -0.15], [51.5, -0.14]];
+        let coverage = track_coverage(&[track], &CoverageOptions::default()).unwrap();
+        let geojson = coverage.to_geojson();
+
+        assert_eq!(geojson["geometry"]["type"], "MultiPolygon");
+        assert_eq!(geojson["properties"]["area_km2"], coverage.area_km2);
+        assert_eq!(geojson["properties"]["width_m"], 50.0);
+        // lng first
+        let first = &geojson["geometry"]["coordinates"][0][0][0];
+        assert!((first[0].as_f64().unwrap() - -0.15).abs() < 0.01);
+        assert!((first[1].as_f64().unwrap() - 51.5).abs() < 0.01);
+
+        assert!(track_coverage(&[], &CoverageOptions::default()).is_none());
+        assert!(track_coverage(&[vec![]], &CoverageOptions::default()).is_none());
+
+        // a lone point is a disc
+        let dot = track_coverage(&[vec![[51.5, -0.15]]], &CoverageOptions::default()).unwrap();
+        let disc = std::f64::consts::PI * 0.025 * 0.025;
+        assert!((dot.area_km2 - disc).abs() < disc * 0.15);
+    }
+}
@@ -0,0 +1,152 @@
+#[cfg(test)]
+mod csv_tests {
+    use fastgeotoolkit::*;
+
+    const LOGGER_CSV: &str = "\
+Track,Lat,Lng,Time,Ele
+a,37.7749,-122.4194,2024-03-31T23:59:58Z,12.5
+a,37.7849,-122.4094,2024-03-31T23:59:59Z,13.0
+b,40.7128,-74.0060,2024-04-01 08:00:00+02:00,
+a,37.7949,-122.3994,1711929600,14.0
+b,not a number,-74.0160,,
+";
+
+    // european spreadsheet export with semicolons and decimal commas
+    const SPREADSHEET_CSV: &str = "\
+Breite;Länge;Höhe
+47,3769;8,5417;408
+47,3779;8,5427;410
+";
+
+    #[test]
+    fn test_auto_detected_mapping() {
+        let mapping = detect_csv_mapping(LOGGER_CSV.as_bytes(), &CsvOptions::default()).unwrap();
+
+        assert_eq!(mapping.delimiter, ',');
+        assert!(!mapping.decimal_comma);
+        assert_eq!(mapping.lat, 1);
+        assert_eq!(mapping.lon, 2);
+        assert_eq!(mapping.time, Some(3));
+        assert_eq!(mapping.elevation, Some(4));
+        assert_eq!(mapping.track_id, Some(0));
+    }
+
+    #[test]
+    fn test_split_into_tracks_by_id() {
+        let tracks = parse_csv(LOGGER_CSV.as_bytes(), &CsvOptions::default()).unwrap();
+
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].id.as_deref(), Some("a"));
+        assert_eq!(tracks[0].points.len(), 3);
+        assert_eq!(tracks[1].id.as_deref(), Some("b"));
+        // the row with an unparseable latitude is skipped
+        assert_eq!(tracks[1].points.len(), 1);
+        assert_eq!(tracks[1].coordinates(), vec![[40.7128, -74.0060]]);
+    }
+
+    #[test]
+    fn test_time_and_elevation_columns() {
+        let tracks = parse_csv(LOGGER_CSV.as_bytes(), &CsvOptions::default()).unwrap();
+        let a = &tracks[0].points;
+
+        assert_eq!(a[0].timestamp_ms, Some(1_711_929_598_000));
+        assert_eq!(a[0].elevation, Some(12.5));
+        // unix seconds are accepted as well
+        assert_eq!(a[2].timestamp_ms, Some(1_711_929_600_000));
+
+        // +02:00 offset converted to UTC
+        let b = &tracks[1].points[0];
+        assert_eq!(b.timestamp_ms, Some(1_711_951_200_000));
+        assert_eq!(b.elevation, None);
+    }
+
+    #[test]
+    fn test_semicolon_and_decimal_comma() {
+        let options = CsvOptions {
+            lat_column: Some("Breite".to_string()),
+            lon_column: Some("Länge".to_string()),
+            elevation_column: Some("Höhe".to_string()),
+            ..CsvOptions::default()
+        };
+        let tracks = parse_csv(SPREADSHEET_CSV.as_bytes(), &options).unwrap();
+
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].id, None);
+        assert_eq!(tracks[0].points[0].lat, 47.3769);
+        assert_eq!(tracks[0].points[0].lon, 8.5417);
+        assert_eq!(tracks[0].points[1].elevation, Some(410.0));
+    }
+
+    #[test]
+    fn test_missing_columns_are_errors() {
+        let result = parse_csv(b"x,y\n1,2\n", &CsvOptions::default());
+        assert!(result.is_err());
+
+        let options = CsvOptions {
+            time_column: Some("recorded".to_string()),
+            ..CsvOptions::default()
+        };
+        let result = parse_csv(LOGGER_CSV.as_bytes(), &options);
+        assert!(result.unwrap_err().contains("recorded"));
+    }
+
+    #[test]
+    fn test_quoted_fields() {
+        let csv = "name,lat,lon\n\"Loop, \"\"north\"\"\",37.7749,-122.4194\n";
+        let options = CsvOptions {
+            track_id_column: Some("name".to_string()),
+            ..CsvOptions::default()
+        };
+        let tracks = parse_csv(csv.as_bytes(), &options).unwrap();
+
+        assert_eq!(tracks[0].id.as_deref(), Some("Loop, \"north\""));
+    }
+
+    #[test]
+    fn test_write_csv_round_trip() {
+        let tracks = parse_csv(LOGGER_CSV.as_bytes(), &CsvOptions::default()).unwrap();
+        let written = write_csv(&tracks, &CsvOptions::default());
+
+        assert!(written.starts_with("track_id,lat,lon,ele,time\n"));
+        assert!(written.contains("a,37.774900,-122.419400,12.5,2024-03-31T23:59:58Z"));
+
+        let reparsed = parse_csv(written.as_bytes(), &CsvOptions::default()).unwrap();
+        assert_eq!(reparsed, tracks);
+    }
+
+    #[test]
+    fn test_write_csv_decimal_comma() {
+        let tracks = parse_csv(SPREADSHEET_CSV.as_bytes(), &CsvOptions::default());
+        assert!(tracks.is_err(), "german headers aren't auto-detected");
+
+        let options = CsvOptions {
+            delimiter: Some(';'),
+            decimal_comma: Some(true),
+            ..CsvOptions::default()
+        };
+        let track = CsvTrack {
+            id: None,
+            points: vec![TrackPoint {
+                lat: 47.3769,
+                lon: 8.5417,
+                elevation: None,
+                timestamp_ms: Some(1_711_929_598_500),
+            }],
+        };
+        let written = write_csv(&[track], &options);
+
+        assert!(written.contains("1;47,376900;8,541700;;2024-03-31T23:59:58.500Z"));
+    }
+
+    #[test]
+    fn test_export_to_csv_rust() {
+        let tracks = vec![
+            vec![[37.7749, -122.4194], [37.7849, -122.4094]],
+            vec![[40.7128, -74.0060]],
+        ];
+        let csv = export_to_csv_rust(&tracks);
+
+        assert_eq!(csv.lines().count(), 4);
+        assert!(csv.contains("2,40.712800,-74.006000,,"));
+    }
+}
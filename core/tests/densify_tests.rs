@@ -0,0 +1,132 @@
+#[cfg(test)]
+mod densify_tests {
+    use fastgeotoolkit::*;
+
+    fn cells_of(start: [f64; 2], end: [f64; 2], tolerance: f64) -> Vec<((i32, i32), f64)> {
+        let mut cells = Vec::new();
+        walk_cells(start, end, tolerance, |cell, entered| {
+            cells.push((cell, entered))
+        });
+        cells
+    }
+
+    #[test]
+    fn test_walk_cells_is_connected_and_ordered() {
+        let start = [51.50012, -0.10034];
+        let end = [51.50788, -0.09411];
+        let cells = cells_of(start, end, 0.001);
+
+        assert_eq!(cells.first().unwrap(), &(grid_position(start, 0.001), 0.0));
+        assert_eq!(cells.last().unwrap().0, grid_position(end, 0.001));
+        // 8 lat steps and 6 lon steps
+        assert_eq!(cells.len(), 15);
+
+        for pair in cells.windows(2) {
+            let ((a, t0), (b, t1)) = (pair[0], pair[1]);
+            assert_eq!((b.0 - a.0).abs() + (b.1 - a.1).abs(), 1);
+            assert!(t0 <= t1 && t1 <= 1.0);
+        }
+
+        assert_eq!(cells_of(start, start, 0.001).len(), 1);
+        let reversed: Vec<_> = cells_of(end, start, 0.001).iter().map(|c| c.0).collect();
+        assert_eq!(reversed.len(), cells.len());
+    }
+
+    #[test]
+    fn test_sampling_does_not_change_counts() {
+        // the same 1 km street, recorded with 2 points and with 21
+        let sparse = vec![[51.5000, -0.1000], [51.5100, -0.1000]];
+        let dense: Vec<[f64; 2]> = (0..=20)
+            .map(|i| [51.5000 + i as f64 * 0.0005, -0.1000])
+            .collect();
+
+        let mut builder = HeatmapBuilder::new();
+        builder.add_track(sparse.clone());
+        builder.add_track(dense.clone());
+        assert_eq!(builder.segment_count(), 10);
+        assert_eq!(builder.build().max_frequency, 2);
+
+        let result = builder.build_segments();
+        assert_eq!(result.max_frequency, 2);
+        assert_eq!(result.segments.len(), 1);
+        assert_eq!(result.segments[0].coordinates.first(), sparse.first());
+        assert_eq!(result.segments[0].coordinates.last(), sparse.last());
+
+        // snapping only the ends never matches the long segment against the short ones
+        let mut ends_only = HeatmapBuilder::with_options(HeatmapOptions {
+            densify_segments: false,
+            ..HeatmapOptions::default()
+        });
+        ends_only.add_tracks(vec![sparse, dense]);
+        assert_eq!(ends_only.build().max_frequency, 1);
+    }
+
+    #[test]
+    fn test_segment_pieces_follow_the_track() {
+        let track = vec![[51.5000, -0.1000], [51.5032, -0.0975], [51.5040, -0.0940]];
+        let mut builder = HeatmapBuilder::new();
+        builder.add_track(track.clone());
+        builder.add_track(vec![[51.5030, -0.0980], [51.5030, -0.0900]]);
+
+        let result = builder.build_segments();
+        assert!(result.segments.len() > 1);
+        assert_eq!(result.segments[0].coordinates[0], track[0]);
+        assert_eq!(result.segments.iter().map(|s| s.frequency).max(), Some(2));
+
+        // the first track's pieces join up end to end
+        let pieces: Vec<_> = result
+            .segments
+            .iter()
+            .take_while(|s| s.coordinates.last() != Some(&track[2]))
+            .collect();
+        for pair in pieces.windows(2) {
+            assert_eq!(pair[0].coordinates.last(), pair[1].coordinates.first());
+        }
+    }
+
+    #[test]
+    fn test_merge_with_different_densify_setting() {
+        let track = vec![[51.5000, -0.1000], [51.5100, -0.1000]];
+        let mut builder = HeatmapBuilder::new();
+        builder.add_track(track.clone());
+
+        let mut other = HeatmapBuilder::with_options(HeatmapOptions {
+            densify_segments: false,
+            ..HeatmapOptions::default()
+        });
+        other.add_track(track);
+        builder.merge(other);
+
+        assert_eq!(builder.segment_count(), 10);
+        assert_eq!(builder.build().max_frequency, 2);
+    }
+
+    #[test]
+    fn test_step_over_the_antimeridian_walks_the_short_way() {
+        let mut builder = HeatmapBuilder::new();
+        builder.add_track(vec![[10.0, 179.9986], [10.0, -179.9986]]);
+
+        // one step up to the meridian on either side, not 360 degrees of cells
+        assert_eq!(builder.segment_count(), 2);
+        let result = builder.build_segments();
+        for segment in &result.segments {
+            for coord in &segment.coordinates {
+                assert!(coord[1].abs() > 179.99, "{coord:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_long_segment_at_fine_tolerance_counts_its_end_cells() {
+        let start = std::time::Instant::now();
+        let mut builder = HeatmapBuilder::with_options(HeatmapOptions {
+            grid_tolerance: MIN_GRID_TOLERANCE,
+            ..HeatmapOptions::default()
+        });
+        builder.add_track(vec![[51.0, 0.0], [51.5, 0.5]]);
+
+        assert_eq!(builder.segment_count(), 1);
+        assert_eq!(builder.build_segments().segments.len(), 1);
+        assert!(start.elapsed().as_secs() < 5);
+    }
+}
@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod directional_tests {
+    use fastgeotoolkit::*;
+
+    fn directional() -> HeatmapOptions {
+        HeatmapOptions {
+            directional: true,
+            ..HeatmapOptions::default()
+        }
+    }
+
+    // a 1 km street ridden north three times and south once, the southbound ride comes first
+    fn commutes() -> Vec<Vec<[f64; 2]>> {
+        let north = vec![[51.5000, -0.1000], [51.5050, -0.1000], [51.5100, -0.1000]];
+        let south: Vec<_> = north.iter().rev().copied().collect();
+        vec![south, north.clone(), north.clone(), north]
+    }
+
+    #[test]
+    fn test_directions_are_counted_separately() {
+        let mut builder = HeatmapBuilder::with_options(directional());
+        builder.add_tracks(commutes());
+
+        let frequencies: Vec<u32> = builder.build().tracks.iter().map(|t| t.frequency).collect();
+        assert_eq!(frequencies, vec![1, 3, 3, 3]);
+
+        let undirected = create_heatmap_from_tracks(commutes());
+        assert!(undirected.tracks.iter().all(|t| t.frequency == 4));
+    }
+
+    #[test]
+    fn test_segment_direction_counts_and_bearing() {
+        let result = process_tracks_by_segment_with_options(commutes(), &directional());
+
+        assert_eq!(result.segments.len(), 1);
+        let segment = &result.segments[0];
+        assert_eq!(segment.coordinates[0], [51.5100, -0.1000]);
+        assert_eq!(segment.frequency, 4);
+        assert_eq!(result.max_frequency, 4);
+
+        // drawn southbound by the first ride, but most traffic heads north
+        let direction = segment.direction.as_ref().unwrap();
+        assert_eq!((direction.forward, direction.backward), (1, 3));
+        assert!(direction.bearing < 0.01 || direction.bearing > 359.99);
+    }
+
+    #[test]
+    fn test_one_way_bearing() {
+        let east = vec![[0.0, 10.0], [0.0, 10.01], [0.0, 10.02]];
+        let result = process_tracks_by_segment_with_options(vec![east], &directional());
+
+        let direction = result.segments[0].direction.as_ref().unwrap();
+        assert_eq!((direction.forward, direction.backward), (1, 0));
+        assert!((direction.bearing - 90.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_undirected_output_has_no_direction() {
+        let result = create_segment_heatmap_from_tracks(commutes());
+        assert!(result.segments.iter().all(|s| s.direction.is_none()));
+
+        let json = serde_json::to_string(&result).unwrap();
+        assert!(!json.contains("direction"));
+        let parsed: SegmentHeatmapResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, result);
+    }
+
+    #[test]
+    fn test_merge_recounts_other_mode() {
+        let mut builder = HeatmapBuilder::with_options(directional());
+        builder.add_tracks(commutes().into_iter().take(2));
+
+        let mut other = HeatmapBuilder::new();
+        other.add_tracks(commutes().into_iter().skip(2));
+        builder.merge(other);
+
+        let mut single = HeatmapBuilder::with_options(directional());
+        single.add_tracks(commutes());
+        assert_eq!(builder, single);
+    }
+
+    #[test]
+    fn test_reverse_segment() {
+        let a = (51_500, -100);
+        let b = (51_501, -100);
+
+        assert_eq!(
+            reverse_segment(directed_segment_id(a, b)),
+            directed_segment_id(b, a)
+        );
+        assert_eq!(cell_segment_id(b, a), directed_segment_id(a, b));
+    }
+}
@@ -0,0 +1,201 @@
+#[cfg(test)]
+mod export_archive_tests {
+    use fastgeotoolkit::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    const GPX_FILE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="fastgeotoolkit tests" xmlns="http://www.topografix.com/GPX/1/1">
+  <trk><trkseg>
+    <trkpt lat="37.7749" lon="-122.4194"><time>2024-03-31T08:00:00Z</time></trkpt>
+    <trkpt lat="37.7849" lon="-122.4094"><time>2024-03-31T08:00:10Z</time></trkpt>
+  </trkseg></trk>
+</gpx>"#;
+
+    const IGC_FILE: &str = "\
+AXCS001
+HFDTE310324
+B1000004708000N01130900EA0054400589
+B1000014708038N01131000EA0054500591
+";
+
+    const ACTIVITIES_CSV: &str = "\
+Activity ID,Activity Date,Activity Name,Activity Type,Activity Description,Elapsed Time,Distance,Filename,Elapsed Time,Distance
+101,\"Mar 31, 2024, 8:00:00 AM\",Morning Ride,Ride,\"Coffee stop,
+then home\",3600,20.5,activities/101.gpx.gz,3600,20500
+102,\"Mar 31, 2024, 10:00:00 PM\",Evening Flight,Hang Glide,,60,1.2,activities/102.igc,60,1200
+103,\"Apr 1, 2024, 12:15:00 PM\",Treadmill,Run,,1800,5.0,,1800,5000
+104,\"Apr 2, 2024, 7:00:00 AM\",Lost Run,Run,,1800,5.0,activities/104.fit.gz,1800,5000
+";
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in entries {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_strava_export_with_manifest() {
+        let gpx = gzip(GPX_FILE.as_bytes());
+        let archive = zip(&[
+            ("export_42/activities.csv", ACTIVITIES_CSV.as_bytes()),
+            ("export_42/activities/101.gpx.gz", &gpx),
+            ("export_42/activities/102.igc", IGC_FILE.as_bytes()),
+            ("export_42/profile.csv", b"Athlete ID,Email\n1,a@b.c\n"),
+        ]);
+        assert!(is_zip_file(&archive));
+
+        let export = parse_export_archive(&archive).unwrap();
+        assert_eq!(export.activities.len(), 3, "manual activity has no file");
+        assert_eq!(export.warnings, vec!["1 activities have no recorded file"]);
+
+        let ride = &export.activities[0];
+        assert_eq!(ride.id.as_deref(), Some("101"));
+        assert_eq!(ride.name.as_deref(), Some("Morning Ride"));
+        assert_eq!(ride.activity_type.as_deref(), Some("Ride"));
+        assert_eq!(ride.file, "export_42/activities/101.gpx.gz");
+        assert_eq!(ride.format, Some(FileFormat::Gpx));
+        assert_eq!(ride.start_time_ms, Some(1_711_872_000_000));
+        assert_eq!(ride.tracks[0].len(), 2);
+
+        let flight = &export.activities[1];
+        assert_eq!(flight.format, Some(FileFormat::Igc));
+        assert_eq!(flight.start_time_ms, Some(1_711_922_400_000));
+
+        let missing = &export.activities[2];
+        assert!(missing.tracks.is_empty());
+        assert_eq!(
+            missing.warnings,
+            vec!["activities/104.fit.gz was not found in the archive"]
+        );
+
+        let tracks = export.heatmap_tracks();
+        assert_eq!(tracks.len(), 2);
+        let heatmap = create_heatmap_from_tracks(tracks);
+        assert_eq!(heatmap.tracks.len(), 2);
+    }
+
+    #[test]
+    fn test_garmin_export_without_manifest() {
+        let uploads = zip(&[
+            ("ride.gpx", GPX_FILE.as_bytes()),
+            ("flight.igc", IGC_FILE.as_bytes()),
+        ]);
+        let archive = zip(&[
+            (
+                "DI_CONNECT/DI-Connect-Fitness/summarizedActivities.json",
+                b"[{\"activityId\": 1}]",
+            ),
+            (
+                "DI_CONNECT/DI-Connect-Uploaded-Files/UploadedFiles_0-_Part1.zip",
+                &uploads,
+            ),
+        ]);
+
+        let export = parse_export_archive(&archive).unwrap();
+        let names: Vec<_> = export
+            .activities
+            .iter()
+            .map(|activity| activity.name.as_deref().unwrap())
+            .collect();
+
+        assert_eq!(names, vec!["flight", "ride"]);
+        assert!(export.activities[1].file.ends_with("Part1.zip/ride.gpx"));
+        assert_eq!(export.heatmap_tracks().len(), 2);
+    }
+
+    #[test]
+    fn test_only_track_files_are_read() {
+        let manifest = "Activity ID,Activity Name,Filename\n105,Odd Upload,activities/105.dat\n";
+        let photo = vec![0u8; 4096];
+        let archive = zip(&[
+            ("activities.csv", manifest.as_bytes()),
+            ("activities/105.dat", GPX_FILE.as_bytes()),
+            ("media/ride.jpg", &photo),
+        ]);
+        let limits = ArchiveLimits {
+            max_file_size: 2048,
+            max_total_size: 4096,
+        };
+
+        // the photo is bigger than any file may be, but it is never decompressed
+        let export = parse_export_archive_with_limits(&archive, limits).unwrap();
+        assert_eq!(export.activities.len(), 1);
+        assert_eq!(export.activities[0].format, Some(FileFormat::Gpx));
+        assert!(export.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_size_limits_fail_instead_of_truncating() {
+        let big_gpx = format!("{GPX_FILE}<!--{}-->", " ".repeat(4096));
+        let archive = zip(&[("ride.gpx", big_gpx.as_bytes())]);
+        let limits = ArchiveLimits {
+            max_file_size: 2048,
+            max_total_size: 1 << 20,
+        };
+        assert!(matches!(
+            parse_export_archive_with_limits(&archive, limits),
+            Err(Error::Archive(_))
+        ));
+
+        // many small files in nested zips add up past the total
+        let inner = zip(&[
+            ("a.gpx", GPX_FILE.as_bytes()),
+            ("b.gpx", GPX_FILE.as_bytes()),
+            ("c.gpx", GPX_FILE.as_bytes()),
+        ]);
+        let archive = zip(&[("part1.zip", &inner), ("part2.zip", &inner)]);
+        let limits = ArchiveLimits {
+            max_file_size: 1 << 20,
+            max_total_size: 4 * GPX_FILE.len() as u64,
+        };
+        assert!(matches!(
+            parse_export_archive_with_limits(&archive, limits),
+            Err(Error::Archive(_))
+        ));
+        assert_eq!(parse_export_archive(&archive).unwrap().activities.len(), 6);
+    }
+
+    #[test]
+    fn test_invalid_archive() {
+        assert!(!is_zip_file(GPX_FILE.as_bytes()));
+        assert!(matches!(
+            parse_export_archive(GPX_FILE.as_bytes()),
+            Err(Error::Archive(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_export_date() {
+        assert_eq!(
+            parse_export_date("Mar 31, 2024, 8:00:00 AM"),
+            Some(1_711_872_000_000)
+        );
+        assert_eq!(
+            parse_export_date("Mar 31, 2024, 12:30:00 AM"),
+            Some(1_711_845_000_000)
+        );
+        assert_eq!(
+            parse_export_date("Mar 31, 2024, 12:30:00 PM"),
+            Some(1_711_888_200_000)
+        );
+        assert_eq!(
+            parse_export_date("2024-03-31T08:00:00Z"),
+            Some(1_711_872_000_000)
+        );
+        assert_eq!(parse_export_date("Foo 31, 2024"), None);
+        assert_eq!(parse_export_date(""), None);
+    }
+}
@@ -0,0 +1,145 @@
+#[cfg(test)]
+mod geohash_tests {
+    use fastgeotoolkit::*;
+
+    #[test]
+    fn test_encode_known_hashes() {
+        assert_eq!(encode_geohash(57.64911, 10.40744, 11), "u4pruydqqvj");
+        assert_eq!(encode_geohash(42.6, -5.6, 5), "ezs42");
+        assert_eq!(encode_geohash(-25.382708, -49.265506, 7), "6gkzwgj");
+        assert_eq!(encode_geohash(51.5, -0.12, 0), "g");
+        assert_eq!(encode_geohash(51.5, -0.12, 20).len(), 12);
+    }
+
+    #[test]
+    fn test_decode_round_trip() {
+        let [lat, lon] = decode_geohash("ezs42").unwrap();
+        assert!((lat - 42.605).abs() < 0.01 && (lon - -5.603).abs() < 0.01);
+
+        let [min_lat, min_lon, max_lat, max_lon] = geohash_bounds("ezs42").unwrap();
+        assert!(min_lat <= 42.6 && 42.6 <= max_lat);
+        assert!(min_lon <= -5.6 && -5.6 <= max_lon);
+
+        for point in [[51.5, -0.12], [-33.9, 151.2], [0.0, 0.0], [89.9, 179.9]] {
+            let hash = encode_geohash(point[0], point[1], 9);
+            let bbox = geohash_bounds(&hash).unwrap();
+            assert!(bbox[0] <= point[0] && point[0] <= bbox[2]);
+            assert!(bbox[1] <= point[1] && point[1] <= bbox[3]);
+            let center = decode_geohash(&hash).unwrap();
+            assert_eq!(encode_geohash(center[0], center[1], 9), hash);
+        }
+
+        // case doesn't matter, letters outside the alphabet do
+        assert_eq!(decode_geohash("EZS42"), decode_geohash("ezs42"));
+        assert!(decode_geohash("ezs4a").is_none());
+        assert!(decode_geohash("").is_none());
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let neighbors = geohash_neighbors("ezs42").unwrap();
+        assert_eq!(
+            neighbors,
+            vec!["ezs48", "ezs49", "ezs43", "ezs41", "ezs40", "ezefp", "ezefr", "ezefx"]
+        );
+
+        // east of the last column wraps to the first
+        let edge = encode_geohash(10.0, 179.99, 4);
+        let neighbors = geohash_neighbors(&edge).unwrap();
+        let east = decode_geohash(&neighbors[2]).unwrap();
+        assert!(east[1] < -179.0);
+
+        // nothing north of the north pole
+        let pole = encode_geohash(89.99, 0.0, 3);
+        assert_eq!(geohash_neighbors(&pole).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_bbox_cover() {
+        let bbox = [51.45, -0.2, 51.55, 0.0];
+        let cover = geohash_cover(bbox, 5).unwrap();
+
+        // 5 characters are about 4.9 x 4.9 km cells, a few of them in each direction
+        assert!(cover.len() > 4 && cover.len() < 40);
+        for hash in &cover {
+            let cell = geohash_bounds(hash).unwrap();
+            assert!(cell[0] <= bbox[2] && cell[2] >= bbox[0]);
+            assert!(cell[1] <= bbox[3] && cell[3] >= bbox[1]);
+        }
+
+        // every point of the bbox is in one of the cells
+        for lat in [51.45, 51.5, 51.55] {
+            for lon in [-0.2, -0.1, 0.0] {
+                assert!(cover.contains(&encode_geohash(lat, lon, 5)));
+            }
+        }
+        assert_eq!(geohash_cover([51.5, -0.1, 51.5, -0.1], 7).unwrap().len(), 1);
+        assert!(geohash_cover([52.0, 0.0, 51.0, 1.0], 5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_bbox_cover_is_capped() {
+        // the whole world at 12 characters is about 10^18 cells
+        assert_eq!(geohash_cover([-90.0, -180.0, 90.0, 180.0], 12), None);
+
+        let bbox = [51.45, -0.2, 51.55, 0.0];
+        let cells = geohash_cover(bbox, 5).unwrap().len();
+        assert_eq!(
+            geohash_cover_with_limit(bbox, 5, cells).unwrap().len(),
+            cells
+        );
+        assert_eq!(geohash_cover_with_limit(bbox, 5, cells - 1), None);
+
+        // both sides of the antimeridian count
+        let crossing = [-17.5, 179.5, -17.0, -179.5];
+        let cells = geohash_cover(crossing, 4).unwrap().len();
+        assert_eq!(geohash_cover_with_limit(crossing, 4, cells - 1), None);
+    }
+
+    #[test]
+    fn test_tracks_aggregate_into_cells() {
+        // about 3 km east to west through central London, twice in one activity and once more
+        let street = vec![[51.5074, -0.1500], [51.5074, -0.1050]];
+        let mut builder = HeatmapBuilder::new();
+        builder.add_activity(vec![street.clone(), street.clone()]);
+        builder.add_track(street.clone());
+        let result = builder.build_geohash(6);
+
+        assert_eq!(result.precision, 6);
+        assert!(result.cells.len() >= 3);
+        for cell in &result.cells {
+            assert_eq!((cell.passes, cell.activities, cell.frequency), (3, 2, 3));
+            assert_eq!(decode_geohash(&cell.geohash), Some(cell.center));
+        }
+        assert_eq!(result.max_frequency, 3);
+
+        let sorted = result.cells.windows(2).all(|w| w[0].geohash < w[1].geohash);
+        assert!(sorted);
+        assert_eq!(
+            create_geohash_heatmap_from_tracks(vec![street.clone(); 3], 6).max_frequency,
+            3
+        );
+    }
+
+    #[test]
+    fn test_aggregation_follows_heatmap_options() {
+        let street = vec![[51.5074, -0.1500], [51.5074, -0.1050]];
+        let options = HeatmapOptions {
+            frequency_metric: FrequencyMetric::Activities,
+            ..HeatmapOptions::default()
+        };
+        let mut builder = HeatmapBuilder::with_options(options.clone());
+        builder.add_activity(vec![street.clone(), street.clone()]);
+        builder.add_weighted_activity(vec![street.clone()], 3.0, None);
+        let result = builder.build_geohash(5);
+
+        for cell in &result.cells {
+            assert_eq!(cell.activities, 2);
+            assert_eq!(cell.weight, 4.0);
+            assert_eq!(cell.frequency, 4);
+        }
+
+        let processed = process_tracks_by_geohash_with_options(vec![street; 2], 5, &options);
+        assert!(processed.cells.iter().all(|c| c.frequency == 2));
+    }
+}
@@ -0,0 +1,151 @@
+#[cfg(test)]
+mod grid_tests {
+    use fastgeotoolkit::*;
+    use std::collections::HashMap;
+
+    // deterministic random walks so the comparison covers plenty of grid cells
+    fn random_tracks(count: usize, points: usize) -> Vec<Vec<[f64; 2]>> {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % 10_000) as f64 / 10_000.0 - 0.5
+        };
+
+        (0..count)
+            .map(|_| {
+                let mut point = [40.7 + next() * 0.02, -74.0 + next() * 0.02];
+                (0..points)
+                    .map(|_| {
+                        point = [point[0] + next() * 0.002, point[1] + next() * 0.002];
+                        point
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // the string keyed implementation the integer ids replaced
+    fn string_keyed_frequencies(tracks: &[Vec<[f64; 2]>]) -> Vec<u32> {
+        let mut usage: HashMap<String, u32> = HashMap::new();
+        for track in tracks {
+            for window in track.windows(2) {
+                *usage
+                    .entry(create_segment_key(window[0], window[1]))
+                    .or_insert(0) += 1;
+            }
+        }
+
+        tracks
+            .iter()
+            .filter(|track| track.len() >= 2)
+            .map(|track| {
+                let total: u32 = track
+                    .windows(2)
+                    .map(|window| usage[&create_segment_key(window[0], window[1])])
+                    .sum();
+                (total as f64 / (track.len() - 1) as f64).round() as u32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cell_packing_round_trips() {
+        for (lat, lon) in [(0, 0), (-90_000, 180_000), (90_000, -180_000), (-1, -1)] {
+            assert_eq!(unpack_cell(pack_cell(lat, lon)), (lat, lon));
+        }
+
+        let cell = grid_cell([40.71284, -74.00597], 0.001);
+        assert_eq!(unpack_cell(cell), (40_713, -74_006));
+        assert_eq!(
+            cell_coordinate(cell, 0.001),
+            snap_to_grid([40.71284, -74.00597], 0.001)
+        );
+    }
+
+    #[test]
+    fn test_segment_id_is_direction_independent() {
+        let a = [40.7128, -74.0060];
+        let b = [40.7200, -74.0040];
+
+        assert_eq!(segment_id(a, b, 0.001), segment_id(b, a, 0.001));
+        assert_ne!(segment_id(a, b, 0.001), segment_id(a, b, 0.0001));
+
+        let (first, second) = segment_cells(segment_id(b, a, 0.001));
+        assert_eq!(first, grid_cell(a, 0.001));
+        assert_eq!(second, grid_cell(b, 0.001));
+    }
+
+    #[test]
+    fn test_segment_ids_match_string_keys() {
+        let tracks = random_tracks(50, 40);
+        let segments: Vec<([f64; 2], [f64; 2])> = tracks
+            .iter()
+            .flat_map(|track| track.windows(2).map(|w| (w[0], w[1])))
+            .collect();
+
+        // two segments share an id exactly when they used to share a string key
+        for (i, &(a1, b1)) in segments.iter().enumerate().step_by(13) {
+            for &(a2, b2) in &segments[i..] {
+                assert_eq!(
+                    segment_id(a1, b1, 0.001) == segment_id(a2, b2, 0.001),
+                    create_segment_key(a1, b1) == create_segment_key(a2, b2)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_heatmap_output_unchanged() {
+        let tracks = random_tracks(200, 60);
+        let expected = string_keyed_frequencies(&tracks);
+
+        // the string keys only ever snapped segment ends
+        let mut builder = HeatmapBuilder::with_options(HeatmapOptions {
+            densify_segments: false,
+            ..HeatmapOptions::default()
+        });
+        builder.add_tracks(tracks);
+        let result = builder.build();
+
+        let frequencies: Vec<u32> = result.tracks.iter().map(|t| t.frequency).collect();
+        assert_eq!(frequencies, expected);
+        assert_eq!(result.max_frequency, *expected.iter().max().unwrap());
+    }
+
+    #[test]
+    fn test_intersection_coordinates_unchanged() {
+        let tracks = random_tracks(20, 30);
+        for tolerance in [0.001, 0.0005, 0.0001] {
+            let intersections = find_track_intersections_rust(&tracks, tolerance);
+            assert!(!intersections.is_empty());
+
+            for (coordinate, _) in intersections {
+                let cell = grid_cell(coordinate, tolerance);
+                let [lat, lon] = snap_to_grid(coordinate, tolerance);
+                let reparsed = [
+                    format!("{lat:.4}").parse::<f64>().unwrap(),
+                    format!("{lon:.4}").parse::<f64>().unwrap(),
+                ];
+                assert_eq!(coordinate, reparsed);
+                assert_eq!(grid_cell(reparsed, tolerance), cell);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fine_intersection_tolerance_groups_by_reported_coordinate() {
+        // 3 m apart, different 1 m cells but the same coordinate at 4 decimals
+        let tracks = vec![
+            vec![[51.50001, -0.10001], [51.6, -0.2]],
+            vec![[51.50003, -0.10003], [51.4, 0.0]],
+        ];
+        let intersections = find_track_intersections_rust(&tracks, 0.00001);
+        assert_eq!(intersections, vec![([51.5, -0.1], vec![0, 1])]);
+
+        assert!(find_track_intersections_rust(&tracks, 0.001)
+            .iter()
+            .any(|(coordinate, _)| *coordinate == [51.5, -0.1]));
+    }
+}
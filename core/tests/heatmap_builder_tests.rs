@@ -0,0 +1,128 @@
+#[cfg(test)]
+mod heatmap_builder_tests {
+    use fastgeotoolkit::*;
+
+    fn sample_tracks() -> Vec<Vec<[f64; 2]>> {
+        vec![
+            vec![
+                [37.7749, -122.4194],
+                [37.7849, -122.4094],
+                [37.7949, -122.3994],
+            ],
+            vec![[37.7749, -122.4194], [37.7849, -122.4094]],
+            vec![[37.7849, -122.4094], [37.7749, -122.4194]], // reversed
+            vec![[40.7128, -74.0060], [40.7228, -74.0160]],
+            vec![[51.5074, -0.1278]],
+        ]
+    }
+
+    #[test]
+    fn test_builder_matches_batch() {
+        let batch = create_heatmap_from_tracks(sample_tracks());
+
+        let mut builder = HeatmapBuilder::new();
+        for track in sample_tracks() {
+            builder.add_track(track);
+        }
+
+        assert_eq!(builder.track_count(), 4, "single point track is ignored");
+        assert_eq!(builder.build(), batch);
+        assert_eq!(batch.tracks[1].frequency, 3);
+        assert_eq!(batch.max_frequency, 3);
+    }
+
+    #[test]
+    fn test_build_is_repeatable_while_adding() {
+        let mut builder = HeatmapBuilder::new();
+        builder.add_tracks(sample_tracks().into_iter().take(2));
+        let first = builder.build();
+        assert_eq!(first.tracks.len(), 2);
+        assert_eq!(first.tracks[1].frequency, 2);
+
+        builder.add_tracks(sample_tracks().into_iter().skip(2));
+        assert_eq!(builder.build(), create_heatmap_from_tracks(sample_tracks()));
+    }
+
+    #[test]
+    fn test_merge_shards() {
+        let tracks = sample_tracks();
+        let mut left = HeatmapBuilder::new();
+        let mut right = HeatmapBuilder::new();
+        left.add_tracks(tracks[..2].to_vec());
+        right.add_tracks(tracks[2..].to_vec());
+
+        left.merge(right);
+
+        let mut single = HeatmapBuilder::new();
+        single.add_tracks(tracks);
+        assert_eq!(left, single);
+        // three 1.4 km diagonals, each crossing 20 grid cells
+        assert_eq!(left.segment_count(), 60);
+    }
+
+    #[test]
+    fn test_builder_round_trips_through_json() {
+        let mut builder = HeatmapBuilder::new();
+        builder.add_tracks(sample_tracks());
+
+        let json = serde_json::to_string(&builder).unwrap();
+        let mut restored: HeatmapBuilder = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, builder);
+
+        restored.add_track(vec![[40.7128, -74.0060], [40.7228, -74.0160]]);
+        let result = restored.build();
+        assert_eq!(result.tracks.len(), 5);
+        assert_eq!(result.tracks[3].frequency, 2);
+    }
+
+    #[test]
+    fn test_empty_builder() {
+        let builder = HeatmapBuilder::new();
+        let result = builder.build();
+
+        assert!(builder.is_empty());
+        assert!(result.tracks.is_empty());
+        assert_eq!(result.max_frequency, 1);
+    }
+
+    #[test]
+    fn test_segment_output_keeps_per_segment_counts() {
+        // both tracks share the middle segment, the second one then takes a detour
+        let main = vec![[45.0, 7.0], [45.01, 7.0], [45.02, 7.0], [45.03, 7.0]];
+        let detour = vec![[45.01, 7.0], [45.02, 7.0], [45.02, 7.01], [45.02, 7.02]];
+        let result = create_segment_heatmap_from_tracks(vec![main, detour.clone()]);
+
+        let runs: Vec<_> = result
+            .segments
+            .iter()
+            .map(|segment| (segment.coordinates.clone(), segment.frequency))
+            .collect();
+        assert_eq!(
+            runs,
+            vec![
+                (vec![[45.0, 7.0], [45.01, 7.0]], 1),
+                (vec![[45.01, 7.0], [45.02, 7.0]], 2),
+                (vec![[45.02, 7.0], [45.03, 7.0]], 1),
+                (vec![[45.02, 7.0], [45.02, 7.01], [45.02, 7.02]], 1),
+            ]
+        );
+        assert_eq!(result.max_frequency, 2);
+
+        // the averaged output flattens the shared segment away
+        let averaged = create_heatmap_from_tracks(vec![detour]);
+        assert_eq!(averaged.tracks[0].frequency, 1);
+    }
+
+    #[test]
+    fn test_segment_output_from_builder() {
+        let mut builder = HeatmapBuilder::new();
+        builder.add_tracks(sample_tracks());
+        let result = builder.build_segments();
+
+        // the second and third tracks only repeat segments of the first one
+        assert_eq!(result.segments.len(), 3);
+        assert_eq!(result.segments[0].frequency, 3);
+        assert_eq!(result.max_frequency, 3);
+        assert!(HeatmapBuilder::new().build_segments().segments.is_empty());
+    }
+}
@@ -0,0 +1,150 @@
+#[cfg(test)]
+mod heatmap_options_tests {
+    use fastgeotoolkit::*;
+
+    // two walks along parallel streets about 30 meters apart
+    fn parallel_walks() -> Vec<Vec<[f64; 2]>> {
+        vec![
+            vec![[51.5000, -0.1000], [51.5010, -0.1000], [51.5020, -0.1000]],
+            vec![[51.5000, -0.1003], [51.5010, -0.1003], [51.5020, -0.1003]],
+        ]
+    }
+
+    #[test]
+    fn test_default_options() {
+        let options = HeatmapOptions::default();
+
+        assert_eq!(options.grid_tolerance, 0.001);
+        assert_eq!(options.simplify_tolerance, 0.00005);
+        assert_eq!(options.coordinate_precision, 5);
+        assert_eq!(options.max_jump_km, 100.0);
+        assert!(options.densify_segments);
+        assert_eq!(
+            process_tracks_with_options(parallel_walks(), &options),
+            create_heatmap_from_tracks(parallel_walks())
+        );
+    }
+
+    #[test]
+    fn test_grid_tolerance_controls_matching() {
+        let coarse = process_tracks_with_options(parallel_walks(), &HeatmapOptions::default());
+        assert_eq!(coarse.max_frequency, 2, "100 m grid merges both streets");
+
+        let fine = HeatmapOptions {
+            grid_tolerance: 0.0001,
+            ..HeatmapOptions::default()
+        };
+        let result = process_tracks_with_options(parallel_walks(), &fine);
+        assert_eq!(result.max_frequency, 1);
+
+        assert_eq!(
+            create_segment_key([51.5, -0.1], [51.501, -0.1]),
+            "51.5000,-0.1000-51.5010,-0.1000"
+        );
+        assert_eq!(
+            segment_key([51.5, -0.1], [51.501, -0.1], 0.00001),
+            "51.500000,-0.100000-51.501000,-0.100000"
+        );
+    }
+
+    #[test]
+    fn test_track_preparation_options() {
+        let track = vec![
+            [51.500001, -0.100001],
+            [51.500002, -0.100002],
+            [51.5010, -0.1000],
+            [48.8566, 2.3522], // 340 km away
+            [51.5020, -0.1000],
+        ];
+
+        let default = process_tracks_with_options(vec![track.clone()], &HeatmapOptions::default());
+        assert_eq!(
+            default.tracks[0].coordinates,
+            vec![[51.5, -0.1], [51.501, -0.1], [51.502, -0.1]]
+        );
+
+        let options = HeatmapOptions {
+            simplify_tolerance: 0.0,
+            coordinate_precision: 6,
+            max_jump_km: 500.0,
+            ..HeatmapOptions::default()
+        };
+        let result = process_tracks_with_options(vec![track.clone()], &options);
+        assert_eq!(result.tracks[0].coordinates, track);
+
+        let strict = HeatmapOptions {
+            max_jump_km: 0.00001, // 1 cm
+            ..HeatmapOptions::default()
+        };
+        assert!(process_tracks_with_options(vec![track], &strict)
+            .tracks
+            .is_empty());
+    }
+
+    #[test]
+    fn test_builder_rejects_bad_grid_tolerance() {
+        for tolerance in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let builder = HeatmapBuilder::with_options(HeatmapOptions {
+                grid_tolerance: tolerance,
+                ..HeatmapOptions::default()
+            });
+            assert_eq!(builder.options().grid_tolerance, 0.001);
+        }
+
+        let mut builder = HeatmapBuilder::with_options(HeatmapOptions {
+            grid_tolerance: 1e-12,
+            ..HeatmapOptions::default()
+        });
+        assert_eq!(builder.options().grid_tolerance, MIN_GRID_TOLERANCE);
+        builder.add_track(parallel_walks()[0].clone());
+        assert_eq!(builder.build().max_frequency, 1);
+    }
+
+    #[test]
+    fn test_partial_options_from_json() {
+        let options: HeatmapOptions =
+            serde_json::from_str(r#"{"grid_tolerance": 0.0002}"#).unwrap();
+
+        assert_eq!(options.grid_tolerance, 0.0002);
+        assert_eq!(options.max_jump_km, 100.0);
+    }
+
+    #[test]
+    fn test_builder_merge_with_different_grids() {
+        let fine = HeatmapOptions {
+            grid_tolerance: 0.0001,
+            ..HeatmapOptions::default()
+        };
+        let mut builder = HeatmapBuilder::with_options(fine.clone());
+        builder.add_track(parallel_walks()[0].clone());
+
+        let mut other = HeatmapBuilder::new();
+        other.add_track(parallel_walks()[1].clone());
+        builder.merge(other);
+
+        assert_eq!(builder.options(), &fine);
+        assert_eq!(builder.build().max_frequency, 1);
+        // two 200 m walks, 20 steps each on the 10 m grid
+        assert_eq!(builder.segment_count(), 40);
+    }
+
+    #[test]
+    fn test_polylines_with_options() {
+        let polylines = vec![
+            "[[51.5, -0.1], [51.501, -0.1], [51.502, -0.1]]".to_string(),
+            "[[51.5, -0.1003], [51.501, -0.1003], [51.502, -0.1003]]".to_string(),
+        ];
+
+        let coarse = process_polylines_with_options_rust(&polylines, &HeatmapOptions::default());
+        assert_eq!(coarse.max_frequency, 2);
+
+        let fine = HeatmapOptions {
+            grid_tolerance: 0.0001,
+            ..HeatmapOptions::default()
+        };
+        assert_eq!(
+            process_polylines_with_options_rust(&polylines, &fine).max_frequency,
+            1
+        );
+    }
+}
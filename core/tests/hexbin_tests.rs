@@ -0,0 +1,158 @@
+#[cfg(test)]
+mod hexbin_tests {
+    use fastgeotoolkit::*;
+
+    #[test]
+    fn test_cell_round_trip() {
+        let size = 300.0;
+        for point in [[51.5, -0.12], [-33.9, 151.2], [0.0, 0.0], [64.1, -21.9]] {
+            let id = hex_cell(point, size);
+            let center = hex_center(id, size);
+            assert_eq!(hex_cell(center, size), id);
+
+            // every corner is one circumradius from the center on the mercator plane, which is
+            // size * cos(lat) on the ground
+            let ground = size * center[0].to_radians().cos() / 1000.0;
+            let boundary = hex_boundary(id, size);
+            assert_eq!(boundary.len(), 7);
+            assert_eq!(boundary[0], boundary[6]);
+            for corner in &boundary[..6] {
+                let distance =
+                    calculate_distance_between_points(center[0], center[1], corner[0], corner[1]);
+                assert!((distance - ground).abs() < ground * 0.01);
+            }
+        }
+    }
+
+    #[test]
+    fn test_points_near_a_center_share_its_cell() {
+        let size = hex_size_for_latitude(200.0, 51.5);
+        let id = hex_cell([51.5, -0.12], size);
+        let center = hex_center(id, size);
+
+        // 150 m off the center in any direction is still inside the 200 m hexagon (its
+        // inradius is about 173 m)
+        for bearing in (0..360).step_by(30) {
+            let bearing = (bearing as f64).to_radians();
+            let point = [
+                center[0] + 150.0 * bearing.cos() / 111_320.0,
+                center[1] + 150.0 * bearing.sin() / (111_320.0 * center[0].to_radians().cos()),
+            ];
+            assert_eq!(hex_cell(point, size), id);
+        }
+    }
+
+    #[test]
+    fn test_neighbors_and_disks() {
+        let id = HexId { q: 10, r: -4 };
+        let neighbors = hex_neighbors(id);
+        assert!(neighbors.iter().all(|&n| hex_distance(id, n) == 1));
+
+        // neighbors share an edge, their centers are sqrt(3) * size apart
+        let size = 1000.0;
+        let center = hex_center(id, size);
+        for neighbor in neighbors {
+            let other = hex_center(neighbor, size);
+            let distance =
+                calculate_distance_between_points(center[0], center[1], other[0], other[1]);
+            let expected = 3f64.sqrt() * size * center[0].to_radians().cos() / 1000.0;
+            assert!((distance - expected).abs() < expected * 0.01);
+        }
+
+        assert_eq!(hex_disk(id, 0), vec![id]);
+        let disk = hex_disk(id, 2);
+        assert_eq!(disk.len(), 19);
+        assert!(disk.iter().all(|&cell| hex_distance(id, cell) <= 2));
+        assert!(neighbors.iter().all(|n| disk.contains(n)));
+    }
+
+    #[test]
+    fn test_points_and_length_are_binned() {
+        let options = HexbinOptions { size_m: 500.0 };
+        let ride = vec![[51.50, -0.15], [51.50, -0.10], [51.50, -0.05]];
+        let result = hexbin_tracks(&[ride.clone(), ride.clone()], &options);
+
+        let points: u32 = result.cells.iter().map(|c| c.points).sum();
+        assert_eq!(points, 6);
+        assert_eq!(result.max_points, 2);
+
+        // all of the distance ends up in some cell
+        let total_km: f64 = result.cells.iter().map(|c| c.length_m).sum::<f64>() / 1000.0;
+        let ride_km = 2.0 * calculate_distance_between_points(51.50, -0.15, 51.50, -0.05);
+        assert!((total_km - ride_km).abs() < 1e-6);
+
+        // both rides pass through every cell, each cell counts them once
+        assert!(result.cells.iter().all(|c| c.tracks == 2));
+        assert!(result.cells.len() > 10);
+        let mut ids: Vec<HexId> = result.cells.iter().map(|c| c.id).collect();
+        ids.dedup();
+        assert_eq!(ids.len(), result.cells.len());
+
+        for point in ride {
+            let cell = result.cells.iter().find(|c| c.id == hex_cell(point, 500.0));
+            assert_eq!(cell.unwrap().points, 2);
+        }
+    }
+
+    #[test]
+    fn test_degenerate_sizes_and_the_antimeridian() {
+        // used to hang on a zero size
+        let result = hexbin_tracks(
+            &[vec![[51.50, -0.12], [51.50, -0.11]]],
+            &HexbinOptions { size_m: 0.0 },
+        );
+        assert_eq!(result.size_m, MIN_HEX_SIZE_M);
+        assert!(!result.cells.is_empty());
+
+        // 20 km over ±180 stays near it instead of crossing the whole world
+        let options = HexbinOptions { size_m: 5000.0 };
+        let result = hexbin_tracks(&[vec![[-17.5, 179.9], [-17.5, -179.9]]], &options);
+        assert!(result.cells.len() < 10, "{}", result.cells.len());
+        assert!(result.cells.iter().all(|c| c.center[1].abs() > 179.0));
+        let total_km: f64 = result.cells.iter().map(|c| c.length_m).sum::<f64>() / 1000.0;
+        let crossing_km = calculate_distance_between_points(-17.5, 179.9, -17.5, -179.9);
+        assert!((total_km - crossing_km).abs() < 1e-6);
+
+        let id = HexId { q: 0, r: 0 };
+        assert_eq!(
+            hex_disk(id, u32::MAX).len(),
+            hex_disk(id, MAX_HEX_DISK_RADIUS).len()
+        );
+    }
+
+    #[test]
+    fn test_geojson_choropleth() {
+        let result = hexbin_points(
+            &[[51.50, -0.12], [51.50, -0.12], [51.52, -0.10]],
+            &HexbinOptions::default(),
+        );
+        assert_eq!(result.cells.len(), 2);
+        assert!(result.cells.iter().all(|c| c.length_m == 0.0));
+
+        let geojson = result.to_geojson();
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features.len(), 2);
+
+        let busy = features
+            .iter()
+            .find(|f| f["properties"]["points"] == 2)
+            .unwrap();
+        let ring = busy["geometry"]["coordinates"][0].as_array().unwrap();
+        assert_eq!(ring.len(), 7);
+        // lng first
+        let lon = ring[0][0].as_f64().unwrap();
+        assert!((lon - -0.12).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_long_step_at_the_smallest_size_is_capped() {
+        // some 1100 km in one step, millions of quarter meter pieces uncapped
+        let step = vec![[40.0, -5.0], [50.0, -5.0]];
+        let result = hexbin_tracks(&[step], &HexbinOptions { size_m: 1.0 });
+
+        assert!(result.cells.len() <= MAX_HEX_PIECES + 2);
+        let length: f64 = result.cells.iter().map(|c| c.length_m).sum();
+        let expected = calculate_distance_between_points(40.0, -5.0, 50.0, -5.0) * 1000.0;
+        assert!((length - expected).abs() < 1.0, "{length} {expected}");
+    }
+}
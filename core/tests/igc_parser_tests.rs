@@ -0,0 +1,133 @@
+#[cfg(test)]
+mod igc_parser_tests {
+    use fastgeotoolkit::*;
+
+    // trimmed flight crossing midnight UTC, with FXA/SIU/ENL extensions
+    const IGC_FLIGHT: &str = "\
+AXCS00123 SkyDrop
+HFDTEDATE:310324,01
+HFPLTPILOTINCHARGE:Jane Doe
+HFCM2CREW2:NOT SET
+HFGTYGLIDERTYPE:Ozone Rush 6
+HFGIDGLIDERID:D-1234
+HFCIDCOMPETITIONID:
+I033638FXA3940SIU4143ENL
+B2359584708000N01130900EA005440058901208012
+B2359594708038N01131000EA005450059101107009
+B0000004708138N01131100EA005460059200907005
+B0000014708238N01131200EV005470000000000000
+LXCSsome logger specific record
+G0123456789ABCDEF
+";
+
+    // 2024-03-31T23:59:58Z
+    const FIRST_FIX_MS: i64 = 1_711_929_598_000;
+
+    fn parse(flight: &str) -> (IgcFlight, IgcParser) {
+        let mut parser = IgcParser::new(flight.as_bytes().to_vec());
+        let parsed = parser.parse_flight();
+        (parsed, parser)
+    }
+
+    #[test]
+    fn test_is_igc_file() {
+        assert!(is_igc_file(IGC_FLIGHT.as_bytes()));
+        assert!(!is_igc_file(b"<?xml version=\"1.0\"?><gpx></gpx>"));
+        assert!(!is_igc_file(b"A plain text note\nwithout headers"));
+        assert!(!is_igc_file(&[]));
+        // multibyte characters right after the A are not a manufacturer code
+        assert!(!is_igc_file("Année,lat,lon\nHeure,1,2".as_bytes()));
+        assert!(!is_igc_file("Aé\nH".as_bytes()));
+    }
+
+    #[test]
+    fn test_header_records() {
+        let (flight, _) = parse(IGC_FLIGHT);
+        let header = flight.header;
+
+        assert_eq!(header.manufacturer.as_deref(), Some("XCS"));
+        assert_eq!(header.logger_id.as_deref(), Some("00123 SkyDrop"));
+        assert_eq!(header.pilot.as_deref(), Some("Jane Doe"));
+        assert_eq!(header.copilot, None);
+        assert_eq!(header.glider_type.as_deref(), Some("Ozone Rush 6"));
+        assert_eq!(header.glider_id.as_deref(), Some("D-1234"));
+        assert_eq!(header.competition_id, None);
+        assert_eq!(header.date_days, Some(19813));
+    }
+
+    #[test]
+    fn test_short_date_header() {
+        let flight = "AXCS001\nHFDTE010124\nB1200004708000N01130900EA0054400589\n";
+        let (parsed, _) = parse(flight);
+
+        assert_eq!(parsed.header.date_days, Some(19723));
+        assert_eq!(parsed.fixes[0].timestamp_ms, Some(1_704_110_400_000));
+    }
+
+    #[test]
+    fn test_b_records_with_both_altitudes() {
+        let (flight, _) = parse(IGC_FLIGHT);
+        assert_eq!(flight.fixes.len(), 4);
+
+        let fix = &flight.fixes[1];
+        assert!((fix.lat - 47.1339667).abs() < 1e-4);
+        assert!((fix.lon - 11.5166667).abs() < 1e-4);
+        assert_eq!(fix.pressure_altitude, Some(545));
+        assert_eq!(fix.gnss_altitude, Some(591));
+        assert!(fix.valid);
+
+        assert!(!flight.fixes[3].valid);
+    }
+
+    #[test]
+    fn test_i_record_extensions() {
+        let (flight, parser) = parse(IGC_FLIGHT);
+        let codes: Vec<&str> = parser.extensions.iter().map(|e| e.code.as_str()).collect();
+        assert_eq!(codes, vec!["FXA", "SIU", "ENL"]);
+
+        let extensions = &flight.fixes[0].extensions;
+        assert_eq!(extensions.get("FXA").map(String::as_str), Some("012"));
+        assert_eq!(extensions.get("SIU").map(String::as_str), Some("08"));
+        assert_eq!(extensions.get("ENL").map(String::as_str), Some("012"));
+    }
+
+    #[test]
+    fn test_non_ascii_i_record_is_ignored() {
+        let flight = IGC_FLIGHT.replace("I033638FXA3940SIU4143ENL", "I023é38FXA3940SIU");
+        let (flight, parser) = parse(&flight);
+        assert!(parser.extensions.is_empty());
+        assert_eq!(flight.fixes.len(), 4);
+    }
+
+    #[test]
+    fn test_timestamps_roll_over_midnight() {
+        let (flight, _) = parse(IGC_FLIGHT);
+        let timestamps: Vec<Option<i64>> = flight.fixes.iter().map(|f| f.timestamp_ms).collect();
+
+        assert_eq!(
+            timestamps,
+            vec![
+                Some(FIRST_FIX_MS),
+                Some(FIRST_FIX_MS + 1000),
+                Some(FIRST_FIX_MS + 2000),
+                Some(FIRST_FIX_MS + 3000),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_malformed_b_records_are_skipped() {
+        let flight = "\
+AXCS001
+HFDTE010124
+B120000
+B9900004708000N01130900EA0054400589
+B1200014708000X01130900EA0054400589
+B1200024708000N01130900EA0054400589
+";
+        let mut parser = IgcParser::new(flight.as_bytes().to_vec());
+        let coords = parser.parse_gps_coordinates();
+
+        assert_eq!(coords.len(), 1);
+    }
+}
@@ -0,0 +1,242 @@
+#[cfg(test)]
+mod import_tests {
+    use fastgeotoolkit::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    const GPX_FILE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="fastgeotoolkit tests" xmlns="http://www.topografix.com/GPX/1/1">
+  <metadata><name>Morning ride</name></metadata>
+  <trk>
+    <name>Loop</name>
+    <trkseg>
+      <trkpt lat="37.7749" lon="-122.4194"><ele>12.5</ele><time>2024-03-31T23:59:58Z</time></trkpt>
+      <trkpt lat="37.7849" lon="-122.4094"><ele>13.0</ele><time>2024-03-31T23:59:59Z</time></trkpt>
+      <trkpt lat="0" lon="0"></trkpt>
+      <trkpt lat="37.7949" lon="-122.3994"></trkpt>
+    </trkseg>
+  </trk>
+</gpx>"#;
+
+    const IGC_FILE: &str = "\
+AXCS001
+HFDTE310324
+B2359584708000N01130900EA0054400589
+B2359594708038N01131000EA0054500591
+";
+
+    const NMEA_FILE: &str = "\
+$GPRMC,235959.00,A,4807.038,N,01131.000,E,022.4,084.4,310324,003.1,W*40
+$GPGGA,000000.00,4807.138,N,01131.100,E,1,09,0.8,546.0,M,46.9,M,,*63
+";
+
+    const CSV_FILE: &str = "lat,lon\n37.7749,-122.4194\n37.7849,-122.4094\n";
+
+    const STREAMS_FILE: &str = include_str!("fixtures/strava_streams.json");
+
+    fn minimal_fit_file() -> Vec<u8> {
+        let mut data = vec![14, 0x10, 0, 0, 0, 0, 0, 0, b'.', b'F', b'I', b'T', 0, 0];
+        data.extend_from_slice(&[0, 0]);
+        data
+    }
+
+    // 2024-04-01T00:00:00Z in seconds since the FIT epoch
+    const FIT_START: u32 = 1_080_864_000;
+
+    fn semicircles(degrees: f64) -> [u8; 4] {
+        ((degrees * 2147483648.0 / 180.0) as i32).to_le_bytes()
+    }
+
+    // two records with timestamp, position and altitude, then one with a compressed
+    // timestamp header 10 seconds later
+    fn fit_activity() -> Vec<u8> {
+        // definitions of record messages: header, reserved, architecture, global number,
+        // field count, then number, size and base type of each field
+        let with_timestamp = [
+            0x40, 0, 0, 20, 0, 4, 253, 4, 0x86, 0, 4, 0x85, 1, 4, 0x85, 2, 2, 0x84,
+        ];
+        let without_timestamp = [0x41, 0, 0, 20, 0, 3, 0, 4, 0x85, 1, 4, 0x85, 2, 2, 0x84];
+        let mut records = [with_timestamp.as_slice(), &without_timestamp].concat();
+        for (seconds, lat, lon) in [(0, 48.1173, 11.5167), (5, 48.1183, 11.5177)] {
+            records.push(0);
+            records.extend_from_slice(&(FIT_START + seconds).to_le_bytes());
+            records.extend_from_slice(&semicircles(lat));
+            records.extend_from_slice(&semicircles(lon));
+            records.extend_from_slice(&5227u16.to_le_bytes()); // 545.4 m
+        }
+        records.push(0x80 | (1 << 5) | 10);
+        records.extend_from_slice(&semicircles(48.1193));
+        records.extend_from_slice(&semicircles(11.5187));
+        records.extend_from_slice(&0xFFFFu16.to_le_bytes());
+
+        let mut data = vec![14, 0x10, 0, 0];
+        data.extend_from_slice(&(records.len() as u32).to_le_bytes());
+        data.extend_from_slice(b".FIT");
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(&records);
+        data.extend_from_slice(&[0, 0]);
+        data
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(detect_format(GPX_FILE.as_bytes()), Some(FileFormat::Gpx));
+        assert_eq!(detect_format(&minimal_fit_file()), Some(FileFormat::Fit));
+        assert_eq!(detect_format(IGC_FILE.as_bytes()), Some(FileFormat::Igc));
+        assert_eq!(detect_format(NMEA_FILE.as_bytes()), Some(FileFormat::Nmea));
+        assert_eq!(detect_format(CSV_FILE.as_bytes()), Some(FileFormat::Csv));
+        assert_eq!(
+            detect_format(STREAMS_FILE.as_bytes()),
+            Some(FileFormat::StravaStreams)
+        );
+        // an A followed by a multibyte character is no IGC A record
+        let french = "Année,lat,lon\n2024,48.1,11.5\n2024,48.2,11.6\n";
+        assert_eq!(detect_format(french.as_bytes()), Some(FileFormat::Csv));
+        assert_eq!(detect_format(b"hello world"), None);
+        assert_eq!(detect_format(&[]), None);
+    }
+
+    #[test]
+    fn test_parse_gpx_file() {
+        let activity = parse_file(GPX_FILE.as_bytes()).unwrap();
+
+        assert_eq!(activity.format, FileFormat::Gpx);
+        assert_eq!(activity.tracks.len(), 1);
+        assert_eq!(activity.point_count(), 3, "null island point is dropped");
+        assert_eq!(activity.tracks[0][0].elevation, Some(12.5));
+        assert_eq!(activity.start_time_ms(), Some(1_711_929_598_000));
+        assert_eq!(activity.end_time_ms(), Some(1_711_929_599_000));
+        assert_eq!(activity.metadata["name"], "Morning ride");
+        assert_eq!(activity.metadata["tracks"][0]["name"], "Loop");
+        assert_eq!(activity.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_text_formats() {
+        let igc = parse_file(IGC_FILE.as_bytes()).unwrap();
+        assert_eq!(igc.point_count(), 2);
+        assert_eq!(igc.tracks[0][0].elevation, Some(589.0));
+        assert_eq!(igc.metadata["header"]["manufacturer"], "XCS");
+
+        let nmea = parse_file(NMEA_FILE.as_bytes()).unwrap();
+        assert_eq!(nmea.point_count(), 2);
+        assert_eq!(nmea.end_time_ms(), Some(1_711_929_600_000));
+        assert!(nmea.warnings.is_empty());
+
+        let csv = parse_file(CSV_FILE.as_bytes()).unwrap();
+        assert_eq!(
+            csv.coordinates(),
+            vec![vec![[37.7749, -122.4194], [37.7849, -122.4094]]]
+        );
+
+        let streams = parse_file(STREAMS_FILE.as_bytes()).unwrap();
+        assert_eq!(streams.point_count(), 6);
+    }
+
+    #[test]
+    fn test_fit_without_records_warns() {
+        let activity = parse_file(&minimal_fit_file()).unwrap();
+
+        assert_eq!(activity.format, FileFormat::Fit);
+        assert!(activity.tracks.is_empty());
+        assert_eq!(activity.warnings, vec!["file contains no track points"]);
+    }
+
+    #[test]
+    fn test_fit_timestamps_and_altitude() {
+        let activity = parse_file(&fit_activity()).unwrap();
+        assert_eq!(activity.format, FileFormat::Fit);
+        assert_eq!(activity.point_count(), 3);
+
+        let points = &activity.tracks[0];
+        let start_ms = 1_711_929_600_000;
+        let timestamps: Vec<Option<i64>> = points.iter().map(|p| p.timestamp_ms).collect();
+        assert_eq!(
+            timestamps,
+            vec![
+                Some(start_ms),
+                Some(start_ms + 5_000),
+                Some(start_ms + 10_000)
+            ]
+        );
+        assert!((points[0].elevation.unwrap() - 545.4).abs() < 1e-9);
+        assert_eq!(points[2].elevation, None);
+        assert!((points[1].lat - 48.1183).abs() < 1e-6);
+        assert_eq!(activity.metadata["start_timestamp_ms"], start_ms);
+
+        // the plain coordinate parser still sees the same positions, rounded as before
+        let coordinates = FitParser::new(fit_activity()).parse_gps_coordinates();
+        assert_eq!(coordinates.len(), 3);
+        assert_eq!(coordinates[1], [48.1183, 11.5177]);
+        assert_ne!(points[1].lat, 48.1183);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse_file(b"just some text"), Err(Error::UnknownFormat));
+
+        let broken = parse_file(b"<?xml version=\"1.0\"?><gpx><trk><trkseg>");
+        match broken {
+            Err(Error::Parse { format, .. }) => assert_eq!(format, FileFormat::Gpx),
+            other => panic!("expected a gpx parse error, got {other:?}"),
+        }
+
+        let error = parse_file(br#"{"latlng": {"data": "oops"}}"#).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("failed to parse strava_streams file"));
+    }
+
+    #[test]
+    fn test_process_gpx_files_rust_mixes_formats() {
+        let files = vec![
+            GPX_FILE.as_bytes().to_vec(),
+            IGC_FILE.as_bytes().to_vec(),
+            NMEA_FILE.as_bytes().to_vec(),
+            b"not a track".to_vec(),
+        ];
+        let heatmap = process_gpx_files_rust(&files);
+
+        assert_eq!(heatmap.tracks.len(), 3);
+        assert!(heatmap.max_frequency >= 1);
+    }
+
+    #[test]
+    fn test_gzip_input_is_decompressed() {
+        let compressed = gzip(GPX_FILE.as_bytes());
+        assert!(is_gzip_file(&compressed));
+        assert_eq!(detect_format(&compressed), None);
+
+        let activity = parse_file(&compressed).unwrap();
+        assert_eq!(activity.format, FileFormat::Gpx);
+        assert_eq!(activity.point_count(), 3);
+
+        let fit = parse_file(&gzip(&minimal_fit_file())).unwrap();
+        assert_eq!(fit.format, FileFormat::Fit);
+
+        let heatmap = process_gpx_files_rust(&[compressed, gzip(IGC_FILE.as_bytes())]);
+        assert_eq!(heatmap.tracks.len(), 2);
+    }
+
+    #[test]
+    fn test_gzip_size_limit_and_corruption() {
+        let compressed = gzip(&vec![b' '; 10_000]);
+
+        assert_eq!(decompress_gzip(&compressed, 10_000).unwrap().len(), 10_000);
+        assert!(matches!(
+            decompress_gzip(&compressed, 9_999),
+            Err(Error::Decompress(_))
+        ));
+
+        let mut truncated = gzip(GPX_FILE.as_bytes());
+        truncated.truncate(truncated.len() / 2);
+        assert!(matches!(parse_file(&truncated), Err(Error::Decompress(_))));
+    }
+}
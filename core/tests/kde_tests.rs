@@ -0,0 +1,157 @@
+#[cfg(test)]
+mod kde_tests {
+    use fastgeotoolkit::*;
+
+    fn cell_area_km2(grid: &DensityGrid) -> f64 {
+        (grid.cell_size_m / 1000.0).powi(2)
+    }
+
+    fn busiest_cell(grid: &DensityGrid) -> (u32, u32) {
+        let i = grid
+            .values
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap()
+            .0;
+        (i as u32 % grid.columns, i as u32 / grid.columns)
+    }
+
+    #[test]
+    fn test_single_point_integrates_to_one() {
+        for kernel in [Kernel::Gaussian, Kernel::Epanechnikov] {
+            let options = KdeOptions {
+                kernel,
+                bandwidth_m: 200.0,
+                cell_size_m: 10.0,
+                ..KdeOptions::default()
+            };
+            let grid = kernel_density(&[[48.2, 16.37]], &options);
+
+            let total: f64 = grid.values.iter().sum::<f64>() * cell_area_km2(&grid);
+            assert!((total - 1.0).abs() < 0.02, "{kernel:?} sums to {total}");
+
+            // the peak sits on the point, in the middle of the fitted grid
+            let (column, row) = busiest_cell(&grid);
+            let center = grid.cell_center(column, row);
+            assert!((center[0] - 48.2).abs() < 0.0002);
+            assert!((center[1] - 16.37).abs() < 0.0002);
+            assert_eq!(grid.max_value, grid.value(column, row));
+        }
+    }
+
+    #[test]
+    fn test_epanechnikov_ends_at_the_bandwidth() {
+        let options = KdeOptions {
+            kernel: Kernel::Epanechnikov,
+            bandwidth_m: 100.0,
+            cell_size_m: 10.0,
+            bbox: Some([48.19, 16.36, 48.21, 16.38]),
+            ..KdeOptions::default()
+        };
+        let grid = kernel_density(&[[48.2, 16.37]], &options);
+
+        // cells more than ~110 m north of the point
+        let (column, row) = busiest_cell(&grid);
+        assert!(grid.value(column, row - 12) == 0.0);
+        assert!(grid.value(column, row - 8) > 0.0);
+        assert_eq!(grid.value(0, 0), 0.0);
+    }
+
+    #[test]
+    fn test_stops_stand_out_from_moving() {
+        // riding east at about 10 m between points, then waiting at the lights
+        let mut track: Vec<[f64; 2]> = (0..100)
+            .map(|i| [48.2, 16.37 + i as f64 * 0.00013])
+            .collect();
+        track.extend([[48.2, 16.3829]; 150]);
+        let grid = kernel_density_from_tracks(&[track], &KdeOptions::default());
+
+        let (column, row) = busiest_cell(&grid);
+        let center = grid.cell_center(column, row);
+        assert!((center[1] - 16.3829).abs() < 0.0005);
+
+        // the middle of the ride is much quieter
+        let middle_column =
+            ((16.375 - grid.bbox[1]) / (grid.bbox[3] - grid.bbox[1]) * grid.columns as f64) as u32;
+        assert!(grid.value(middle_column, row) * 3.0 < grid.max_value);
+    }
+
+    #[test]
+    fn test_grid_size_is_capped() {
+        let options = KdeOptions {
+            cell_size_m: 1.0,
+            max_cells: 10_000,
+            ..KdeOptions::default()
+        };
+        let grid = kernel_density(&[[48.2, 16.37], [48.25, 16.45]], &options);
+
+        assert!(grid.values.len() <= 10_000);
+        assert_eq!(grid.values.len(), (grid.columns * grid.rows) as usize);
+        assert!(grid.cell_size_m > 1.0);
+        assert!(grid.bbox[0] < 48.2 && grid.bbox[2] > 48.25);
+
+        let empty = kernel_density(&[], &options);
+        assert_eq!((empty.columns, empty.rows), (0, 0));
+    }
+
+    #[test]
+    fn test_degenerate_cell_size_and_bandwidth() {
+        // a zero cell across a continent used to overflow columns * rows
+        let options = KdeOptions {
+            cell_size_m: 0.0,
+            bandwidth_m: 0.0,
+            max_cells: 10_000,
+            ..KdeOptions::default()
+        };
+        let grid = kernel_density(&[[36.0, -9.0], [70.0, 40.0]], &options);
+        assert!(grid.values.len() <= 10_000);
+        assert!(grid.values.iter().all(|v| v.is_finite()));
+
+        let tiny = KdeOptions {
+            cell_size_m: 1e-9,
+            bandwidth_m: 1e-9,
+            ..KdeOptions::default()
+        };
+        let grid = kernel_density(&[[48.2, 16.37]], &tiny);
+        assert_eq!(grid.cell_size_m, MIN_KDE_CELL_SIZE_M);
+        assert!(grid.max_value.is_finite() && grid.max_value > 0.0);
+    }
+
+    #[test]
+    fn test_png_and_geojson_export() {
+        let options = KdeOptions {
+            kernel: Kernel::Epanechnikov,
+            cell_size_m: 50.0,
+            ..KdeOptions::default()
+        };
+        let grid = kernel_density(&[[48.2, 16.37], [48.2, 16.371]], &options);
+
+        let image = grid.to_image(&RenderOptions::default());
+        assert_eq!((image.width, image.height), (grid.columns, grid.rows));
+        let (column, row) = busiest_cell(&grid);
+        assert_eq!(image.pixel(column, row), [255, 255, 255, 255]);
+        assert!(image.to_png().is_ok());
+
+        let geojson = grid.to_geojson(0.0);
+        let features = geojson["features"].as_array().unwrap();
+        let occupied = grid.values.iter().filter(|&&v| v > 0.0).count();
+        assert_eq!(features.len(), occupied);
+
+        let ring = features[0]["geometry"]["coordinates"][0]
+            .as_array()
+            .unwrap();
+        assert_eq!(ring.len(), 5);
+        assert_eq!(ring[0], ring[4]);
+        assert!(features[0]["properties"]["density"].as_f64().unwrap() > 0.0);
+    }
+
+    #[test]
+    fn test_options_from_json() {
+        let options: KdeOptions =
+            serde_json::from_str(r#"{"kernel": "epanechnikov", "bandwidth_m": 50}"#).unwrap();
+        assert_eq!(options.kernel, Kernel::Epanechnikov);
+        assert_eq!(options.bandwidth_m, 50.0);
+        assert_eq!(options.cell_size_m, 25.0);
+    }
+}
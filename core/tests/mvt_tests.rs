@@ -0,0 +1,342 @@
+#[cfg(test)]
+mod mvt_tests {
+    use fastgeotoolkit::*;
+
+    // just enough protobuf to read the tiles back
+    #[derive(Debug)]
+    enum Field {
+        Varint(u64),
+        Fixed64(u64),
+        Bytes(Vec<u8>),
+    }
+
+    fn read_varint(bytes: &[u8], pos: &mut usize) -> u64 {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = bytes[*pos];
+            *pos += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte < 0x80 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+
+    fn fields(bytes: &[u8]) -> Vec<(u64, Field)> {
+        let mut pos = 0;
+        let mut out = Vec::new();
+        while pos < bytes.len() {
+            let key = read_varint(bytes, &mut pos);
+            let field = match key & 7 {
+                0 => Field::Varint(read_varint(bytes, &mut pos)),
+                1 => {
+                    let value = u64::from_le_bytes(bytes[pos..pos + 8].try_into().unwrap());
+                    pos += 8;
+                    Field::Fixed64(value)
+                }
+                2 => {
+                    let len = read_varint(bytes, &mut pos) as usize;
+                    pos += len;
+                    Field::Bytes(bytes[pos - len..pos].to_vec())
+                }
+                wire => panic!("unexpected wire type {wire}"),
+            };
+            out.push((key >> 3, field));
+        }
+        out
+    }
+
+    fn packed(bytes: &[u8]) -> Vec<u32> {
+        let mut pos = 0;
+        let mut out = Vec::new();
+        while pos < bytes.len() {
+            out.push(read_varint(bytes, &mut pos) as u32);
+        }
+        out
+    }
+
+    #[derive(Debug)]
+    struct Layer {
+        version: u64,
+        name: String,
+        extent: u64,
+        features: Vec<Feature>,
+    }
+
+    #[derive(Debug)]
+    struct Feature {
+        id: u64,
+        kind: u64,
+        attributes: Vec<(String, f64)>,
+        // one list of tile coordinates per MoveTo
+        parts: Vec<Vec<[i32; 2]>>,
+    }
+
+    impl Feature {
+        fn attribute(&self, key: &str) -> Option<f64> {
+            self.attributes
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| *v)
+        }
+    }
+
+    fn decode(tile: &[u8]) -> Vec<Layer> {
+        fields(tile)
+            .into_iter()
+            .map(|(number, field)| match (number, field) {
+                (3, Field::Bytes(layer)) => decode_layer(&layer),
+                other => panic!("unexpected tile field {other:?}"),
+            })
+            .collect()
+    }
+
+    fn decode_layer(bytes: &[u8]) -> Layer {
+        let (mut version, mut name, mut extent) = (0, String::new(), 4096);
+        let (mut keys, mut values, mut raw_features) = (Vec::new(), Vec::new(), Vec::new());
+
+        for (number, field) in fields(bytes) {
+            match (number, field) {
+                (15, Field::Varint(v)) => version = v,
+                (1, Field::Bytes(b)) => name = String::from_utf8(b).unwrap(),
+                (2, Field::Bytes(b)) => raw_features.push(b),
+                (3, Field::Bytes(b)) => keys.push(String::from_utf8(b).unwrap()),
+                (4, Field::Bytes(b)) => values.push(match &fields(&b)[0] {
+                    (5, Field::Varint(v)) => *v as f64,
+                    (3, Field::Fixed64(v)) => f64::from_bits(*v),
+                    other => panic!("unexpected value {other:?}"),
+                }),
+                (5, Field::Varint(v)) => extent = v,
+                other => panic!("unexpected layer field {other:?}"),
+            }
+        }
+
+        let features = raw_features
+            .iter()
+            .map(|bytes| {
+                let mut feature = Feature {
+                    id: 0,
+                    kind: 0,
+                    attributes: Vec::new(),
+                    parts: Vec::new(),
+                };
+                for (number, field) in fields(bytes) {
+                    match (number, field) {
+                        (1, Field::Varint(v)) => feature.id = v,
+                        (3, Field::Varint(v)) => feature.kind = v,
+                        (2, Field::Bytes(b)) => {
+                            feature.attributes = packed(&b)
+                                .chunks(2)
+                                .map(|t| (keys[t[0] as usize].clone(), values[t[1] as usize]))
+                                .collect();
+                        }
+                        (4, Field::Bytes(b)) => feature.parts = decode_geometry(&packed(&b)),
+                        other => panic!("unexpected feature field {other:?}"),
+                    }
+                }
+                feature
+            })
+            .collect();
+
+        Layer {
+            version,
+            name,
+            extent,
+            features,
+        }
+    }
+
+    fn decode_geometry(commands: &[u32]) -> Vec<Vec<[i32; 2]>> {
+        let unzigzag = |v: u32| ((v >> 1) as i32) ^ -((v & 1) as i32);
+        let (mut parts, mut cursor, mut i) = (Vec::<Vec<[i32; 2]>>::new(), [0, 0], 0);
+        while i < commands.len() {
+            let (id, count) = (commands[i] & 7, commands[i] >> 3);
+            i += 1;
+            if id == 1 {
+                parts.push(Vec::new());
+            }
+            for _ in 0..count {
+                cursor = [
+                    cursor[0] + unzigzag(commands[i]),
+                    cursor[1] + unzigzag(commands[i + 1]),
+                ];
+                parts.last_mut().unwrap().push(cursor);
+                i += 2;
+            }
+        }
+        parts
+    }
+
+    fn busy_and_quiet() -> HeatmapResult {
+        create_heatmap_from_tracks(vec![
+            vec![[51.50, -0.12], [51.51, -0.11]],
+            vec![[51.50, -0.12], [51.51, -0.11]],
+            vec![[51.40, -0.30], [51.45, -0.25]],
+        ])
+    }
+
+    #[test]
+    fn test_layer_and_frequency_attribute() {
+        // the z8 tile over London, at z0 these tracks would round to single points
+        let tile = heatmap_mvt_tile(&busy_and_quiet(), 8, 127, 85, &MvtOptions::default());
+        let layers = decode(&tile);
+
+        assert_eq!(layers.len(), 1);
+        let layer = &layers[0];
+        assert_eq!(
+            (layer.version, layer.name.as_str(), layer.extent),
+            (2, "heatmap", 4096)
+        );
+        assert_eq!(layer.features.len(), 3);
+
+        let mut frequencies: Vec<f64> = layer
+            .features
+            .iter()
+            .map(|f| f.attribute("frequency").unwrap())
+            .collect();
+        frequencies.sort_by(f64::total_cmp);
+        assert_eq!(frequencies, vec![1.0, 2.0, 2.0]);
+        assert!(layer.features.iter().all(|f| f.kind == 2 && f.id > 0));
+    }
+
+    #[test]
+    fn test_geometry_is_clipped_and_quantized() {
+        // crosses the prime meridian, the z1 tiles either side each get one half
+        let result = create_heatmap_from_tracks(vec![vec![[51.5, -20.0], [51.5, 20.0]]]);
+        let options = MvtOptions {
+            extent: 512,
+            buffer: 8,
+            ..MvtOptions::default()
+        };
+
+        for x in [0, 1] {
+            let layers = decode(&heatmap_mvt_tile(&result, 1, x, 0, &options));
+            let parts = &layers[0].features[0].parts;
+            assert_eq!(parts.len(), 1);
+
+            let points = &parts[0];
+            assert_eq!(points.len(), 2);
+            assert!(points.iter().flatten().all(|&v| (-8..=520).contains(&v)));
+            let edge = if x == 0 { 520 } else { -8 };
+            assert!(points.iter().any(|p| p[0] == edge), "cut at the buffer");
+        }
+
+        // nothing lands in the southern tiles
+        assert!(heatmap_mvt_tile(&result, 1, 0, 1, &options).is_empty());
+    }
+
+    #[test]
+    fn test_line_leaving_and_reentering_a_tile_has_two_parts() {
+        // out east across the prime meridian and back west a little further north
+        let result = create_heatmap_from_tracks(vec![vec![
+            [10.0, -100.0],
+            [10.0, 20.0],
+            [30.0, 20.0],
+            [30.0, -100.0],
+        ]]);
+        let options = MvtOptions {
+            buffer: 0,
+            ..MvtOptions::default()
+        };
+
+        let west = decode(&heatmap_mvt_tile(&result, 1, 0, 0, &options));
+        let parts = &west[0].features[0].parts;
+        assert_eq!(parts.len(), 2);
+        assert!(parts.iter().all(|part| part.len() == 2));
+
+        // the turn itself stays in one piece
+        let east = decode(&heatmap_mvt_tile(&result, 1, 1, 0, &options));
+        assert_eq!(east[0].features[0].parts.len(), 1);
+        assert_eq!(east[0].features[0].parts[0].len(), 4);
+    }
+
+    #[test]
+    fn test_segment_attributes() {
+        let options = HeatmapOptions {
+            directional: true,
+            ..HeatmapOptions::default()
+        };
+        let result = process_tracks_by_segment_with_options(
+            vec![
+                vec![[51.50, -0.12], [51.51, -0.12]],
+                vec![[51.51, -0.12], [51.50, -0.12]],
+                vec![[51.50, -0.12], [51.51, -0.12]],
+            ],
+            &options,
+        );
+        let tile = segment_mvt_tile(&result, 8, 127, 85, &MvtOptions::default());
+        let feature = &decode(&tile)[0].features[0];
+
+        assert_eq!(feature.attribute("frequency"), Some(3.0));
+        assert_eq!(feature.attribute("passes"), Some(3.0));
+        assert_eq!(feature.attribute("forward"), Some(2.0));
+        assert_eq!(feature.attribute("backward"), Some(1.0));
+        assert!(feature.attribute("bearing").unwrap().abs() < 1.0);
+    }
+
+    #[test]
+    fn test_bulk_tiles_skip_empty_ones() {
+        let options = MvtOptions {
+            min_zoom: 8,
+            max_zoom: 12,
+            ..MvtOptions::default()
+        };
+        let tiles = heatmap_mvt_tiles(&busy_and_quiet(), &options);
+
+        for zoom in 8..=12 {
+            assert!(tiles.iter().any(|t| t.z == zoom));
+        }
+        for tile in &tiles {
+            assert_eq!(
+                tile.data,
+                heatmap_mvt_tile(&busy_and_quiet(), tile.z, tile.x, tile.y, &options)
+            );
+            assert!(!decode(&tile.data)[0].features.is_empty());
+        }
+
+        let at_zoom = |zoom: u8| {
+            tiles
+                .iter()
+                .filter(|t| t.z == zoom)
+                .map(|t| (t.x, t.y))
+                .collect::<Vec<_>>()
+        };
+        // only the tiles the two diagonals actually pass through, not their bounding box
+        assert_eq!(
+            at_zoom(12),
+            vec![
+                (2044, 1363),
+                (2044, 1364),
+                (2045, 1363),
+                (2046, 1361),
+                (2046, 1362)
+            ]
+        );
+        assert!(heatmap_mvt_tiles(&create_heatmap_from_tracks(vec![]), &options).is_empty());
+    }
+
+    #[test]
+    fn test_line_across_the_antimeridian() {
+        let result = create_heatmap_from_tracks(vec![vec![[-17.0, 179.8], [-17.0, -179.8]]]);
+        let options = MvtOptions {
+            min_zoom: 3,
+            max_zoom: 3,
+            ..MvtOptions::default()
+        };
+        let tiles = heatmap_mvt_tiles(&result, &options);
+
+        let ids: Vec<_> = tiles.iter().map(|t| (t.z, t.x, t.y)).collect();
+        assert_eq!(ids, vec![(3, 0, 4), (3, 7, 4)]);
+        // each side only runs from the world's edge into the tile, not across it
+        for tile in &tiles {
+            let layers = decode(&tile.data);
+            for part in &layers[0].features[0].parts {
+                let xs: Vec<i32> = part.iter().map(|p| p[0]).collect();
+                let span = xs.iter().max().unwrap() - xs.iter().min().unwrap();
+                assert!(span < 100, "{xs:?}");
+            }
+        }
+    }
+}
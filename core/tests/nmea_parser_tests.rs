@@ -0,0 +1,141 @@
+#[cfg(test)]
+mod nmea_parser_tests {
+    use fastgeotoolkit::*;
+
+    // short log crossing midnight, the first GGA arrives before any RMC
+    const NMEA_LOG: &str = "\
+$GNGGA,235958.00,4807.000,N,01130.900,E,2,07,1.1,544.0,M,46.9,M,,*79
+$GPGGA,235959.00,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*65
+$GPRMC,235959.00,A,4807.038,N,01131.000,E,022.4,084.4,310324,003.1,W*40
+$GPVTG,084.4,T,,M,022.4,N,041.5,K,A*01
+$GPGGA,000000.00,4807.138,N,01131.100,E,1,09,0.8,546.0,M,46.9,M,,*63
+$GPGLL,4807.238,N,01131.200,E,000001.00,A,A*6A
+$GPGGA,000002.00,4807.338,N,01131.300,E,0,00,99.9,,M,,M,,*64
+";
+
+    // 2024-03-31T23:59:59Z
+    const RMC_TIMESTAMP_MS: i64 = 1_711_929_599_000;
+
+    fn parse(log: &str) -> (Vec<NmeaFix>, NmeaParser) {
+        let mut parser = NmeaParser::new(log.as_bytes().to_vec());
+        let fixes = parser.parse_fixes();
+        (fixes, parser)
+    }
+
+    #[test]
+    fn test_is_nmea_file() {
+        assert!(is_nmea_file(NMEA_LOG.as_bytes()));
+        assert!(!is_nmea_file(b"<?xml version=\"1.0\"?><gpx></gpx>"));
+        assert!(!is_nmea_file(b"$PGRMZ,246,f,3*1B\n"));
+        assert!(!is_nmea_file(&[]));
+    }
+
+    #[test]
+    fn test_nmea_checksum() {
+        assert_eq!(
+            nmea_checksum("GPGLL,4807.238,N,01131.200,E,000001.00,A,A"),
+            0x6A
+        );
+    }
+
+    #[test]
+    fn test_parse_fixes_skips_invalid_quality() {
+        let (fixes, parser) = parse(NMEA_LOG);
+
+        // the quality 0 GGA at 00:00:02 is dropped
+        assert_eq!(fixes.len(), 4);
+        assert_eq!(parser.sentence_count, 7);
+        assert_eq!(parser.checksum_errors, 0);
+    }
+
+    #[test]
+    fn test_sentences_merge_into_one_fix_per_epoch() {
+        let (fixes, _) = parse(NMEA_LOG);
+        let fix = &fixes[1];
+
+        assert!((fix.lat - 48.1173).abs() < 1e-4);
+        assert!((fix.lon - 11.51667).abs() < 1e-4);
+        // full precision, rounding is up to the heatmap's coordinate_precision
+        assert!((fix.lon - 11.5 - 1.0 / 60.0).abs() < 1e-9);
+        let mut parser = NmeaParser::new(NMEA_LOG.as_bytes().to_vec());
+        assert_eq!(parser.parse_gps_coordinates()[1], [48.1173, 11.51667]);
+        assert_eq!(fix.elevation, Some(545.4));
+        assert_eq!(fix.fix_quality, Some(1));
+        assert_eq!(fix.satellites, Some(8));
+        assert_eq!(fix.hdop, Some(0.9));
+        assert_eq!(fix.course, Some(84.4));
+        // the VTG km/h field wins over the converted RMC knots
+        assert_eq!(fix.speed_kmh, Some(41.5));
+    }
+
+    #[test]
+    fn test_date_stitching_across_midnight() {
+        let (fixes, _) = parse(NMEA_LOG);
+        let timestamps: Vec<Option<i64>> = fixes.iter().map(|fix| fix.timestamp_ms).collect();
+
+        assert_eq!(
+            timestamps,
+            vec![
+                Some(RMC_TIMESTAMP_MS - 1000), // backfilled from the later RMC
+                Some(RMC_TIMESTAMP_MS),
+                Some(RMC_TIMESTAMP_MS + 1000), // rolled over to 2024-04-01
+                Some(RMC_TIMESTAMP_MS + 2000),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_rmc_means_no_timestamps() {
+        let log = "$GPGGA,000000.00,4807.138,N,01131.100,E,1,09,0.8,546.0,M,46.9,M,,*63\n";
+        let (fixes, _) = parse(log);
+
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(fixes[0].timestamp_ms, None);
+    }
+
+    #[test]
+    fn test_bad_checksum_is_rejected() {
+        let log = "\
+$GPGGA,000000.00,4807.138,N,01131.100,E,1,09,0.8,546.0,M,46.9,M,,*00
+$GPGLL,4807.238,N,01131.200,E,000001.00,A,A*6A
+";
+        let (fixes, parser) = parse(log);
+
+        assert_eq!(parser.checksum_errors, 1);
+        assert_eq!(fixes.len(), 1);
+        assert!((fixes[0].lat - 48.12063).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_void_rmc_and_junk_prefix() {
+        let log = "\
+1711929599 $GPRMC,120000.00,V,,,,,,,010124,,*1A
+garbage line without a sentence
+2024-01-01T12:00:01 $GPGLL,4807.238,N,01131.200,E,000001.00,A,A*6A
+";
+        let (fixes, parser) = parse(log);
+
+        assert_eq!(parser.sentence_count, 2);
+        assert_eq!(fixes.len(), 1);
+    }
+
+    #[test]
+    fn test_non_ascii_time_field() {
+        // 6 bytes with a multibyte character inside the hours and minutes
+        let log = "$GPGGA,1é345,4807.138,N,01131.100,E,1,09,0.8,546.0,M,46.9,M,,\n";
+        let (fixes, _) = parse(log);
+
+        assert!(fixes.iter().all(|fix| fix.timestamp_ms.is_none()));
+    }
+
+    #[test]
+    fn test_parse_gps_coordinates_southern_western_hemisphere() {
+        let log = "$GPGLL,3351.600,S,15112.600,W,000001.00,A,A\n";
+        let mut parser = NmeaParser::new(log.as_bytes().to_vec());
+        let coords = parser.parse_gps_coordinates();
+
+        assert_eq!(coords.len(), 1);
+        assert!((coords[0][0] + 33.86).abs() < 1e-4);
+        assert!((coords[0][1] + 151.21).abs() < 1e-4);
+    }
+}
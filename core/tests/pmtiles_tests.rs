@@ -0,0 +1,276 @@
+#[cfg(test)]
+mod pmtiles_tests {
+    use fastgeotoolkit::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    // just enough of a PMTiles reader to look tiles up again
+    struct Archive {
+        bytes: Vec<u8>,
+    }
+
+    struct Entry {
+        tile_id: u64,
+        offset: u64,
+        length: u64,
+        run_length: u64,
+    }
+
+    fn gunzip(bytes: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        GzDecoder::new(bytes).read_to_end(&mut out).unwrap();
+        out
+    }
+
+    fn read_varint(bytes: &[u8], pos: &mut usize) -> u64 {
+        let (mut value, mut shift) = (0u64, 0);
+        loop {
+            let byte = bytes[*pos];
+            *pos += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte < 0x80 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+
+    impl Archive {
+        fn u64_at(&self, at: usize) -> u64 {
+            u64::from_le_bytes(self.bytes[at..at + 8].try_into().unwrap())
+        }
+
+        fn root(&self) -> (u64, u64) {
+            (self.u64_at(8), self.u64_at(16))
+        }
+
+        fn leaves_offset(&self) -> u64 {
+            self.u64_at(40)
+        }
+
+        fn data_offset(&self) -> u64 {
+            self.u64_at(56)
+        }
+
+        // addressed tiles, tile entries, tile contents
+        fn counts(&self) -> (u64, u64, u64) {
+            (self.u64_at(72), self.u64_at(80), self.u64_at(88))
+        }
+
+        fn metadata(&self) -> serde_json::Value {
+            let (offset, length) = (self.u64_at(24) as usize, self.u64_at(32) as usize);
+            serde_json::from_slice(&gunzip(&self.bytes[offset..offset + length])).unwrap()
+        }
+
+        fn directory(&self, offset: u64, length: u64) -> Vec<Entry> {
+            let bytes = gunzip(&self.bytes[offset as usize..(offset + length) as usize]);
+            let mut pos = 0;
+            let count = read_varint(&bytes, &mut pos) as usize;
+            let mut column = || {
+                (0..count)
+                    .map(|_| read_varint(&bytes, &mut pos))
+                    .collect::<Vec<_>>()
+            };
+            let (ids, runs, lengths, offsets) = (column(), column(), column(), column());
+
+            let mut entries: Vec<Entry> = Vec::new();
+            let mut tile_id = 0;
+            for i in 0..count {
+                tile_id += ids[i];
+                let offset = match (offsets[i], entries.last()) {
+                    (0, Some(previous)) => previous.offset + previous.length,
+                    (offset, _) => offset - 1,
+                };
+                entries.push(Entry {
+                    tile_id,
+                    offset,
+                    length: lengths[i],
+                    run_length: runs[i],
+                });
+            }
+            entries
+        }
+
+        fn tile(&self, z: u8, x: u32, y: u32) -> Option<Vec<u8>> {
+            let id = zxy_to_tile_id(z, x, y);
+            let (mut offset, mut length) = self.root();
+            loop {
+                let entries = self.directory(offset, length);
+                let entry = entries.iter().rev().find(|e| e.tile_id <= id)?;
+                if entry.run_length == 0 {
+                    offset = self.leaves_offset() + entry.offset;
+                    length = entry.length;
+                    continue;
+                }
+                if id >= entry.tile_id + entry.run_length {
+                    return None;
+                }
+                let start = (self.data_offset() + entry.offset) as usize;
+                return Some(self.bytes[start..start + entry.length as usize].to_vec());
+            }
+        }
+    }
+
+    fn london() -> HeatmapResult {
+        create_heatmap_from_tracks(vec![
+            vec![[51.50, -0.12], [51.51, -0.11]],
+            vec![[51.50, -0.12], [51.51, -0.11]],
+            vec![[51.40, -0.30], [51.45, -0.25]],
+        ])
+    }
+
+    #[test]
+    fn test_tile_ids_follow_the_hilbert_curve() {
+        assert_eq!(zxy_to_tile_id(0, 0, 0), 0);
+        assert_eq!(zxy_to_tile_id(1, 0, 0), 1);
+        assert_eq!(zxy_to_tile_id(1, 0, 1), 2);
+        assert_eq!(zxy_to_tile_id(1, 1, 1), 3);
+        assert_eq!(zxy_to_tile_id(1, 1, 0), 4);
+        assert_eq!(zxy_to_tile_id(2, 0, 0), 5);
+        assert_eq!(zxy_to_tile_id(12, 3423, 1763), 19078479);
+
+        for z in 0..=6u8 {
+            for x in 0..1u32 << z {
+                for y in 0..1u32 << z {
+                    assert_eq!(tile_id_to_zxy(zxy_to_tile_id(z, x, y)), (z, x, y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_vector_archive_round_trip() {
+        let mvt_options = MvtOptions {
+            min_zoom: 4,
+            max_zoom: 10,
+            ..MvtOptions::default()
+        };
+        let tiles = heatmap_mvt_tiles(&london(), &mvt_options);
+        let archive = Archive {
+            bytes: vector_tiles_to_pmtiles(&tiles, &mvt_options, &PmtilesOptions::default())
+                .unwrap(),
+        };
+
+        assert_eq!(&archive.bytes[0..8], b"PMTiles\x03");
+        // clustered, gzip directories, gzip tiles, mvt, zoom 4 to 10
+        assert_eq!(&archive.bytes[96..102], &[1, 2, 2, 1, 4, 10]);
+        assert_eq!(archive.counts().0, tiles.len() as u64);
+
+        for tile in &tiles {
+            let stored = archive.tile(tile.z, tile.x, tile.y).unwrap();
+            assert_eq!(gunzip(&stored), tile.data);
+        }
+        assert!(archive.tile(10, 0, 0).is_none());
+
+        let metadata = archive.metadata();
+        assert_eq!(metadata["name"], "heatmap");
+        assert_eq!(metadata["format"], "pbf");
+        assert_eq!(metadata["vector_layers"][0]["id"], "heatmap");
+
+        // bounds around London, in e7 degrees
+        let e7 = |at: usize| {
+            i32::from_le_bytes(archive.bytes[at..at + 4].try_into().unwrap()) as f64 / 1e7
+        };
+        let (min_lon, min_lat, max_lon, max_lat) = (e7(102), e7(106), e7(110), e7(114));
+        assert!(min_lon < -0.30 && max_lon > -0.11);
+        assert!(min_lat < 51.40 && max_lat > 51.51);
+        assert!(max_lon - min_lon < 1.0);
+    }
+
+    #[test]
+    fn test_identical_tiles_are_stored_once() {
+        let mut writer = PmtilesWriter::new(PmtilesTileType::Png);
+        for x in 0..4 {
+            for y in 0..4 {
+                writer.add_tile(2, x, y, b"same".to_vec());
+            }
+        }
+        writer.add_tile(0, 0, 0, b"other".to_vec());
+        assert_eq!(writer.tile_count(), 17);
+
+        let archive = Archive {
+            bytes: writer.finish(&PmtilesOptions::default()).unwrap(),
+        };
+        // the 16 z2 tiles are one run of the same data
+        assert_eq!(archive.counts(), (17, 2, 2));
+        assert_eq!(archive.tile(2, 3, 1).unwrap(), b"same");
+        assert_eq!(archive.tile(0, 0, 0).unwrap(), b"other");
+        assert_eq!(archive.bytes.len() as u64 - archive.data_offset(), 9);
+    }
+
+    #[test]
+    fn test_large_archives_use_leaf_directories() {
+        // scattered tiles of uneven size, so the directory doesn't compress away
+        let data = |x: u32, y: u32| {
+            let mut data = format!("{x}/{y}").into_bytes();
+            data.resize(data.len() + ((x * 7919 + y * 104729) % 97) as usize, b'.');
+            data
+        };
+        let mut writer = PmtilesWriter::new(PmtilesTileType::Png);
+        for x in (0..900u32).step_by(3) {
+            for y in (0..900u32).step_by(3) {
+                writer.add_tile(10, x, y, data(x, y));
+            }
+        }
+        let archive = Archive {
+            bytes: writer.finish(&PmtilesOptions::default()).unwrap(),
+        };
+
+        let (_, root_length) = archive.root();
+        assert!(127 + root_length <= 16_384);
+        assert!(archive.u64_at(48) > 0, "leaf directories were written");
+        for (x, y) in [(0, 0), (18, 897), (897, 3), (123, 45)] {
+            assert_eq!(archive.tile(10, x, y).unwrap(), data(x, y));
+        }
+        assert!(archive.tile(10, 1, 0).is_none());
+        assert!(archive.tile(10, 900, 0).is_none());
+    }
+
+    #[test]
+    fn test_raster_archive() {
+        let options = TileOptions {
+            min_zoom: 8,
+            max_zoom: 9,
+            tile_size: 64,
+            ..TileOptions::default()
+        };
+        let tiles = render_heatmap_tiles(&london(), &options);
+        let archive = Archive {
+            bytes: raster_tiles_to_pmtiles(
+                &tiles,
+                &PmtilesOptions {
+                    attribution: Some("me".to_string()),
+                    ..PmtilesOptions::default()
+                },
+            )
+            .unwrap(),
+        };
+
+        // uncompressed png tiles
+        assert_eq!(&archive.bytes[98..100], &[1, 2]);
+        for tile in &tiles {
+            assert_eq!(
+                archive.tile(tile.z, tile.x, tile.y).unwrap(),
+                tile.image.to_png().unwrap()
+            );
+        }
+        assert_eq!(archive.metadata()["format"], "png");
+        assert_eq!(archive.metadata()["attribution"], "me");
+    }
+
+    #[test]
+    fn test_streamed_raster_archive() {
+        let options = TileOptions {
+            min_zoom: 6,
+            max_zoom: 9,
+            tile_size: 64,
+            ..TileOptions::default()
+        };
+        let tiles = render_heatmap_tiles(&london(), &options);
+        let pmtiles_options = PmtilesOptions::default();
+        assert_eq!(
+            heatmap_to_pmtiles_raster(&london(), &options, &pmtiles_options).unwrap(),
+            raster_tiles_to_pmtiles(&tiles, &pmtiles_options).unwrap()
+        );
+    }
+}
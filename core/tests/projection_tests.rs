@@ -0,0 +1,95 @@
+#[cfg(test)]
+mod projection_tests {
+    use fastgeotoolkit::*;
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() <= tolerance
+    }
+
+    #[test]
+    fn test_mercator_known_values() {
+        let [x, y] = lat_lon_to_mercator([0.0, 0.0]);
+        assert!(close(x, 0.0, 1e-6) && close(y, 0.0, 1e-6));
+
+        let [x, y] = lat_lon_to_mercator([0.0, 180.0]);
+        assert!(close(x, 20_037_508.342789244, 1e-6) && close(y, 0.0, 1e-6));
+
+        // the corner of the square world
+        let [x, y] = lat_lon_to_mercator([MAX_MERCATOR_LAT, -180.0]);
+        assert!(close(x, -20_037_508.342789244, 1e-6));
+        assert!(close(y, 20_037_508.342789244, 1e-3));
+
+        // London, as given by EPSG transformations
+        let [x, y] = lat_lon_to_mercator([51.5074, -0.1278]);
+        assert!(close(x, -14_226.63, 0.01));
+        assert!(close(y, 6_711_542.47, 0.1));
+
+        // beyond the square it's clamped instead of running off to infinity
+        assert_eq!(
+            lat_lon_to_mercator([90.0, 0.0]),
+            lat_lon_to_mercator([MAX_MERCATOR_LAT, 0.0])
+        );
+    }
+
+    #[test]
+    fn test_round_trips() {
+        for point in [
+            [51.5074, -0.1278],
+            [-33.87, 151.21],
+            [0.0, 0.0],
+            [70.0, -179.9],
+        ] {
+            let [lat, lon] = mercator_to_lat_lon(lat_lon_to_mercator(point));
+            assert!(close(lat, point[0], 1e-9) && close(lon, point[1], 1e-9));
+
+            let [lat, lon] = pixel_to_lat_lon(lat_lon_to_pixel(point, 13.5, 512), 13.5, 512);
+            assert!(close(lat, point[0], 1e-9) && close(lon, point[1], 1e-9));
+        }
+    }
+
+    #[test]
+    fn test_pixels_and_tiles() {
+        // the world at zoom 0 is a single 256 pixel tile
+        assert_eq!(lat_lon_to_pixel([0.0, 0.0], 0.0, 256), [128.0, 128.0]);
+        assert_eq!(lat_lon_to_pixel([0.0, 0.0], 1.0, 256), [256.0, 256.0]);
+
+        assert_eq!(lat_lon_to_tile([51.5074, -0.1278], 0), (0, 0));
+        assert_eq!(lat_lon_to_tile([51.5074, -0.1278], 10), (511, 340));
+        assert_eq!(lat_lon_to_tile([-33.87, 151.21], 12), (3768, 2457));
+        // the far edges belong to the last tile
+        assert_eq!(lat_lon_to_tile([-90.0, 180.0], 2), (3, 3));
+
+        let [min_lat, min_lon, max_lat, max_lon] = tile_bounds(10, 511, 340);
+        assert!(min_lat <= 51.5074 && 51.5074 <= max_lat);
+        assert!(min_lon <= -0.1278 && -0.1278 <= max_lon);
+
+        let world = tile_bounds(0, 0, 0);
+        assert!(
+            close(world[0], -MAX_MERCATOR_LAT, 1e-9) && close(world[2], MAX_MERCATOR_LAT, 1e-9)
+        );
+        assert_eq!((world[1], world[3]), (-180.0, 180.0));
+    }
+
+    #[test]
+    fn test_tile_range_covers_a_viewport() {
+        let viewport = [51.28, -0.51, 51.69, 0.33];
+        let [min_x, min_y, max_x, max_y] = tile_range(viewport, 10);
+        assert_eq!([min_x, min_y, max_x, max_y], [510, 339, 512, 341]);
+
+        // the tiles at the range's corners hold the viewport's corners
+        let north_west = tile_bounds(10, min_x, min_y);
+        assert!(north_west[1] <= -0.51 && north_west[2] >= 51.69);
+        let south_east = tile_bounds(10, max_x, max_y);
+        assert!(south_east[3] >= 0.33 && south_east[0] <= 51.28);
+    }
+
+    #[test]
+    fn test_meters_per_pixel() {
+        // the well known 156 km per pixel of a 256 pixel world at the equator
+        assert!(close(meters_per_pixel(0.0, 0.0, 256), 156_543.03, 0.01));
+        assert!(close(meters_per_pixel(0.0, 1.0, 256), 78_271.52, 0.01));
+        assert!(close(meters_per_pixel(0.0, 0.0, 512), 78_271.52, 0.01));
+        // halved at 60 degrees north
+        assert!(close(meters_per_pixel(60.0, 10.0, 256), 152.87 / 2.0, 0.01));
+    }
+}
@@ -0,0 +1,199 @@
+#[cfg(test)]
+mod raster_tests {
+    use fastgeotoolkit::*;
+
+    fn options(width: u32, height: u32) -> RenderOptions {
+        RenderOptions {
+            width,
+            height,
+            bbox: Some([51.49, -0.11, 51.51, -0.09]),
+            ..RenderOptions::default()
+        }
+    }
+
+    // horizontal through the middle of the bbox, the other one crosses it vertically
+    fn east_west() -> Vec<[f64; 2]> {
+        vec![[51.5, -0.11], [51.5, -0.09]]
+    }
+
+    fn north_south() -> Vec<[f64; 2]> {
+        vec![[51.49, -0.1], [51.51, -0.1]]
+    }
+
+    #[test]
+    fn test_lines_are_drawn_inside_the_bbox() {
+        let image = render_tracks(&[east_west()], &options(64, 64));
+
+        assert_eq!((image.width, image.height), (64, 64));
+        assert_eq!(image.pixels.len(), 64 * 64 * 4);
+        assert!(image.pixel(10, 32)[3] > 0 || image.pixel(10, 31)[3] > 0);
+        assert_eq!(image.pixel(10, 5), [0, 0, 0, 0]);
+        assert_eq!(image.pixel(10, 60), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_overlap_is_additive() {
+        let ramp = RenderOptions {
+            color_ramp: vec![[0, 0, 0, 0], [255, 0, 0, 255]],
+            saturation: Some(2.0),
+            log_scale: false,
+            line_width: 4.0,
+            ..options(64, 64)
+        };
+        let image = render_tracks(&[east_west(), north_south()], &ramp);
+
+        // only one track at the edge, both where they cross
+        let single = image.pixel(5, 32);
+        let crossing = image.pixel(32, 32);
+        assert!(single[3] > 0);
+        assert!(crossing[3] > single[3]);
+        assert_eq!(crossing, [255, 0, 0, 255]);
+
+        // a track's own corners don't count twice
+        let corner = vec![[51.5, -0.11], [51.5, -0.1], [51.49, -0.1]];
+        let image = render_tracks(&[corner], &ramp);
+        assert!(image.pixel(32, 32)[3] <= 128);
+    }
+
+    #[test]
+    fn test_log_scale_keeps_single_tracks_visible() {
+        let mut tracks = vec![east_west(); 50];
+        tracks.push(north_south());
+
+        let log = render_tracks(&tracks, &options(64, 64));
+        let linear = render_tracks(
+            &tracks,
+            &RenderOptions {
+                log_scale: false,
+                ..options(64, 64)
+            },
+        );
+        assert!(log.pixel(32, 5)[3] > 100);
+        assert!(linear.pixel(32, 5)[3] < 20);
+    }
+
+    #[test]
+    fn test_anti_aliased_edges() {
+        let image = render_tracks(&[vec![[51.49, -0.11], [51.51, -0.095]]], &options(128, 128));
+
+        let mut alphas: Vec<u8> = image.pixels.chunks(4).map(|pixel| pixel[3]).collect();
+        alphas.sort_unstable();
+        alphas.dedup();
+        assert!(alphas.len() > 3, "edges blend instead of being on or off");
+    }
+
+    #[test]
+    fn test_heatmap_frequency_drives_color() {
+        let result = create_heatmap_from_tracks(vec![
+            east_west(),
+            east_west(),
+            east_west(),
+            vec![[51.495, -0.11], [51.495, -0.09]],
+        ]);
+        let image = render_heatmap(
+            &result,
+            &RenderOptions {
+                color_ramp: vec![[0, 0, 0, 255], [255, 255, 255, 255]],
+                line_width: 3.0,
+                ..options(64, 64)
+            },
+        );
+
+        // the quiet parallel street is a darker gray than the busy one
+        assert_eq!(image.pixel(32, 32), [255, 255, 255, 255]);
+        assert!((0..64)
+            .map(|y| image.pixel(32, y)[0])
+            .any(|v| v > 0 && v < 128));
+    }
+
+    #[test]
+    fn test_fitted_bbox_and_background() {
+        let image = render_tracks(
+            &[east_west()],
+            &RenderOptions {
+                width: 100,
+                height: 50,
+                padding: 10,
+                background: [10, 20, 30, 255],
+                ..RenderOptions::default()
+            },
+        );
+
+        // the line is fitted between the paddings and centered vertically
+        assert_eq!(image.pixel(5, 25), [10, 20, 30, 255]);
+        assert_ne!(image.pixel(50, 25), [10, 20, 30, 255]);
+        assert_eq!(image.pixel(95, 25), [10, 20, 30, 255]);
+
+        let empty = render_tracks(&[], &RenderOptions::default());
+        assert!(empty.pixels.iter().all(|&v| v == 0));
+    }
+
+    #[test]
+    fn test_tracks_around_the_globe() {
+        // the fitted bbox of these runs east from tokyo over the antimeridian to london
+        let tracks = vec![
+            vec![[34.05, -118.25], [34.06, -118.24]],
+            vec![[51.50, -0.12], [51.51, -0.11]],
+            vec![[35.68, 139.69], [35.69, 139.70]],
+        ];
+        let image = render_tracks(
+            &tracks,
+            &RenderOptions {
+                width: 200,
+                height: 100,
+                padding: 10,
+                ..RenderOptions::default()
+            },
+        );
+
+        let drawn = |columns: std::ops::Range<u32>| {
+            columns
+                .flat_map(|x| (0..100).map(move |y| (x, y)))
+                .any(|(x, y)| image.pixel(x, y)[3] > 0)
+        };
+        // tokyo at the west edge, los angeles in between, london at the east edge
+        assert!(drawn(5..20));
+        assert!(drawn(80..110));
+        assert!(drawn(180..195));
+        assert!(!drawn(30..70));
+
+        // a given bbox across the antimeridian is stretched the same way
+        let image = render_tracks(
+            &[vec![[-17.5, 179.5], [-17.5, -179.5]]],
+            &RenderOptions {
+                width: 64,
+                height: 64,
+                bbox: Some([-18.0, 179.0, -17.0, -179.0]),
+                ..RenderOptions::default()
+            },
+        );
+        for x in [16, 32, 48] {
+            assert!((28..36).any(|y| image.pixel(x, y)[3] > 0), "column {x}");
+        }
+        assert_eq!(image.pixel(4, 32), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_png_encoding() {
+        let image = render_tracks(&[east_west(), north_south()], &options(32, 16));
+        let png_bytes = image.to_png().unwrap();
+        assert!(png_bytes.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+        let decoder = png::Decoder::new(png_bytes.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+
+        assert_eq!((info.width, info.height), (32, 16));
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        assert_eq!(pixels, image.pixels);
+    }
+
+    #[test]
+    fn test_options_from_json() {
+        let options: RenderOptions =
+            serde_json::from_str(r#"{"width": 256, "color_ramp": [[255, 0, 0, 255]]}"#).unwrap();
+        assert_eq!((options.width, options.height), (256, 1024));
+        assert_eq!(options.color_ramp, vec![[255, 0, 0, 255]]);
+    }
+}
@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod strava_streams_tests {
+    use fastgeotoolkit::*;
+
+    const STREAMS_FIXTURE: &str = include_str!("fixtures/strava_streams.json");
+
+    // 2024-03-31T23:59:58Z
+    const START_TIME_MS: i64 = 1_711_929_598_000;
+
+    #[test]
+    fn test_key_by_type_streams() {
+        let points = parse_strava_streams(STREAMS_FIXTURE, None).unwrap();
+        assert_eq!(points.len(), 6, "every latlng sample should be kept");
+
+        let point = &points[3];
+        assert_eq!([point.lat, point.lon], [37.77561, -122.41829]);
+        assert_eq!(point.elapsed_seconds, Some(12));
+        assert_eq!(point.timestamp_ms, None);
+        assert_eq!(point.altitude, Some(14.1));
+        assert_eq!(point.distance, Some(124.8));
+        assert_eq!(point.heartrate, Some(131));
+        assert_eq!(point.velocity, Some(10.6));
+        assert_eq!(point.moving, Some(true));
+        assert_eq!(points[0].moving, Some(false));
+    }
+
+    #[test]
+    fn test_timestamps_from_activity_start() {
+        let points = parse_strava_streams(STREAMS_FIXTURE, Some(START_TIME_MS)).unwrap();
+
+        assert_eq!(points[0].timestamp_ms, Some(START_TIME_MS));
+        assert_eq!(points[5].timestamp_ms, Some(START_TIME_MS + 30_000));
+    }
+
+    #[test]
+    fn test_array_shaped_streams() {
+        let json = r#"[
+            {"type": "latlng", "data": [[40.7128, -74.0060], [40.7138, -74.0050]]},
+            {"type": "time", "data": [0, 5]},
+            {"type": "distance", "data": [0.0, 138.4]}
+        ]"#;
+        let points = parse_strava_streams(json, None).unwrap();
+
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[1].elapsed_seconds, Some(5));
+        assert_eq!(points[1].distance, Some(138.4));
+        assert_eq!(points[1].heartrate, None);
+    }
+
+    #[test]
+    fn test_streams_without_position() {
+        // indoor activities only have time and heartrate streams
+        let json = r#"{"time": {"data": [0, 1]}, "heartrate": {"data": [90, 91]}}"#;
+        assert!(parse_strava_streams(json, None).is_err());
+        assert!(parse_strava_streams("not json", None).is_err());
+        assert!(parse_strava_streams("42", None).is_err());
+    }
+
+    #[test]
+    fn test_streams_feed_the_heatmap() {
+        let points = parse_strava_streams(STREAMS_FIXTURE, None).unwrap();
+        let track: Vec<[f64; 2]> = points.iter().map(|p| [p.lat, p.lon]).collect();
+
+        let heatmap = create_heatmap_from_tracks(vec![track.clone(), track]);
+        assert_eq!(heatmap.tracks.len(), 2);
+        assert_eq!(heatmap.tracks[0].coordinates.len(), 6);
+        assert_eq!(heatmap.tracks[0].frequency, heatmap.tracks[1].frequency);
+        assert!(
+            heatmap.max_frequency >= 2,
+            "identical tracks should overlap"
+        );
+    }
+}
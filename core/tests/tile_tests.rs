@@ -0,0 +1,193 @@
+#[cfg(test)]
+mod tile_tests {
+    use fastgeotoolkit::*;
+
+    fn options(min_zoom: u8, max_zoom: u8) -> TileOptions {
+        TileOptions {
+            min_zoom,
+            max_zoom,
+            tile_size: 64,
+            ..TileOptions::default()
+        }
+    }
+
+    fn tile_ids(tiles: &[RasterTile]) -> Vec<(u8, u32, u32)> {
+        tiles.iter().map(|t| (t.z, t.x, t.y)).collect()
+    }
+
+    #[test]
+    fn test_tile_coordinates() {
+        // a few kilometers of Berlin
+        let track = vec![[52.50, 13.38], [52.53, 13.42]];
+        let tiles = render_track_tiles(&[track], &options(0, 3));
+
+        assert_eq!(
+            tile_ids(&tiles),
+            vec![(0, 0, 0), (1, 1, 0), (2, 2, 1), (3, 4, 2)]
+        );
+        for tile in &tiles {
+            assert_eq!((tile.image.width, tile.image.height), (64, 64));
+            assert!(tile.image.pixels.chunks(4).any(|p| p[3] > 0));
+        }
+    }
+
+    #[test]
+    fn test_track_across_tile_edges() {
+        // crosses the prime meridian and so the z1 tile boundary
+        let track = vec![[51.5, -0.5], [51.5, 0.5]];
+        let tiles = render_track_tiles(&[track], &options(1, 1));
+        assert_eq!(tile_ids(&tiles), vec![(1, 0, 0), (1, 1, 0)]);
+
+        // the halves meet at the shared edge
+        let (west, east) = (&tiles[0].image, &tiles[1].image);
+        let row = (0..64).find(|&y| west.pixel(63, y)[3] > 0).unwrap();
+        assert!(east.pixel(0, row)[3] > 0);
+    }
+
+    #[test]
+    fn test_empty_tiles_are_skipped() {
+        // a long diagonal, most tiles in its bounding box stay empty
+        let track = vec![[10.0, 10.0], [50.0, 60.0]];
+        let tiles = render_track_tiles(&[track], &options(6, 6));
+
+        let (xs, ys): (Vec<u32>, Vec<u32>) = tiles.iter().map(|t| (t.x, t.y)).unzip();
+        let bbox_tiles = (xs.iter().max().unwrap() - xs.iter().min().unwrap() + 1)
+            * (ys.iter().max().unwrap() - ys.iter().min().unwrap() + 1);
+        assert!(tiles.len() < bbox_tiles as usize / 3);
+        assert!(tiles
+            .iter()
+            .all(|tile| tile.image.pixels.chunks(4).any(|p| p[3] > 0)));
+
+        assert!(render_track_tiles(&[], &options(0, 4)).is_empty());
+    }
+
+    #[test]
+    fn test_every_zoom_is_normalized_on_its_own() {
+        let result = create_heatmap_from_tracks(vec![
+            vec![[51.50, -0.12], [51.51, -0.11]],
+            vec![[51.50, -0.12], [51.51, -0.11]],
+            vec![[51.40, -0.30], [51.45, -0.25]],
+        ]);
+        let tiles = render_heatmap_tiles(&result, &options(2, 8));
+        let top = *TileOptions::default().render.color_ramp.last().unwrap();
+
+        for zoom in 2..=8 {
+            let level: Vec<_> = tiles.iter().filter(|t| t.z == zoom).collect();
+            assert!(!level.is_empty());
+            assert!(
+                level
+                    .iter()
+                    .any(|t| t.image.pixels.chunks(4).any(|p| p == top)),
+                "zoom {zoom} reaches the end of the ramp"
+            );
+        }
+    }
+
+    #[test]
+    fn test_line_width_grows_with_zoom() {
+        let track = vec![[0.0, -60.0], [0.0, 60.0]];
+        let options = TileOptions {
+            min_line_width: 1.0,
+            render: RenderOptions {
+                line_width: 9.0,
+                ..RenderOptions::default()
+            },
+            ..options(3, 5)
+        };
+        let tiles = render_track_tiles(&[track], &options);
+
+        // the equator is the edge between two rows of tiles, count both
+        let thickness = |zoom: u8| {
+            tiles
+                .iter()
+                .filter(|t| t.z == zoom && t.x == (1 << (zoom - 1)) - 1)
+                .map(|t| (0..64).filter(|&y| t.image.pixel(10, y)[3] > 0).count())
+                .sum::<usize>()
+        };
+        assert!(thickness(3) < thickness(4));
+        assert!(thickness(4) < thickness(5));
+    }
+
+    #[test]
+    fn test_tiles_are_emitted_one_at_a_time() {
+        let track = vec![[52.50, 13.37], [52.52, 13.42], [52.53, 13.45]];
+        let tracks = vec![track];
+        let all = render_track_tiles(&tracks, &options(4, 10));
+
+        let mut emitted = Vec::new();
+        for_each_track_tile(&tracks, &options(4, 10), |tile| {
+            emitted.push((tile.z, tile.x, tile.y, tile.image.to_png()?));
+            Ok::<_, Error>(())
+        })
+        .unwrap();
+        assert_eq!(
+            emitted.iter().map(|t| (t.0, t.1, t.2)).collect::<Vec<_>>(),
+            tile_ids(&all)
+        );
+
+        // a fixed saturation emits straight away, an error stops rendering
+        let fixed = TileOptions {
+            render: RenderOptions {
+                saturation: Some(1.0),
+                ..RenderOptions::default()
+            },
+            ..options(4, 10)
+        };
+        let mut count = 0;
+        let stopped = for_each_track_tile(&tracks, &fixed, |_| {
+            count += 1;
+            if count == 3 {
+                Err("enough")
+            } else {
+                Ok(())
+            }
+        });
+        assert_eq!(stopped, Err("enough"));
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_track_across_the_antimeridian() {
+        // Fiji, a short step east over ±180 rather than back around the world
+        let track = vec![[-17.0, 179.8], [-17.0, -179.8]];
+        let tiles = render_track_tiles(&[track], &options(3, 3));
+        assert_eq!(tile_ids(&tiles), vec![(3, 0, 4), (3, 7, 4)]);
+
+        // the halves meet at the world's edge
+        let (east, west) = (&tiles[0].image, &tiles[1].image);
+        let row = (0..64).find(|&y| west.pixel(63, y)[3] > 0).unwrap();
+        assert!(east.pixel(0, row)[3] > 0);
+        assert!(west.pixel(0, row)[3] == 0 && east.pixel(63, row)[3] == 0);
+    }
+
+    #[test]
+    fn test_track_returning_to_a_tile_counts_once() {
+        // out over the z6 tile east of Greenwich, away beyond it and back the same way
+        let there = vec![[51.5, -0.5], [51.5, 6.0]];
+        let there_and_back = vec![
+            [51.5, -0.5],
+            [51.5, 6.0],
+            [55.0, 10.0],
+            [51.5, 6.0],
+            [51.5, -0.5],
+        ];
+        let options = TileOptions {
+            render: RenderOptions {
+                saturation: Some(2.0),
+                ..RenderOptions::default()
+            },
+            ..options(6, 6)
+        };
+
+        let tile = |track: Vec<[f64; 2]>| {
+            render_track_tiles(&[track], &options)
+                .into_iter()
+                .find(|t| t.x == 32)
+                .unwrap()
+        };
+        let (once, twice) = (tile(there), tile(there_and_back));
+        assert_eq!((once.x, once.y), (twice.x, twice.y));
+        assert!(once.image.pixels.chunks(4).any(|p| p[3] > 0));
+        assert_eq!(once.image.pixels, twice.image.pixels);
+    }
+}
@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod weighted_heatmap_tests {
+    use fastgeotoolkit::*;
+
+    const DAY_MS: i64 = 86_400_000;
+    const NOW_MS: i64 = 1_735_689_600_000; // 2025-01-01
+
+    fn street() -> Vec<[f64; 2]> {
+        vec![[51.5000, -0.1000], [51.5050, -0.1000], [51.5100, -0.1000]]
+    }
+
+    fn other_street() -> Vec<[f64; 2]> {
+        vec![[51.5200, -0.1200], [51.5200, -0.1100]]
+    }
+
+    fn decaying() -> HeatmapOptions {
+        HeatmapOptions {
+            recency_decay: Some(RecencyDecay {
+                half_life_days: 30.0,
+                reference_ms: NOW_MS,
+            }),
+            ..HeatmapOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_weights_scale_counts() {
+        let mut builder = HeatmapBuilder::new();
+        builder.add_weighted_activity(vec![street()], 3.0, None);
+        builder.add_weighted_activity(vec![street()], 1.0, None);
+
+        assert_eq!(builder.build().max_frequency, 4);
+        let segment = &builder.build_segments().segments[0];
+        assert_eq!((segment.frequency, segment.passes), (4, 2));
+        assert_eq!(segment.weight, 4.0);
+
+        // negative weights count as nothing, frequency keeps its minimum
+        let mut ignored = HeatmapBuilder::new();
+        ignored.add_weighted_activity(vec![street()], -2.0, None);
+        assert_eq!(ignored.build().max_frequency, 1);
+        assert_eq!(ignored.build_segments().segments[0].weight, 0.0);
+    }
+
+    #[test]
+    fn test_recency_decay() {
+        let decay = decaying().recency_decay.unwrap();
+        assert_eq!(decay.factor(NOW_MS), 1.0);
+        assert!((decay.factor(NOW_MS - 30 * DAY_MS) - 0.5).abs() < 1e-12);
+        assert_eq!(decay.factor(NOW_MS + DAY_MS), 1.0);
+
+        // ridden daily two months ago, once today
+        let mut builder = HeatmapBuilder::with_options(decaying());
+        for day in 60..66 {
+            builder.add_weighted_activity(vec![street()], 1.0, Some(NOW_MS - day * DAY_MS));
+        }
+        builder.add_weighted_activity(vec![other_street()], 1.0, Some(NOW_MS));
+
+        let result = builder.build();
+        assert_eq!(result.tracks[0].frequency, 1);
+        assert_eq!(result.tracks[6].frequency, 1);
+
+        let segments = builder.build_segments().segments;
+        let old: f64 = (60..66).map(|day| 0.5f64.powf(day as f64 / 30.0)).sum();
+        assert!((segments[0].weight - old).abs() < 1e-9);
+        assert_eq!(segments[0].passes, 6);
+        assert_eq!(segments.last().unwrap().weight, 1.0);
+
+        // without decay the old habit dominates
+        let mut all_time = HeatmapBuilder::new();
+        all_time.add_tracks(vec![street(); 6]);
+        all_time.add_track(other_street());
+        assert_eq!(all_time.build().tracks[0].frequency, 6);
+    }
+
+    #[test]
+    fn test_invalid_half_life_does_not_decay() {
+        for half_life_days in [0.0, -30.0, f64::NAN] {
+            let decay = RecencyDecay {
+                half_life_days,
+                reference_ms: NOW_MS,
+            };
+            assert_eq!(decay.factor(NOW_MS), 1.0);
+            assert_eq!(decay.factor(NOW_MS - 30 * DAY_MS), 1.0);
+
+            let mut builder = HeatmapBuilder::with_options(HeatmapOptions {
+                recency_decay: Some(decay),
+                ..HeatmapOptions::default()
+            });
+            builder.add_weighted_activity(vec![street()], 1.0, Some(NOW_MS - DAY_MS));
+            let weight = builder.build_segments().segments[0].weight;
+            assert_eq!(weight, 1.0);
+        }
+    }
+
+    #[test]
+    fn test_undated_activities_are_not_decayed() {
+        let mut builder = HeatmapBuilder::with_options(decaying());
+        builder.add_weighted_activity(vec![street()], 2.0, None);
+        assert_eq!(builder.build_segments().segments[0].weight, 2.0);
+    }
+
+    #[test]
+    fn test_merge_recounts_with_original_weights() {
+        let dated = |days: i64| Some(NOW_MS - days * DAY_MS);
+
+        let mut builder = HeatmapBuilder::with_options(decaying());
+        builder.add_weighted_activity(vec![street()], 2.0, dated(10));
+
+        let mut other = HeatmapBuilder::new();
+        other.add_weighted_activity(vec![street(), other_street()], 0.5, dated(90));
+        builder.merge(other);
+
+        let mut single = HeatmapBuilder::with_options(decaying());
+        single.add_weighted_activity(vec![street()], 2.0, dated(10));
+        single.add_weighted_activity(vec![street(), other_street()], 0.5, dated(90));
+
+        assert_eq!(builder, single);
+    }
+
+    #[test]
+    fn test_weighted_activities_from_json() {
+        let json = format!(
+            r#"[
+                {{"tracks": [[[51.5, -0.1], [51.51, -0.1]]], "weight": 2.5}},
+                {{"tracks": [[[51.5, -0.1], [51.51, -0.1]]], "timestamp_ms": {}}}
+            ]"#,
+            NOW_MS - 30 * DAY_MS
+        );
+        let activities: Vec<WeightedActivity> = serde_json::from_str(&json).unwrap();
+        assert_eq!(activities[1].weight, 1.0);
+
+        let result = process_weighted_activities_by_segment_rust(activities.clone(), &decaying());
+        assert_eq!(result.segments[0].weight, 3.0);
+        assert_eq!(result.max_frequency, 3);
+
+        let result = process_weighted_activities_rust(activities, &HeatmapOptions::default());
+        assert_eq!(result.max_frequency, 4); // 3.5 rounds up
+    }
+}
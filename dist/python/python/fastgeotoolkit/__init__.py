@@ -8,6 +8,8 @@ from ._fastgeotoolkit import (
     process_gpx_files,
     decode_polyline,
     process_polylines,
+    create_heatmap,
+    HeatmapOptions,
     
     # Analysis functions
     calculate_track_statistics,
@@ -64,6 +66,8 @@ __all__ = [
     "process_gpx_files",
     "decode_polyline",
     "process_polylines",
+    "create_heatmap",
+    "HeatmapOptions",
     
     # Analysis functions
     "calculate_track_statistics",
@@ -7,6 +7,7 @@ use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
 
 use pyo3::prelude::*;
+use pyo3::exceptions::PyValueError;
 use pyo3::wrap_pyfunction;
 use pyo3::types::{PyList, PyDict};
 
@@ -23,6 +24,88 @@ pub struct HeatmapResult {
     pub max_frequency: u32,
 }
 
+// knobs for the heatmap pipeline, the defaults are the values it always used
+#[pyclass]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct HeatmapOptions {
+    #[pyo3(get)]
+    pub grid_tolerance: f64, // degrees, segment ends are snapped to this grid for matching
+    #[pyo3(get, set)]
+    pub simplify_tolerance: f64, // degrees, points closer than this to the last kept one are dropped
+    #[pyo3(get)]
+    pub coordinate_precision: u32, // decimals kept when a track is prepared
+    #[pyo3(get, set)]
+    pub max_jump_km: f64, // larger jumps between points are treated as GPS glitches
+}
+
+impl Default for HeatmapOptions {
+    fn default() -> Self {
+        Self {
+            grid_tolerance: 0.001, // About 100 meters
+            simplify_tolerance: 0.00005,
+            coordinate_precision: 5,
+            max_jump_km: 100.0,
+        }
+    }
+}
+
+// degrees, about 1 cm. finer grids push the snapping arithmetic past what f64 and the key
+// decimals can hold, the same floor the core crate applies
+pub const MIN_GRID_TOLERANCE: f64 = 1e-7;
+// beyond this 10^precision stops being exact and rounding turns coordinates into noise
+pub const MAX_COORDINATE_PRECISION: u32 = 12;
+
+// the constructor and setters raise these as ValueError rather than produce NaN coordinates
+pub fn check_grid_tolerance(value: f64) -> Result<f64, String> {
+    if value.is_finite() && value >= MIN_GRID_TOLERANCE {
+        Ok(value)
+    } else {
+        Err(format!("grid_tolerance must be a number of at least {MIN_GRID_TOLERANCE} degrees, got {value}"))
+    }
+}
+
+pub fn check_coordinate_precision(value: u32) -> Result<u32, String> {
+    if value <= MAX_COORDINATE_PRECISION {
+        Ok(value)
+    } else {
+        Err(format!("coordinate_precision must be at most {MAX_COORDINATE_PRECISION} decimals, got {value}"))
+    }
+}
+
+#[pymethods]
+impl HeatmapOptions {
+    #[new]
+    #[pyo3(signature = (grid_tolerance=0.001, simplify_tolerance=0.00005, coordinate_precision=5, max_jump_km=100.0))]
+    fn py_new(grid_tolerance: f64, simplify_tolerance: f64, coordinate_precision: u32, max_jump_km: f64) -> PyResult<Self> {
+        Ok(Self {
+            grid_tolerance: check_grid_tolerance(grid_tolerance).map_err(PyValueError::new_err)?,
+            simplify_tolerance,
+            coordinate_precision: check_coordinate_precision(coordinate_precision).map_err(PyValueError::new_err)?,
+            max_jump_km,
+        })
+    }
+
+    #[setter]
+    fn set_grid_tolerance(&mut self, value: f64) -> PyResult<()> {
+        self.grid_tolerance = check_grid_tolerance(value).map_err(PyValueError::new_err)?;
+        Ok(())
+    }
+
+    #[setter]
+    fn set_coordinate_precision(&mut self, value: u32) -> PyResult<()> {
+        self.coordinate_precision = check_coordinate_precision(value).map_err(PyValueError::new_err)?;
+        Ok(())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "HeatmapOptions(grid_tolerance={}, simplify_tolerance={}, coordinate_precision={}, max_jump_km={})",
+            self.grid_tolerance, self.simplify_tolerance, self.coordinate_precision, self.max_jump_km
+        )
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ValidationResult {
     valid_count: u32,
@@ -181,6 +264,35 @@ pub fn process_polylines(polylines: js_sys::Array) -> JsValue {
 
 // Helper function to create heatmap from coordinate arrays
 pub fn create_heatmap_from_tracks(all_tracks: Vec<Vec<[f64; 2]>>) -> HeatmapResult {
+    create_heatmap_from_tracks_with_options(all_tracks, &HeatmapOptions::default())
+}
+
+// jump filtering, rounding and simplification followed by counting, all driven by options
+pub fn process_tracks_with_options(tracks: Vec<Vec<[f64; 2]>>, options: &HeatmapOptions) -> HeatmapResult {
+    let prepared = tracks.iter()
+        .filter_map(|track| prepare_heatmap_track(track, options))
+        .collect();
+    create_heatmap_from_tracks_with_options(prepared, options)
+}
+
+fn prepare_heatmap_track(coords: &[[f64; 2]], options: &HeatmapOptions) -> Option<Vec<[f64; 2]>> {
+    let filtered: Vec<[f64; 2]> = filter_unrealistic_jumps_with(coords, options.max_jump_km)
+        .into_iter()
+        .map(|[lat, lon]| [round_to(lat, options.coordinate_precision), round_to(lon, options.coordinate_precision)])
+        .collect();
+    if filtered.len() < 2 {
+        return None;
+    }
+
+    let simplified = simplify_track(&filtered, options.simplify_tolerance);
+    if simplified.len() > 1 {
+        Some(simplified)
+    } else {
+        None
+    }
+}
+
+pub fn create_heatmap_from_tracks_with_options(all_tracks: Vec<Vec<[f64; 2]>>, options: &HeatmapOptions) -> HeatmapResult {
     // Create a segment usage map to count overlapping segments
     let mut segment_usage: HashMap<String, u32> = HashMap::new();
     
@@ -188,7 +300,7 @@ pub fn create_heatmap_from_tracks(all_tracks: Vec<Vec<[f64; 2]>>) -> HeatmapResu
     for track in &all_tracks {
         for window in track.windows(2) {
             if let [start, end] = window {
-                let segment_key = create_segment_key(*start, *end);
+                let segment_key = segment_key(*start, *end, options.grid_tolerance);
                 *segment_usage.entry(segment_key).or_insert(0) += 1;
             }
         }
@@ -208,7 +320,7 @@ pub fn create_heatmap_from_tracks(all_tracks: Vec<Vec<[f64; 2]>>) -> HeatmapResu
         
         for window in track.windows(2) {
             if let [start, end] = window {
-                let segment_key = create_segment_key(*start, *end);
+                let segment_key = segment_key(*start, *end, options.grid_tolerance);
                 if let Some(&usage) = segment_usage.get(&segment_key) {
                     total_usage += usage;
                     segment_count += 1;
@@ -242,12 +354,18 @@ pub fn create_heatmap_from_tracks(all_tracks: Vec<Vec<[f64; 2]>>) -> HeatmapResu
 }
 
 pub fn round(value: f64) -> f64 {
-    (value * 100000.0).round() / 100000.0
+    round_to(value, 5)
+}
+
+pub fn round_to(value: f64, decimals: u32) -> f64 {
+    let factor = 10f64.powi(decimals as i32);
+    (value * factor).round() / factor
 }
 
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub fn process_gpx_files(files: js_sys::Array) -> JsValue {
+    let options = HeatmapOptions::default();
     let mut all_tracks: Vec<Vec<[f64; 2]>> = Vec::new();
     
     // Parse all GPX and FIT files and extract tracks
@@ -317,7 +435,7 @@ pub fn process_gpx_files(files: js_sys::Array) -> JsValue {
     for track in &all_tracks {
         for window in track.windows(2) {
             if let [start, end] = window {
-                let segment_key = create_segment_key(*start, *end);
+                let segment_key = segment_key(*start, *end, options.grid_tolerance);
                 *segment_usage.entry(segment_key).or_insert(0) += 1;
             }
         }
@@ -338,7 +456,7 @@ pub fn process_gpx_files(files: js_sys::Array) -> JsValue {
         
         for window in track.windows(2) {
             if let [start, end] = window {
-                let segment_key = create_segment_key(*start, *end);
+                let segment_key = segment_key(*start, *end, options.grid_tolerance);
                 if let Some(&usage) = segment_usage.get(&segment_key) {
                     total_usage += usage;
                     segment_count += 1;
@@ -370,8 +488,10 @@ pub fn process_gpx_files(files: js_sys::Array) -> JsValue {
 }
 
 pub fn create_segment_key(start: [f64; 2], end: [f64; 2]) -> String {
-    // change to a larger tolerance for less aggressive matching
-    let tolerance = 0.001; // About 100 meters
+    segment_key(start, end, HeatmapOptions::default().grid_tolerance)
+}
+
+pub fn segment_key(start: [f64; 2], end: [f64; 2], tolerance: f64) -> String {
     let snap_start = snap_to_grid(start, tolerance);
     let snap_end = snap_to_grid(end, tolerance);
     
@@ -382,7 +502,9 @@ pub fn create_segment_key(start: [f64; 2], end: [f64; 2]) -> String {
         (snap_end, snap_start)
     };
     
-    format!("{:.4},{:.4}-{:.4},{:.4}", p1[0], p1[1], p2[0], p2[1])
+    // one decimal more than the grid resolution so neighbouring cells never print the same
+    let decimals = ((-tolerance.log10()).ceil().max(0.0) as usize + 1).max(4);
+    format!("{:.*},{:.*}-{:.*},{:.*}", decimals, p1[0], decimals, p1[1], decimals, p2[0], decimals, p2[1])
 }
 
 pub fn snap_to_grid(point: [f64; 2], tolerance: f64) -> [f64; 2] {
@@ -435,12 +557,15 @@ fn is_valid_coordinate(lat: f64, lon: f64) -> bool {
 }
 
 pub fn filter_unrealistic_jumps(coords: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    filter_unrealistic_jumps_with(coords, HeatmapOptions::default().max_jump_km)
+}
+
+pub fn filter_unrealistic_jumps_with(coords: &[[f64; 2]], max_jump_km: f64) -> Vec<[f64; 2]> {
     if coords.len() <= 1 {
         return coords.to_vec();
     }
     
     let mut filtered = vec![coords[0]];
-    let max_jump_km = 100.0;
     let mut consecutive_bad_points = 0;
     const MAX_CONSECUTIVE_BAD: usize = 10; // Allow up to 10 consecutive bad points
     
@@ -1877,6 +2002,28 @@ fn py_export_to_gpx(tracks: Vec<Vec<[f64; 2]>>) -> String {
     export_to_gpx_rust(&tracks)
 }
 
+/// Build a route density heatmap, returns ([(coordinates, frequency)], max_frequency)
+#[pyfunction]
+#[pyo3(name = "create_heatmap", signature = (tracks, options=None))]
+fn py_create_heatmap(tracks: Vec<Vec<[f64; 2]>>, options: Option<HeatmapOptions>) -> (Vec<(Vec<[f64; 2]>, u32)>, u32) {
+    let result = process_tracks_with_options(tracks, &options.unwrap_or_default());
+    let tracks = result.tracks.into_iter().map(|track| (track.coordinates, track.frequency)).collect();
+    (tracks, result.max_frequency)
+}
+
+/// Build a heatmap from encoded or JSON polylines
+#[pyfunction]
+#[pyo3(name = "process_polylines", signature = (polylines, options=None))]
+fn py_process_polylines(polylines: Vec<String>, options: Option<HeatmapOptions>) -> (Vec<(Vec<[f64; 2]>, u32)>, u32) {
+    let tracks = polylines.iter()
+        .map(|polyline| match serde_json::from_str::<Vec<[f64; 2]>>(polyline) {
+            Ok(coords) => coords,
+            Err(_) => decode_polyline(polyline),
+        })
+        .collect();
+    py_create_heatmap(tracks, options)
+}
+
 /// PyO3 module definition
 #[pymodule]
 fn fastgeotoolkit(_py: Python, m: &PyModule) -> PyResult<()> {
@@ -1896,5 +2043,8 @@ fn fastgeotoolkit(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(py_cluster_tracks_by_similarity, m)?)?;
     m.add_function(wrap_pyfunction!(py_resample_track, m)?)?;
     m.add_function(wrap_pyfunction!(py_export_to_gpx, m)?)?;
+    m.add_function(wrap_pyfunction!(py_create_heatmap, m)?)?;
+    m.add_function(wrap_pyfunction!(py_process_polylines, m)?)?;
+    m.add_class::<HeatmapOptions>()?;
     Ok(())
 }
\ No newline at end of file
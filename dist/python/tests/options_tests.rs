@@ -0,0 +1,32 @@
+#[cfg(test)]
+mod options_tests {
+    use fastgeotoolkit::*;
+
+    #[test]
+    fn test_grid_tolerance_is_checked() {
+        assert_eq!(check_grid_tolerance(0.001).unwrap(), 0.001);
+        assert_eq!(
+            check_grid_tolerance(MIN_GRID_TOLERANCE).unwrap(),
+            MIN_GRID_TOLERANCE
+        );
+        for tolerance in [0.0, -1.0, 1e-12, f64::NAN, f64::INFINITY] {
+            assert!(check_grid_tolerance(tolerance).is_err(), "{tolerance}");
+        }
+    }
+
+    #[test]
+    fn test_coordinate_precision_is_checked() {
+        assert_eq!(check_coordinate_precision(5).unwrap(), 5);
+        assert_eq!(
+            check_coordinate_precision(MAX_COORDINATE_PRECISION).unwrap(),
+            MAX_COORDINATE_PRECISION
+        );
+        assert!(check_coordinate_precision(400).is_err());
+
+        // the largest allowed precision still rounds to a coordinate, not NaN
+        let rounded = round_to(51.123456789012345, MAX_COORDINATE_PRECISION);
+        assert!((rounded - 51.123456789012).abs() < 1e-9);
+        let key = segment_key([51.5, -0.1], [51.501, -0.1], MIN_GRID_TOLERANCE);
+        assert!(!key.contains("NaN"));
+    }
+}